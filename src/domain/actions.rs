@@ -0,0 +1,929 @@
+//! Output Actions
+//!
+//! Platform independent description of the input the application injects
+//! into the system. Bindings resolve controller events to these actions and
+//! [`ActionExecutor`] carries them out on an [`InputBackend`].
+//!
+//! Actions also have a compact human-readable form used in the UI, e.g.
+//! `ctrl+alt+t`, `click:middle`, `scroll:-3`, `text:Hello`,
+//! `mode:presentation`, `run:notepad.exe`, `media:seek+10`, `macro:Paste`,
+//! `record`, `cancel` or a sequence such as `ctrl+c; wait:100; ctrl+v`.
+//! A literal `;` in text, commands and macro names is written `;;`.
+
+use crate::domain::media::MediaCommand;
+use crate::domain::models::ControlMode;
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    X1,
+    X2,
+}
+
+impl MouseButton {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MouseButton::Left => "left",
+            MouseButton::Right => "right",
+            MouseButton::Middle => "middle",
+            MouseButton::X1 => "x1",
+            MouseButton::X2 => "x2",
+        }
+    }
+}
+
+impl FromStr for MouseButton {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "left" => Ok(MouseButton::Left),
+            "right" => Ok(MouseButton::Right),
+            "middle" => Ok(MouseButton::Middle),
+            "x1" | "back" => Ok(MouseButton::X1),
+            "x2" | "forward" => Ok(MouseButton::X2),
+            other => bail!("Unknown mouse button '{}'", other),
+        }
+    }
+}
+
+/// Keys that can be sent by an action
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Key {
+    /// Letter `a`-`z` or digit `0`-`9` (stored lowercase)
+    Char(char),
+    /// Function key F1-F24
+    F(u8),
+    Enter,
+    Tab,
+    Escape,
+    Space,
+    Backspace,
+    Delete,
+    Insert,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Left,
+    Right,
+    Up,
+    Down,
+    Ctrl,
+    Shift,
+    Alt,
+    Win,
+    CapsLock,
+    PrintScreen,
+    VolumeUp,
+    VolumeDown,
+    VolumeMute,
+    MediaPlayPause,
+    MediaNext,
+    MediaPrevious,
+    MediaStop,
+}
+
+/// Canonical key names, used for both parsing and display
+const KEY_NAMES: &[(&str, Key)] = &[
+    ("enter", Key::Enter),
+    ("tab", Key::Tab),
+    ("esc", Key::Escape),
+    ("space", Key::Space),
+    ("backspace", Key::Backspace),
+    ("delete", Key::Delete),
+    ("insert", Key::Insert),
+    ("home", Key::Home),
+    ("end", Key::End),
+    ("pageup", Key::PageUp),
+    ("pagedown", Key::PageDown),
+    ("left", Key::Left),
+    ("right", Key::Right),
+    ("up", Key::Up),
+    ("down", Key::Down),
+    ("ctrl", Key::Ctrl),
+    ("shift", Key::Shift),
+    ("alt", Key::Alt),
+    ("win", Key::Win),
+    ("capslock", Key::CapsLock),
+    ("printscreen", Key::PrintScreen),
+    ("volumeup", Key::VolumeUp),
+    ("volumedown", Key::VolumeDown),
+    ("mute", Key::VolumeMute),
+    ("playpause", Key::MediaPlayPause),
+    ("nexttrack", Key::MediaNext),
+    ("prevtrack", Key::MediaPrevious),
+    ("stop", Key::MediaStop),
+];
+
+/// Alternative spellings accepted by the parser
+const KEY_ALIASES: &[(&str, Key)] = &[
+    ("return", Key::Enter),
+    ("escape", Key::Escape),
+    ("del", Key::Delete),
+    ("ins", Key::Insert),
+    ("pgup", Key::PageUp),
+    ("pgdn", Key::PageDown),
+    ("control", Key::Ctrl),
+    ("super", Key::Win),
+    ("meta", Key::Win),
+    ("cmd", Key::Win),
+    ("prtsc", Key::PrintScreen),
+    ("volumemute", Key::VolumeMute),
+    ("play", Key::MediaPlayPause),
+    ("next", Key::MediaNext),
+    ("prev", Key::MediaPrevious),
+    ("previous", Key::MediaPrevious),
+];
+
+impl Key {
+    /// Whether the key acts as a modifier in a chord
+    pub fn is_modifier(&self) -> bool {
+        matches!(self, Key::Ctrl | Key::Shift | Key::Alt | Key::Win)
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Key::Char(c) => write!(f, "{}", c),
+            Key::F(n) => write!(f, "f{}", n),
+            key => {
+                let name = KEY_NAMES
+                    .iter()
+                    .find(|(_, k)| k == key)
+                    .map(|(name, _)| *name)
+                    .unwrap_or("?");
+                f.write_str(name)
+            }
+        }
+    }
+}
+
+impl FromStr for Key {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase();
+
+        let mut chars = name.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            if c.is_ascii_alphanumeric() {
+                return Ok(Key::Char(c));
+            }
+        }
+
+        if let Some(n) = name.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+            if (1..=24).contains(&n) {
+                return Ok(Key::F(n));
+            }
+        }
+
+        KEY_NAMES
+            .iter()
+            .chain(KEY_ALIASES)
+            .find(|(n, _)| *n == name)
+            .map(|(_, key)| *key)
+            .ok_or_else(|| anyhow!("Unknown key '{}'", s.trim()))
+    }
+}
+
+/// A key with optional modifiers, e.g. `ctrl+shift+tab`
+///
+/// A chord consisting only of modifiers (e.g. `alt`) uses the last one as
+/// its key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct KeyChord {
+    pub modifiers: Vec<Key>,
+    pub key: Key,
+}
+
+impl KeyChord {
+    pub fn new(modifiers: Vec<Key>, key: Key) -> Self {
+        Self { modifiers, key }
+    }
+
+    /// All keys in press order (modifiers first)
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = Key> + '_ {
+        self.modifiers
+            .iter()
+            .copied()
+            .chain(std::iter::once(self.key))
+    }
+}
+
+impl From<Key> for KeyChord {
+    fn from(key: Key) -> Self {
+        Self::new(Vec::new(), key)
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for modifier in &self.modifiers {
+            write!(f, "{}+", modifier)?;
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut keys = s
+            .split('+')
+            .map(Key::from_str)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let key = keys.pop().ok_or_else(|| anyhow!("Empty key chord"))?;
+
+        if let Some(bad) = keys.iter().find(|k| !k.is_modifier()) {
+            bail!("'{}' is not a modifier in '{}'", bad, s.trim());
+        }
+
+        Ok(Self::new(keys, key))
+    }
+}
+
+impl TryFrom<String> for KeyChord {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<KeyChord> for String {
+    fn from(chord: KeyChord) -> Self {
+        chord.to_string()
+    }
+}
+
+/// A single injected input, or a sequence of them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Action {
+    /// Press and release a key chord
+    KeyPress(KeyChord),
+    /// Press a key chord and keep it held until the matching `KeyUp`
+    KeyDown(KeyChord),
+    KeyUp(KeyChord),
+    MouseClick(MouseButton),
+    MouseDown(MouseButton),
+    MouseUp(MouseButton),
+    /// Vertical wheel notches (positive = up)
+    Scroll(i32),
+    /// Horizontal wheel notches (positive = right)
+    HScroll(i32),
+    /// Type Unicode text
+    Text(String),
+    SwitchMode(ControlMode),
+    /// Launch a command line
+    RunCommand(String),
+    /// Control the media player
+    Media(MediaCommand),
+    /// Pause between the steps of a sequence (milliseconds)
+    Delay(u64),
+    Sequence(Vec<Action>),
+    /// Play the macro with the given name
+    PlayMacro(String),
+    /// Start or stop macro recording
+    ToggleRecording,
+    /// Stop a running macro and release what it holds
+    CancelMacro,
+    /// Make the direction the controller points at the screen center
+    Recenter,
+}
+
+impl Action {
+    /// Whether running the action involves waiting
+    pub fn has_delay(&self) -> bool {
+        match self {
+            Action::Delay(_) => true,
+            Action::Sequence(steps) => steps.iter().any(Action::has_delay),
+            _ => false,
+        }
+    }
+
+    /// Point `PlayMacro` steps at a renamed macro, inside sequences too
+    pub fn rename_macro(&mut self, old: &str, new: &str) {
+        match self {
+            Action::PlayMacro(name) if name == old => *name = new.to_string(),
+            Action::Sequence(steps) => steps.iter_mut().for_each(|s| s.rename_macro(old, new)),
+            _ => {}
+        }
+    }
+
+    /// Drop `PlayMacro` steps of a deleted macro
+    ///
+    /// Returns false if nothing is left of the action. A sequence left
+    /// with a single step becomes that step.
+    pub fn forget_macro(&mut self, name: &str) -> bool {
+        match self {
+            Action::PlayMacro(played) => played != name,
+            Action::Sequence(steps) => {
+                steps.retain_mut(|s| s.forget_macro(name));
+                if steps.len() == 1 {
+                    *self = steps.remove(0);
+                    return true;
+                }
+                !steps.is_empty()
+            }
+            _ => true,
+        }
+    }
+
+    fn parse_step(s: &str) -> anyhow::Result<Action> {
+        let s = s.trim();
+        let Some((kind, arg)) = s.split_once(':') else {
+            return match s.to_ascii_lowercase().as_str() {
+                "record" => Ok(Action::ToggleRecording),
+                "cancel" => Ok(Action::CancelMacro),
+                "recenter" => Ok(Action::Recenter),
+                _ => Ok(Action::KeyPress(s.parse()?)),
+            };
+        };
+
+        let action = match kind.trim().to_ascii_lowercase().as_str() {
+            "hold" => Action::KeyDown(arg.parse()?),
+            "release" => Action::KeyUp(arg.parse()?),
+            "click" => Action::MouseClick(arg.parse()?),
+            "mousedown" => Action::MouseDown(arg.parse()?),
+            "mouseup" => Action::MouseUp(arg.parse()?),
+            "scroll" => Action::Scroll(arg.trim().parse()?),
+            "hscroll" => Action::HScroll(arg.trim().parse()?),
+            "text" => Action::Text(arg.to_string()),
+            "mode" => Action::SwitchMode(parse_mode(arg)?),
+            "run" => Action::RunCommand(arg.trim().to_string()),
+            "media" => Action::Media(arg.parse()?),
+            "wait" => Action::Delay(arg.trim().trim_end_matches("ms").parse()?),
+            "macro" => Action::PlayMacro(arg.trim().to_string()),
+            other => bail!("Unknown action '{}'", other),
+        };
+        Ok(action)
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::KeyPress(chord) => write!(f, "{}", chord),
+            Action::KeyDown(chord) => write!(f, "hold:{}", chord),
+            Action::KeyUp(chord) => write!(f, "release:{}", chord),
+            Action::MouseClick(button) => write!(f, "click:{}", button.name()),
+            Action::MouseDown(button) => write!(f, "mousedown:{}", button.name()),
+            Action::MouseUp(button) => write!(f, "mouseup:{}", button.name()),
+            Action::Scroll(notches) => write!(f, "scroll:{}", notches),
+            Action::HScroll(notches) => write!(f, "hscroll:{}", notches),
+            Action::Text(text) => write!(f, "text:{}", escape(text)),
+            Action::SwitchMode(mode) => write!(f, "mode:{}", mode_name(*mode)),
+            Action::RunCommand(command) => write!(f, "run:{}", escape(command)),
+            Action::Media(command) => write!(f, "media:{}", command),
+            Action::Delay(ms) => write!(f, "wait:{}", ms),
+            Action::Sequence(steps) => {
+                for (i, step) in steps.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str("; ")?;
+                    }
+                    write!(f, "{}", step)?;
+                }
+                Ok(())
+            }
+            Action::PlayMacro(name) => write!(f, "macro:{}", escape(name)),
+            Action::ToggleRecording => f.write_str("record"),
+            Action::CancelMacro => f.write_str("cancel"),
+            Action::Recenter => f.write_str("recenter"),
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = anyhow::Error;
+
+    /// Parse a single action or a `;`-separated sequence
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut steps = split_steps(s)
+            .iter()
+            .filter(|step| !step.trim().is_empty())
+            .map(|step| Action::parse_step(step))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        match steps.len() {
+            0 => bail!("Empty action"),
+            1 => Ok(steps.remove(0)),
+            _ => Ok(Action::Sequence(steps)),
+        }
+    }
+}
+
+/// Double the `;` in an argument so it does not end the step
+fn escape(arg: &str) -> String {
+    arg.replace(';', ";;")
+}
+
+/// Split a sequence at single `;`, turning `;;` back into `;`
+fn split_steps(s: &str) -> Vec<String> {
+    let mut steps = vec![String::new()];
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ';' if chars.next_if_eq(&';').is_some() => steps.last_mut().unwrap().push(';'),
+            ';' => steps.push(String::new()),
+            c => steps.last_mut().unwrap().push(c),
+        }
+    }
+    steps
+}
+
+fn mode_name(mode: ControlMode) -> &'static str {
+    match mode {
+        ControlMode::Mouse => "mouse",
+        ControlMode::Touchpad => "touchpad",
+        ControlMode::Pointer => "pointer",
+        ControlMode::Presentation => "presentation",
+        ControlMode::Media => "media",
+        ControlMode::Settings => "settings",
+    }
+}
+
+fn parse_mode(s: &str) -> anyhow::Result<ControlMode> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "mouse" => Ok(ControlMode::Mouse),
+        "touchpad" => Ok(ControlMode::Touchpad),
+        "pointer" => Ok(ControlMode::Pointer),
+        "presentation" => Ok(ControlMode::Presentation),
+        "media" => Ok(ControlMode::Media),
+        "settings" => Ok(ControlMode::Settings),
+        other => bail!("Unknown mode '{}'", other),
+    }
+}
+
+/// Low-level input injection used by [`ActionExecutor`]
+pub trait InputBackend {
+    fn key_down(&mut self, key: Key) -> anyhow::Result<()>;
+    fn key_up(&mut self, key: Key) -> anyhow::Result<()>;
+    fn mouse_down(&mut self, button: MouseButton) -> anyhow::Result<()>;
+    fn mouse_up(&mut self, button: MouseButton) -> anyhow::Result<()>;
+    fn scroll(&mut self, notches: i32) -> anyhow::Result<()>;
+    fn h_scroll(&mut self, notches: i32) -> anyhow::Result<()>;
+    fn type_text(&mut self, text: &str) -> anyhow::Result<()>;
+    fn run_command(&mut self, command: &str) -> anyhow::Result<()>;
+}
+
+/// Work an action asks of the application rather than the input backend
+#[derive(Debug, Clone, PartialEq)]
+pub enum HostRequest {
+    SwitchMode(ControlMode),
+    Media(MediaCommand),
+    PlayMacro(String),
+    ToggleRecording,
+    Recenter,
+}
+
+/// Carries out actions on an [`InputBackend`]
+///
+/// Actions without delays run immediately. Sequences containing delays and
+/// macros are queued and advanced by [`ActionExecutor::tick`], so nothing
+/// ever blocks the caller. Keys and buttons that are held down are tracked
+/// so they can be released on demand.
+pub struct ActionExecutor<B: InputBackend> {
+    backend: B,
+    pending: VecDeque<Action>,
+    resume_at: Option<Instant>,
+    held_keys: Vec<Key>,
+    held_buttons: Vec<MouseButton>,
+    /// The part of `held_keys` / `held_buttons` pressed by queued playback
+    played_keys: Vec<Key>,
+    played_buttons: Vec<MouseButton>,
+    /// A queued step is being performed
+    playing: bool,
+    requests: Vec<HostRequest>,
+}
+
+impl<B: InputBackend> ActionExecutor<B> {
+    pub fn new(backend: B) -> Self {
+        Self {
+            backend,
+            pending: VecDeque::new(),
+            resume_at: None,
+            held_keys: Vec::new(),
+            held_buttons: Vec::new(),
+            played_keys: Vec::new(),
+            played_buttons: Vec::new(),
+            playing: false,
+            requests: Vec::new(),
+        }
+    }
+
+    /// Run an action, queueing it if it contains delays
+    pub fn run(&mut self, action: &Action, now: Instant) {
+        if action.has_delay() {
+            flatten_into(action, &mut self.pending);
+            self.tick(now);
+        } else {
+            self.perform(action);
+        }
+    }
+
+    /// Queue the steps of a macro for playback
+    pub fn play(&mut self, steps: &[Action], now: Instant) {
+        for step in steps {
+            flatten_into(step, &mut self.pending);
+        }
+        self.tick(now);
+    }
+
+    /// Whether queued steps are still waiting to run
+    pub fn is_busy(&self) -> bool {
+        !self.pending.is_empty() || self.resume_at.is_some()
+    }
+
+    /// Advance queued sequences
+    pub fn tick(&mut self, now: Instant) {
+        loop {
+            if let Some(resume_at) = self.resume_at {
+                if now < resume_at {
+                    return;
+                }
+                self.resume_at = None;
+            }
+
+            match self.pending.pop_front() {
+                Some(Action::Delay(ms)) => {
+                    self.resume_at = Some(now + Duration::from_millis(ms));
+                }
+                Some(action) => {
+                    self.playing = true;
+                    self.perform(&action);
+                    self.playing = false;
+                }
+                None => return,
+            }
+        }
+    }
+
+    /// Drop queued steps and release what they still hold
+    ///
+    /// Inputs held by bindings (e.g. the trigger holding the left button
+    /// for a drag) stay down.
+    pub fn cancel(&mut self) {
+        self.pending.clear();
+        self.resume_at = None;
+        let keys = std::mem::take(&mut self.played_keys);
+        let buttons = std::mem::take(&mut self.played_buttons);
+        self.release(keys, buttons);
+    }
+
+    /// Release all keys and mouse buttons held by previous actions
+    pub fn release_all(&mut self) {
+        self.played_keys.clear();
+        self.played_buttons.clear();
+        let keys = std::mem::take(&mut self.held_keys);
+        let buttons = std::mem::take(&mut self.held_buttons);
+        self.release(keys, buttons);
+    }
+
+    /// Release `keys` and `buttons`, most recently pressed first
+    fn release(&mut self, keys: Vec<Key>, buttons: Vec<MouseButton>) {
+        for key in keys.into_iter().rev() {
+            self.held_keys.retain(|k| *k != key);
+            if let Err(e) = self.backend.key_up(key) {
+                warn!("Failed to release {}: {}", key, e);
+            }
+        }
+        for button in buttons.into_iter().rev() {
+            self.held_buttons.retain(|b| *b != button);
+            if let Err(e) = self.backend.mouse_up(button) {
+                warn!("Failed to release {} button: {}", button.name(), e);
+            }
+        }
+    }
+
+    /// Requests raised by the actions run since the last call
+    pub fn take_requests(&mut self) -> Vec<HostRequest> {
+        std::mem::take(&mut self.requests)
+    }
+
+    fn perform(&mut self, action: &Action) {
+        if let Err(e) = self.try_perform(action) {
+            warn!("Failed to perform '{}': {}", action, e);
+        }
+    }
+
+    fn try_perform(&mut self, action: &Action) -> anyhow::Result<()> {
+        match action {
+            Action::KeyPress(chord) => {
+                for key in chord.keys() {
+                    self.backend.key_down(key)?;
+                }
+                for key in chord.keys().rev() {
+                    self.backend.key_up(key)?;
+                }
+            }
+            Action::KeyDown(chord) => {
+                for key in chord.keys() {
+                    self.backend.key_down(key)?;
+                    if !self.held_keys.contains(&key) {
+                        self.held_keys.push(key);
+                        if self.playing {
+                            self.played_keys.push(key);
+                        }
+                    }
+                }
+            }
+            Action::KeyUp(chord) => {
+                for key in chord.keys().rev() {
+                    self.held_keys.retain(|k| *k != key);
+                    self.played_keys.retain(|k| *k != key);
+                    self.backend.key_up(key)?;
+                }
+            }
+            Action::MouseClick(button) => {
+                self.backend.mouse_down(*button)?;
+                self.backend.mouse_up(*button)?;
+            }
+            Action::MouseDown(button) => {
+                self.backend.mouse_down(*button)?;
+                if !self.held_buttons.contains(button) {
+                    self.held_buttons.push(*button);
+                    if self.playing {
+                        self.played_buttons.push(*button);
+                    }
+                }
+            }
+            Action::MouseUp(button) => {
+                self.held_buttons.retain(|b| b != button);
+                self.played_buttons.retain(|b| b != button);
+                self.backend.mouse_up(*button)?;
+            }
+            Action::Scroll(notches) => self.backend.scroll(*notches)?,
+            Action::HScroll(notches) => self.backend.h_scroll(*notches)?,
+            Action::Text(text) => self.backend.type_text(text)?,
+            Action::SwitchMode(mode) => self.requests.push(HostRequest::SwitchMode(*mode)),
+            Action::RunCommand(command) => self.backend.run_command(command)?,
+            Action::Media(command) => self.requests.push(HostRequest::Media(*command)),
+            // Only meaningful inside a queued sequence
+            Action::Delay(_) => {}
+            Action::Sequence(steps) => {
+                for step in steps {
+                    self.perform(step);
+                }
+            }
+            Action::PlayMacro(name) => self.requests.push(HostRequest::PlayMacro(name.clone())),
+            Action::ToggleRecording => self.requests.push(HostRequest::ToggleRecording),
+            Action::CancelMacro => self.cancel(),
+            Action::Recenter => self.requests.push(HostRequest::Recenter),
+        }
+        Ok(())
+    }
+}
+
+fn flatten_into(action: &Action, queue: &mut VecDeque<Action>) {
+    match action {
+        Action::Sequence(steps) => {
+            for step in steps {
+                flatten_into(step, queue);
+            }
+        }
+        other => queue.push_back(other.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Backend recording every call as a string
+    #[derive(Default)]
+    struct RecordingBackend {
+        log: Vec<String>,
+    }
+
+    impl InputBackend for RecordingBackend {
+        fn key_down(&mut self, key: Key) -> anyhow::Result<()> {
+            self.log.push(format!("down {}", key));
+            Ok(())
+        }
+        fn key_up(&mut self, key: Key) -> anyhow::Result<()> {
+            self.log.push(format!("up {}", key));
+            Ok(())
+        }
+        fn mouse_down(&mut self, button: MouseButton) -> anyhow::Result<()> {
+            self.log.push(format!("mousedown {}", button.name()));
+            Ok(())
+        }
+        fn mouse_up(&mut self, button: MouseButton) -> anyhow::Result<()> {
+            self.log.push(format!("mouseup {}", button.name()));
+            Ok(())
+        }
+        fn scroll(&mut self, notches: i32) -> anyhow::Result<()> {
+            self.log.push(format!("scroll {}", notches));
+            Ok(())
+        }
+        fn h_scroll(&mut self, notches: i32) -> anyhow::Result<()> {
+            self.log.push(format!("hscroll {}", notches));
+            Ok(())
+        }
+        fn type_text(&mut self, text: &str) -> anyhow::Result<()> {
+            self.log.push(format!("text {}", text));
+            Ok(())
+        }
+        fn run_command(&mut self, command: &str) -> anyhow::Result<()> {
+            self.log.push(format!("run {}", command));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_parse_key_chord() {
+        let chord: KeyChord = "Ctrl+Alt+T".parse().unwrap();
+        assert_eq!(chord.modifiers, vec![Key::Ctrl, Key::Alt]);
+        assert_eq!(chord.key, Key::Char('t'));
+        assert_eq!(chord.to_string(), "ctrl+alt+t");
+
+        let chord: KeyChord = "control + shift + tab".parse().unwrap();
+        assert_eq!(chord.to_string(), "ctrl+shift+tab");
+
+        assert_eq!("alt".parse::<KeyChord>().unwrap(), KeyChord::from(Key::Alt));
+        assert_eq!("f12".parse::<KeyChord>().unwrap().key, Key::F(12));
+        assert!("t+ctrl".parse::<KeyChord>().is_err());
+        assert!("ctrl+".parse::<KeyChord>().is_err());
+        assert!("f25".parse::<KeyChord>().is_err());
+    }
+
+    #[test]
+    fn test_parse_actions_round_trip() {
+        for text in [
+            "ctrl+shift+tab",
+            "hold:shift",
+            "click:middle",
+            "mouseup:x2",
+            "scroll:-3",
+            "text:Grüße, world",
+            "mode:presentation",
+            "run:notepad.exe notes.txt",
+            "ctrl+c; wait:100; ctrl+v",
+            "macro:Open Terminal",
+            "record",
+            "recenter",
+        ] {
+            let action: Action = text.parse().unwrap();
+            assert_eq!(action.to_string(), text);
+        }
+
+        assert_eq!("wait:250ms".parse::<Action>().unwrap(), Action::Delay(250));
+        assert!("jump:high".parse::<Action>().is_err());
+        assert!(" ; ".parse::<Action>().is_err());
+    }
+
+    #[test]
+    fn test_semicolons_in_arguments_round_trip() {
+        let action = Action::Sequence(vec![
+            Action::Text("a; b+c;".into()),
+            Action::RunCommand("cmd /c echo 1; echo 2".into()),
+            Action::Text("ctrl+c".into()),
+        ]);
+        let text = action.to_string();
+        assert_eq!(
+            text,
+            "text:a;; b+c;;; run:cmd /c echo 1;; echo 2; text:ctrl+c"
+        );
+        assert_eq!(text.parse::<Action>().unwrap(), action);
+
+        let single = Action::Text("1;2+3".into());
+        assert_eq!(single.to_string().parse::<Action>().unwrap(), single);
+    }
+
+    #[test]
+    fn test_chord_serializes_as_string() {
+        let action = Action::KeyPress("ctrl+alt+delete".parse().unwrap());
+        let json = serde_json::to_string(&action).unwrap();
+        assert_eq!(json, r#"{"KeyPress":"ctrl+alt+delete"}"#);
+        assert_eq!(serde_json::from_str::<Action>(&json).unwrap(), action);
+    }
+
+    #[test]
+    fn test_chord_press_order() {
+        let mut executor = ActionExecutor::new(RecordingBackend::default());
+        executor.run(&"ctrl+shift+esc".parse().unwrap(), Instant::now());
+        assert_eq!(
+            executor.backend.log,
+            [
+                "down ctrl",
+                "down shift",
+                "down esc",
+                "up esc",
+                "up shift",
+                "up ctrl"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sequence_with_delay_runs_on_tick() {
+        let mut executor = ActionExecutor::new(RecordingBackend::default());
+        let start = Instant::now();
+
+        executor.run(&"scroll:1; wait:100; scroll:2".parse().unwrap(), start);
+        assert_eq!(executor.backend.log, ["scroll 1"]);
+        assert!(executor.resume_at.is_some());
+
+        executor.tick(start + Duration::from_millis(50));
+        assert_eq!(executor.backend.log.len(), 1);
+
+        executor.tick(start + Duration::from_millis(100));
+        assert_eq!(executor.backend.log, ["scroll 1", "scroll 2"]);
+        assert!(executor.pending.is_empty());
+    }
+
+    #[test]
+    fn test_cancel_releases_held_inputs() {
+        let mut executor = ActionExecutor::new(RecordingBackend::default());
+        let start = Instant::now();
+
+        executor.run(
+            &"hold:ctrl; mousedown:left; wait:1000; text:never"
+                .parse()
+                .unwrap(),
+            start,
+        );
+        executor.cancel();
+        executor.tick(start + Duration::from_secs(2));
+
+        assert_eq!(
+            executor.backend.log,
+            ["down ctrl", "mousedown left", "up ctrl", "mouseup left"]
+        );
+        assert!(executor.pending.is_empty());
+    }
+
+    #[test]
+    fn test_mode_switch_is_reported() {
+        let mut executor = ActionExecutor::new(RecordingBackend::default());
+        executor.run(&"mode:touchpad".parse().unwrap(), Instant::now());
+        assert_eq!(
+            executor.take_requests(),
+            [HostRequest::SwitchMode(ControlMode::Touchpad)]
+        );
+        assert!(executor.take_requests().is_empty());
+    }
+
+    #[test]
+    fn test_cancel_action_stops_macro_playback() {
+        let mut executor = ActionExecutor::new(RecordingBackend::default());
+        let start = Instant::now();
+
+        executor.play(
+            &[
+                "hold:shift".parse().unwrap(),
+                "wait:500".parse().unwrap(),
+                "a".parse().unwrap(),
+            ],
+            start,
+        );
+        assert!(executor.is_busy());
+
+        executor.run(&Action::CancelMacro, start + Duration::from_millis(100));
+        executor.tick(start + Duration::from_secs(1));
+
+        assert!(!executor.is_busy());
+        assert_eq!(executor.backend.log, ["down shift", "up shift"]);
+    }
+
+    #[test]
+    fn test_cancel_keeps_inputs_held_by_bindings() {
+        let mut executor = ActionExecutor::new(RecordingBackend::default());
+        let start = Instant::now();
+
+        // Trigger press starts a drag, then a macro holds ctrl
+        executor.run(&"mousedown:left".parse().unwrap(), start);
+        executor.play(
+            &[
+                "hold:ctrl".parse().unwrap(),
+                "wait:500".parse().unwrap(),
+                "c".parse().unwrap(),
+            ],
+            start,
+        );
+        executor.cancel();
+        assert_eq!(
+            executor.backend.log,
+            ["mousedown left", "down ctrl", "up ctrl"]
+        );
+
+        // The drag ends with the trigger release, or on release_all
+        executor.release_all();
+        assert_eq!(executor.backend.log.last().unwrap(), "mouseup left");
+        executor.release_all();
+        assert_eq!(executor.backend.log.len(), 4);
+    }
+}
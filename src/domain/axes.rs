@@ -0,0 +1,268 @@
+//! Controller Axes
+//!
+//! How the controller is held decides which way is "up" on the touchpad
+//! and which way the cursor goes when it turns. The touchpad is rotated in
+//! quarter turns plus a fine angle for the slant of the thumb, which is
+//! mirrored between the right and the left hand. Each input source can
+//! have its axes inverted, and natural scrolling makes the content follow
+//! the finger.
+//!
+//! Angles are clockwise as seen on screen (y pointing down).
+
+use crate::domain::filters::SampleTimer;
+use crate::domain::models::ControllerData;
+use serde::{Deserialize, Serialize};
+
+/// Shortest swipe (normalized units) the wizard accepts
+const MIN_WIZARD_SWIPE: f64 = 0.5;
+/// Rotation (radians) that counts as turning the controller
+const MIN_WIZARD_TURN: f64 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Hand {
+    Right,
+    Left,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AxisInvert {
+    pub x: bool,
+    pub y: bool,
+}
+
+impl AxisInvert {
+    pub fn apply(&self, [x, y]: [f64; 2]) -> [f64; 2] {
+        [if self.x { -x } else { x }, if self.y { -y } else { y }]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AxisSettings {
+    pub hand: Hand,
+    /// Clockwise quarter turns of the touchpad, 0 to 3
+    pub touchpad_quarter_turns: u8,
+    /// Further clockwise rotation in degrees (-45 to 45) for the right
+    /// hand; the left hand uses the mirror image
+    pub touchpad_fine_angle: f64,
+    pub invert_touchpad: AxisInvert,
+    pub invert_scroll: AxisInvert,
+    pub invert_imu: AxisInvert,
+    /// Content follows the finger, like on a phone
+    pub natural_scrolling: bool,
+}
+
+impl Default for AxisSettings {
+    fn default() -> Self {
+        Self {
+            hand: Hand::Right,
+            touchpad_quarter_turns: 0,
+            touchpad_fine_angle: 0.0,
+            invert_touchpad: AxisInvert::default(),
+            invert_scroll: AxisInvert::default(),
+            invert_imu: AxisInvert::default(),
+            natural_scrolling: false,
+        }
+    }
+}
+
+impl AxisSettings {
+    /// Total clockwise touchpad rotation in degrees
+    pub fn touchpad_angle(&self) -> f64 {
+        let fine = match self.hand {
+            Hand::Right => self.touchpad_fine_angle,
+            Hand::Left => -self.touchpad_fine_angle,
+        };
+        (self.touchpad_quarter_turns % 4) as f64 * 90.0 + fine
+    }
+
+    /// Split a total rotation into quarter turns and a fine angle
+    pub fn set_touchpad_angle(&mut self, degrees: f64) {
+        let quarter_turns = (degrees / 90.0).round();
+        let fine = degrees - quarter_turns * 90.0;
+        self.touchpad_quarter_turns = quarter_turns.rem_euclid(4.0) as u8;
+        self.touchpad_fine_angle = match self.hand {
+            Hand::Right => fine,
+            Hand::Left => -fine,
+        };
+    }
+
+    /// Touchpad position (normalized, -1 to 1) as the user sees it
+    pub fn map_touchpad(&self, [x, y]: [f64; 2]) -> [f64; 2] {
+        let (sin, cos) = self.touchpad_angle().to_radians().sin_cos();
+        let rotated = [x * cos - y * sin, x * sin + y * cos];
+        self.invert_touchpad
+            .apply(rotated)
+            .map(|v| v.clamp(-1.0, 1.0))
+    }
+
+    /// Scroll direction for a finger movement on the (mapped) touchpad
+    pub fn map_scroll(&self, delta: [f64; 2]) -> [f64; 2] {
+        let [x, y] = self.invert_scroll.apply(delta);
+        if self.natural_scrolling {
+            [-x, -y]
+        } else {
+            [x, y]
+        }
+    }
+
+    /// Cursor direction for a controller rotation
+    pub fn map_imu(&self, delta: [f64; 2]) -> [f64; 2] {
+        self.invert_imu.apply(delta)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WizardStep {
+    SwipeUp,
+    TurnRight,
+    Done,
+}
+
+/// Infers the touchpad rotation from a swipe "up" and the air mouse
+/// direction from a turn to the right
+///
+/// Fed with processed touchpad positions, which already carry the current
+/// rotation; the swipe tells how much further to turn. The result starts
+/// without touchpad inversion so that "up" really is up.
+pub struct AxisWizard {
+    step: WizardStep,
+    result: AxisSettings,
+    swipe_start: Option<[f64; 2]>,
+    swipe_end: [f64; 2],
+    turned: f64,
+    timer: SampleTimer,
+}
+
+impl AxisWizard {
+    pub fn new(current: &AxisSettings) -> Self {
+        Self {
+            step: WizardStep::SwipeUp,
+            result: current.clone(),
+            swipe_start: None,
+            swipe_end: [0.0; 2],
+            turned: 0.0,
+            timer: SampleTimer::default(),
+        }
+    }
+
+    pub fn step(&self) -> WizardStep {
+        self.step
+    }
+
+    /// Inferred settings, once both steps are done
+    pub fn result(&self) -> Option<&AxisSettings> {
+        (self.step == WizardStep::Done).then_some(&self.result)
+    }
+
+    pub fn add(&mut self, data: &ControllerData) {
+        match self.step {
+            WizardStep::SwipeUp => self.add_swipe(data),
+            WizardStep::TurnRight => self.add_turn(data),
+            WizardStep::Done => {}
+        }
+    }
+
+    fn add_swipe(&mut self, data: &ControllerData) {
+        if data.touchpad_touched {
+            let point = [data.processed_touchpad_x, data.processed_touchpad_y];
+            self.swipe_start.get_or_insert(point);
+            self.swipe_end = point;
+            return;
+        }
+        let Some(start) = self.swipe_start.take() else {
+            return;
+        };
+        // Undo the current inversion to get the rotated-only direction
+        let [dx, dy] = self
+            .result
+            .invert_touchpad
+            .apply([self.swipe_end[0] - start[0], self.swipe_end[1] - start[1]]);
+        if dx.hypot(dy) < MIN_WIZARD_SWIPE {
+            return;
+        }
+        // Up is -y; turn the swipe onto it
+        let correction = -90.0 - dy.atan2(dx).to_degrees();
+        let angle = (self.result.touchpad_angle() + correction + 180.0).rem_euclid(360.0) - 180.0;
+        self.result.invert_touchpad = AxisInvert::default();
+        self.result.set_touchpad_angle(angle);
+        self.step = WizardStep::TurnRight;
+        self.timer.reset();
+    }
+
+    fn add_turn(&mut self, data: &ControllerData) {
+        let dt = self.timer.interval(data.timestamp) as f64;
+        // The air mouse moves right for positive gyro X
+        self.turned += data.gyro_x as f64 * dt;
+        if self.turned.abs() >= MIN_WIZARD_TURN {
+            self.result.invert_imu.x = self.turned < 0.0;
+            self.step = WizardStep::Done;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(actual: [f64; 2], expected: [f64; 2]) {
+        let close = actual
+            .iter()
+            .zip(expected)
+            .all(|(a, e)| (a - e).abs() < 1e-9);
+        assert!(close, "{:?} != {:?}", actual, expected);
+    }
+
+    #[test]
+    fn test_touchpad_rotation_and_handedness() {
+        let mut axes = AxisSettings {
+            touchpad_quarter_turns: 1,
+            ..AxisSettings::default()
+        };
+        // A quarter turn clockwise: right becomes down
+        assert_near(axes.map_touchpad([0.5, 0.0]), [0.0, 0.5]);
+
+        // The fine angle is mirrored for the left hand
+        axes.touchpad_fine_angle = 10.0;
+        assert_eq!(axes.touchpad_angle(), 100.0);
+        axes.hand = Hand::Left;
+        assert_eq!(axes.touchpad_angle(), 80.0);
+        axes.set_touchpad_angle(-100.0);
+        assert_eq!(axes.touchpad_quarter_turns, 3);
+        assert!((axes.touchpad_angle() - 260.0).abs() < 1e-9);
+
+        axes.natural_scrolling = true;
+        axes.invert_scroll.x = true;
+        assert_eq!(axes.map_scroll([1.0, 1.0]), [1.0, -1.0]);
+    }
+
+    #[test]
+    fn test_wizard_infers_rotation_and_turn_direction() {
+        // The pad is held a quarter turn clockwise: "up" arrives as +x
+        let mut wizard = AxisWizard::new(&AxisSettings::default());
+        let mut data = ControllerData {
+            touchpad_touched: true,
+            ..ControllerData::default()
+        };
+        for x in [-0.4, 0.0, 0.4] {
+            data.processed_touchpad_x = x;
+            wizard.add(&data);
+        }
+        data.touchpad_touched = false;
+        wizard.add(&data);
+        assert_eq!(wizard.step(), WizardStep::TurnRight);
+
+        // Turning right reads as negative gyro X
+        data.gyro_x = -2.0;
+        for i in 0..30 {
+            data.timestamp = i as f64 * 0.015;
+            wizard.add(&data);
+        }
+        let result = wizard.result().expect("wizard finished");
+        assert_eq!(result.touchpad_quarter_turns, 3);
+        assert!(result.touchpad_fine_angle.abs() < 1e-9);
+        assert!(result.invert_imu.x);
+        assert_near(result.map_touchpad([0.4, 0.0]), [0.0, -0.4]);
+    }
+}
@@ -0,0 +1,442 @@
+//! Button Event Engine
+//!
+//! Turns raw per-packet button states into debounced, higher level events:
+//! press/release, tap, double tap, long press, hold repeat and two-button
+//! chords. Time comes from a [`Clock`] so the engine can be driven
+//! deterministically in tests.
+
+use crate::domain::input_mapper::{ButtonTrigger, ControllerButton, InputEvent};
+use crate::domain::models::ControllerData;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Source of the current time
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+/// Wall clock used outside of tests
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Clock that only moves when told to; clones share the same time
+#[cfg(test)]
+#[derive(Debug, Clone)]
+pub(crate) struct FakeClock {
+    now: std::rc::Rc<std::cell::Cell<Instant>>,
+}
+
+#[cfg(test)]
+impl FakeClock {
+    pub(crate) fn new() -> Self {
+        Self {
+            now: std::rc::Rc::new(std::cell::Cell::new(Instant::now())),
+        }
+    }
+
+    pub(crate) fn advance(&self, ms: u64) {
+        self.now.set(self.now.get() + Duration::from_millis(ms));
+    }
+}
+
+#[cfg(test)]
+impl Clock for FakeClock {
+    fn now(&self) -> Instant {
+        self.now.get()
+    }
+}
+
+/// Timing thresholds, all in milliseconds
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ButtonTimings {
+    /// State changes closer together than this are ignored
+    pub debounce_ms: u64,
+    /// Maximum press duration that still counts as a tap
+    pub long_press_ms: u64,
+    /// Maximum gap between two taps to form a double tap
+    pub double_tap_ms: u64,
+    /// Delay before hold repeat starts after the initial press
+    pub repeat_delay_ms: u64,
+    pub repeat_interval_ms: u64,
+    /// Maximum gap between the presses of a chord; a press is reported
+    /// only once this has passed without a second button joining it
+    pub chord_window_ms: u64,
+}
+
+impl Default for ButtonTimings {
+    fn default() -> Self {
+        Self {
+            debounce_ms: 50,
+            long_press_ms: 300,
+            double_tap_ms: 300,
+            repeat_delay_ms: 50,
+            repeat_interval_ms: 50,
+            chord_window_ms: 200,
+        }
+    }
+}
+
+impl ControllerButton {
+    pub const ALL: [ControllerButton; 6] = [
+        ControllerButton::Trigger,
+        ControllerButton::TouchpadButton,
+        ControllerButton::Back,
+        ControllerButton::Home,
+        ControllerButton::VolumeUp,
+        ControllerButton::VolumeDown,
+    ];
+
+    /// Raw state of this button in a controller packet
+    pub fn is_pressed(&self, data: &ControllerData) -> bool {
+        match self {
+            ControllerButton::Trigger => data.trigger_button,
+            ControllerButton::TouchpadButton => data.touchpad_button,
+            ControllerButton::Back => data.back_button,
+            ControllerButton::Home => data.home_button,
+            ControllerButton::VolumeUp => data.volume_up_button,
+            ControllerButton::VolumeDown => data.volume_down_button,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+struct ButtonState {
+    /// Debounced state
+    pressed: bool,
+    last_change: Option<Instant>,
+    pressed_at: Option<Instant>,
+    /// Release time of the previous tap, for double tap detection
+    last_tap: Option<Instant>,
+    long_press_fired: bool,
+    next_repeat: Option<Instant>,
+    /// Press held back until the chord window closes without a partner
+    press_pending: bool,
+    /// Part of a chord; suppresses tap/long press/repeat until released
+    in_chord: bool,
+}
+
+/// Per-button state machines plus chord detection
+pub struct ButtonEngine<C: Clock = SystemClock> {
+    clock: C,
+    timings: ButtonTimings,
+    states: [ButtonState; 6],
+}
+
+impl ButtonEngine<SystemClock> {
+    pub fn new(timings: ButtonTimings) -> Self {
+        Self::with_clock(timings, SystemClock)
+    }
+}
+
+impl<C: Clock> ButtonEngine<C> {
+    pub fn with_clock(timings: ButtonTimings, clock: C) -> Self {
+        Self {
+            clock,
+            timings,
+            states: Default::default(),
+        }
+    }
+
+    pub fn set_timings(&mut self, timings: &ButtonTimings) {
+        if &self.timings != timings {
+            self.timings = timings.clone();
+        }
+    }
+
+    /// Forget all state, e.g. after the link was dropped
+    pub fn reset(&mut self) {
+        self.states = Default::default();
+    }
+
+    /// Feed the raw state of every button from one controller packet
+    pub fn update(&mut self, data: &ControllerData) -> Vec<InputEvent> {
+        let raw = ControllerButton::ALL.map(|b| b.is_pressed(data));
+        self.update_raw(raw)
+    }
+
+    /// Feed raw states in the order of [`ControllerButton::ALL`]
+    pub fn update_raw(&mut self, raw: [bool; 6]) -> Vec<InputEvent> {
+        let now = self.clock.now();
+        let t = &self.timings;
+        let debounce = Duration::from_millis(t.debounce_ms);
+        let long_press = Duration::from_millis(t.long_press_ms);
+        let double_tap = Duration::from_millis(t.double_tap_ms);
+        let chord_window = Duration::from_millis(t.chord_window_ms);
+        let repeat_delay = Duration::from_millis(t.repeat_delay_ms);
+        let repeat_interval = Duration::from_millis(t.repeat_interval_ms.max(1));
+
+        let mut events = Vec::new();
+
+        for (i, button) in ControllerButton::ALL.into_iter().enumerate() {
+            let state = &self.states[i];
+            let changed = raw[i] != state.pressed
+                && state
+                    .last_change
+                    .is_none_or(|last| now.duration_since(last) > debounce);
+
+            if changed && raw[i] {
+                // A second button pressed shortly after a held one forms a chord
+                let partner = ControllerButton::ALL
+                    .into_iter()
+                    .enumerate()
+                    .find(|(j, _)| {
+                        let other = &self.states[*j];
+                        *j != i
+                            && other.pressed
+                            && other.press_pending
+                            && other
+                                .pressed_at
+                                .is_some_and(|at| now.duration_since(at) <= chord_window)
+                    });
+
+                let state = &mut self.states[i];
+                state.pressed = true;
+                state.last_change = Some(now);
+                state.pressed_at = Some(now);
+                state.long_press_fired = false;
+                state.in_chord = false;
+
+                if let Some((j, other)) = partner {
+                    // Neither member reports its own press or release
+                    self.states[i].in_chord = true;
+                    self.states[j].in_chord = true;
+                    self.states[j].press_pending = false;
+                    events.push(InputEvent::Chord(button.min(other), button.max(other)));
+                } else if chord_window.is_zero() {
+                    state.next_repeat = Some(now + repeat_delay);
+                    events.push(InputEvent::Button(button, ButtonTrigger::Press));
+                    events.push(InputEvent::Button(button, ButtonTrigger::Repeat));
+                } else {
+                    // Wait for a possible chord partner before acting
+                    state.press_pending = true;
+                }
+            } else if changed {
+                let state = &mut self.states[i];
+                state.pressed = false;
+                state.last_change = Some(now);
+                state.next_repeat = None;
+                if std::mem::take(&mut state.press_pending) {
+                    // Released within the chord window: the press was still held back
+                    events.push(InputEvent::Button(button, ButtonTrigger::Press));
+                    events.push(InputEvent::Button(button, ButtonTrigger::Repeat));
+                }
+                if !state.in_chord {
+                    events.push(InputEvent::Button(button, ButtonTrigger::Release));
+                }
+
+                let held = state
+                    .pressed_at
+                    .take()
+                    .map_or(Duration::ZERO, |at| now.duration_since(at));
+                if !state.in_chord && !state.long_press_fired && held < long_press {
+                    if state
+                        .last_tap
+                        .is_some_and(|tap| now.duration_since(tap) <= double_tap)
+                    {
+                        state.last_tap = None;
+                        events.push(InputEvent::Button(button, ButtonTrigger::DoubleTap));
+                    } else {
+                        state.last_tap = Some(now);
+                        events.push(InputEvent::Button(button, ButtonTrigger::Tap));
+                    }
+                }
+                state.in_chord = false;
+            } else if state.pressed && !state.in_chord {
+                let state = &mut self.states[i];
+                let held = state
+                    .pressed_at
+                    .map_or(Duration::ZERO, |at| now.duration_since(at));
+
+                if state.press_pending && held > chord_window {
+                    state.press_pending = false;
+                    state.next_repeat = Some(now + repeat_delay);
+                    events.push(InputEvent::Button(button, ButtonTrigger::Press));
+                    events.push(InputEvent::Button(button, ButtonTrigger::Repeat));
+                }
+
+                if !state.long_press_fired && held >= long_press {
+                    state.long_press_fired = true;
+                    state.last_tap = None;
+                    events.push(InputEvent::Button(button, ButtonTrigger::LongPress));
+                }
+
+                if state.next_repeat.is_some_and(|at| now >= at) {
+                    state.next_repeat = Some(now + repeat_interval);
+                    events.push(InputEvent::Button(button, ButtonTrigger::Repeat));
+                }
+            }
+        }
+
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ButtonTrigger::*;
+    use ControllerButton::*;
+
+    fn engine() -> (ButtonEngine<FakeClock>, FakeClock) {
+        let clock = FakeClock::new();
+        (
+            ButtonEngine::with_clock(ButtonTimings::default(), clock.clone()),
+            clock,
+        )
+    }
+
+    fn pressed(buttons: &[ControllerButton]) -> [bool; 6] {
+        ControllerButton::ALL.map(|b| buttons.contains(&b))
+    }
+
+    /// Only the events of one kind, for concise assertions
+    fn of(events: &[InputEvent], trigger: ButtonTrigger) -> Vec<ControllerButton> {
+        events
+            .iter()
+            .filter_map(|e| match e {
+                InputEvent::Button(b, t) if *t == trigger => Some(*b),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_tap_and_double_tap() {
+        let (mut engine, clock) = engine();
+
+        // The press is held back for the chord window, then reported on release
+        let events = engine.update_raw(pressed(&[Back]));
+        assert!(of(&events, Press).is_empty());
+        clock.advance(100);
+        let events = engine.update_raw(pressed(&[]));
+        assert_eq!(of(&events, Press), [Back]);
+        assert_eq!(of(&events, Release), [Back]);
+        assert_eq!(of(&events, Tap), [Back]);
+
+        clock.advance(100);
+        engine.update_raw(pressed(&[Back]));
+        clock.advance(100);
+        let events = engine.update_raw(pressed(&[]));
+        assert_eq!(of(&events, DoubleTap), [Back]);
+        assert!(of(&events, Tap).is_empty());
+
+        // A third tap starts over
+        clock.advance(100);
+        engine.update_raw(pressed(&[Back]));
+        clock.advance(100);
+        assert_eq!(of(&engine.update_raw(pressed(&[])), Tap), [Back]);
+    }
+
+    #[test]
+    fn test_bounces_are_ignored() {
+        let (mut engine, clock) = engine();
+
+        engine.update_raw(pressed(&[Trigger]));
+        clock.advance(10);
+        assert!(engine.update_raw(pressed(&[])).is_empty());
+        clock.advance(10);
+        assert!(engine.update_raw(pressed(&[Trigger])).is_empty());
+
+        // The release is picked up once the debounce time has passed
+        clock.advance(60);
+        assert_eq!(of(&engine.update_raw(pressed(&[])), Release), [Trigger]);
+    }
+
+    #[test]
+    fn test_long_press_suppresses_tap() {
+        let (mut engine, clock) = engine();
+
+        engine.update_raw(pressed(&[Home]));
+        clock.advance(200);
+        assert!(of(&engine.update_raw(pressed(&[Home])), LongPress).is_empty());
+        clock.advance(150);
+        assert_eq!(of(&engine.update_raw(pressed(&[Home])), LongPress), [Home]);
+        clock.advance(50);
+        assert!(of(&engine.update_raw(pressed(&[Home])), LongPress).is_empty());
+
+        let events = engine.update_raw(pressed(&[]));
+        assert_eq!(of(&events, Release), [Home]);
+        assert!(of(&events, Tap).is_empty());
+    }
+
+    #[test]
+    fn test_hold_repeat() {
+        let (mut engine, clock) = engine();
+
+        assert!(of(&engine.update_raw(pressed(&[VolumeUp])), Repeat).is_empty());
+        let mut presses = 0;
+        let mut repeats = 0;
+        for _ in 0..40 {
+            clock.advance(10);
+            let events = engine.update_raw(pressed(&[VolumeUp]));
+            presses += of(&events, Press).len();
+            repeats += of(&events, Repeat).len();
+        }
+        // 400 ms held: press and first repeat once the chord window has
+        // passed at 210 ms, then every 50 ms
+        assert_eq!(presses, 1);
+        assert_eq!(repeats, 4);
+
+        engine.update_raw(pressed(&[]));
+        clock.advance(100);
+        assert!(of(&engine.update_raw(pressed(&[])), Repeat).is_empty());
+    }
+
+    #[test]
+    fn test_chord() {
+        let (mut engine, clock) = engine();
+
+        assert!(engine.update_raw(pressed(&[Back])).is_empty());
+        clock.advance(80);
+        let events = engine.update_raw(pressed(&[Back, Trigger]));
+        assert_eq!(events, [InputEvent::Chord(Trigger, Back)]);
+
+        // Chord members produce nothing of their own, not even once held
+        // past the chord window or released
+        clock.advance(500);
+        assert!(engine.update_raw(pressed(&[Back, Trigger])).is_empty());
+        assert!(engine.update_raw(pressed(&[])).is_empty());
+    }
+
+    #[test]
+    fn test_quick_taps_act_twice_without_double_tap_binding() {
+        use crate::domain::actions::{Action, Key};
+        use crate::domain::input_mapper::InputMapper;
+        use crate::domain::models::ControlMode;
+        use crate::domain::settings::{Settings, SettingsService};
+        use std::sync::{Arc, Mutex};
+
+        let settings = SettingsService::in_memory(Settings::default());
+        let mapper = InputMapper::new(Arc::new(Mutex::new(settings)));
+        let (mut engine, clock) = engine();
+
+        // Back twice in Presentation mode goes back two slides
+        let mut actions = Vec::new();
+        for _ in 0..2 {
+            for buttons in [&[Back][..], &[]] {
+                for event in engine.update_raw(pressed(buttons)) {
+                    actions.extend(mapper.map(ControlMode::Presentation, event));
+                }
+                clock.advance(100);
+            }
+        }
+        assert_eq!(actions, vec![Action::KeyPress(Key::Left.into()); 2]);
+    }
+
+    #[test]
+    fn test_late_second_press_is_not_a_chord() {
+        let (mut engine, clock) = engine();
+
+        engine.update_raw(pressed(&[Back]));
+        clock.advance(250);
+        let events = engine.update_raw(pressed(&[Back, Trigger]));
+        assert!(!events.iter().any(|e| matches!(e, InputEvent::Chord(..))));
+    }
+}
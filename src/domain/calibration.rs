@@ -0,0 +1,1025 @@
+//! Sensor Calibration
+//!
+//! Per-controller corrections for the IMU, stored by Bluetooth address.
+//!
+//! Rotating a perfect magnetometer traces a sphere whose radius is the
+//! local field strength. Magnetized parts inside the controller shift its
+//! center (hard iron) and nearby metal stretches it into an ellipsoid (soft
+//! iron). Fitting an ellipsoid to samples from many orientations recovers
+//! both, and the inverse mapping turns it back into a sphere.
+//!
+//! The gyroscope reads a small nonzero rate at rest that drifts with the
+//! sensor temperature. Whenever the controller lies still the average rate
+//! is that bias; it is learned in the background per temperature.
+//!
+//! Each accelerometer axis has its own offset and gain error. Resting the
+//! controller on all six faces shows every axis at +1 g and -1 g, which
+//! pins both down.
+//!
+//! `ImuPreprocessor` applies all of this to every sample before any other
+//! consumer sees it.
+
+use crate::domain::models::ControllerData;
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Samples needed before an ellipsoid fit is attempted
+pub const MIN_MAG_SAMPLES: usize = 100;
+/// Collection stops growing beyond this many samples
+const MAX_MAG_SAMPLES: usize = 4000;
+/// A sample is kept only if it moved this fraction of its magnitude away
+/// from the previous one, so holding still doesn't flood the fit
+const MIN_SAMPLE_SPACING: f32 = 0.02;
+/// Coverage is measured over 8 azimuth sectors x 4 equal-area elevation bands
+const AZIMUTH_BINS: usize = 8;
+const ELEVATION_BINS: usize = 4;
+/// Samples averaged for each accelerometer calibration position
+const ACCEL_WINDOW: usize = 50;
+/// Largest accelerometer variance, relative to gravity squared, that
+/// counts as resting
+const ACCEL_REST_VARIANCE: f32 = 0.0001;
+/// The resting axis must carry this fraction of gravity (about 15° tilt)
+const FACE_ALIGNMENT: f32 = 0.965;
+/// Accepted per-axis gain before calibration, in g per g
+const ACCEL_GAIN_RANGE: std::ops::RangeInclusive<f32> = 0.8..=1.2;
+/// Largest deviation from 1 g accepted after calibration
+const MAX_GRAVITY_ERROR: f32 = 0.05;
+
+/// Calibration results for one controller
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceCalibration {
+    pub address: u64,
+    #[serde(default)]
+    pub magnetometer: Option<MagCalibration>,
+    #[serde(default)]
+    pub gyro_bias: GyroBias,
+    #[serde(default)]
+    pub accelerometer: Option<AccelCalibration>,
+}
+
+impl DeviceCalibration {
+    pub fn new(address: u64) -> Self {
+        Self {
+            address,
+            magnetometer: None,
+            gyro_bias: GyroBias::default(),
+            accelerometer: None,
+        }
+    }
+}
+
+/// Hard- and soft-iron correction: `corrected = soft_iron * (raw - offset)`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MagCalibration {
+    /// Hard-iron offset (ellipsoid center), in sensor units
+    pub offset: [f32; 3],
+    /// Symmetric matrix mapping the ellipsoid onto a sphere
+    pub soft_iron: [[f32; 3]; 3],
+    /// Radius of that sphere, i.e. the local field strength
+    pub field_strength: f32,
+    pub quality: FitQuality,
+}
+
+/// How well the samples support a calibration
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FitQuality {
+    /// RMS deviation of corrected magnitudes from the field strength, as a
+    /// fraction of it
+    pub rms_error: f32,
+    /// Fraction of directions the samples cover, 0.0 - 1.0
+    pub coverage: f32,
+    pub samples: usize,
+}
+
+impl FitQuality {
+    /// Good enough to correct the heading with
+    pub fn is_good(&self) -> bool {
+        self.rms_error <= 0.05 && self.coverage >= 0.75
+    }
+}
+
+impl MagCalibration {
+    pub fn apply(&self, raw: [f32; 3]) -> [f32; 3] {
+        let centered = [0, 1, 2].map(|i| raw[i] - self.offset[i]);
+        self.soft_iron
+            .map(|row| row[0] * centered[0] + row[1] * centered[1] + row[2] * centered[2])
+    }
+
+    /// Replace the raw magnetometer reading with the corrected one
+    pub fn apply_to(&self, data: &mut ControllerData) {
+        [data.mag_x, data.mag_y, data.mag_z] = self.apply([data.mag_x, data.mag_y, data.mag_z]);
+    }
+
+    fn evaluate(&self, samples: &[[f32; 3]]) -> FitQuality {
+        let corrected: Vec<[f32; 3]> = samples.iter().map(|&s| self.apply(s)).collect();
+        let squared_error: f32 = corrected
+            .iter()
+            .map(|c| (norm(*c) / self.field_strength - 1.0).powi(2))
+            .sum();
+        FitQuality {
+            rms_error: (squared_error / samples.len() as f32).sqrt(),
+            coverage: coverage(corrected),
+            samples: samples.len(),
+        }
+    }
+}
+
+/// Collects magnetometer samples while the user rotates the controller
+#[derive(Debug, Default)]
+pub struct MagCalibrator {
+    samples: Vec<[f32; 3]>,
+}
+
+impl MagCalibrator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, data: &ControllerData) {
+        self.add_sample([data.mag_x, data.mag_y, data.mag_z]);
+    }
+
+    pub fn add_sample(&mut self, sample: [f32; 3]) {
+        if self.samples.len() >= MAX_MAG_SAMPLES {
+            return;
+        }
+        if let Some(last) = self.samples.last() {
+            let moved = norm([0, 1, 2].map(|i| sample[i] - last[i]));
+            if moved < MIN_SAMPLE_SPACING * norm(*last) {
+                return;
+            }
+        }
+        self.samples.push(sample);
+    }
+
+    pub fn sample_count(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Rough coverage while collecting, before the fit is known: directions
+    /// are taken from the center of the bounding box, per-axis normalized
+    pub fn coverage(&self) -> f32 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+        let mut min = [f32::MAX; 3];
+        let mut max = [f32::MIN; 3];
+        for sample in &self.samples {
+            for i in 0..3 {
+                min[i] = min[i].min(sample[i]);
+                max[i] = max[i].max(sample[i]);
+            }
+        }
+        coverage(self.samples.iter().map(|s| {
+            [0, 1, 2].map(|i| {
+                let half_range = ((max[i] - min[i]) / 2.0).max(f32::EPSILON);
+                (s[i] - (min[i] + max[i]) / 2.0) / half_range
+            })
+        }))
+    }
+
+    pub fn fit(&self) -> anyhow::Result<MagCalibration> {
+        fit_ellipsoid(&self.samples)
+    }
+}
+
+/// Least-squares fit of a general ellipsoid to magnetometer samples
+///
+/// Solves `ax² + by² + cz² + 2dxy + 2exz + 2fyz + 2gx + 2hy + 2iz = 1`, then
+/// rewrites it as `(x - c)ᵀ Q (x - c) = 1`. The soft-iron matrix is the
+/// symmetric square root of Q scaled so that the sphere keeps the
+/// ellipsoid's mean radius; being symmetric, it corrects without rotating.
+pub fn fit_ellipsoid(samples: &[[f32; 3]]) -> anyhow::Result<MagCalibration> {
+    if samples.len() < MIN_MAG_SAMPLES {
+        bail!(
+            "Need at least {} samples, have {}",
+            MIN_MAG_SAMPLES,
+            samples.len()
+        );
+    }
+
+    // Center and scale the samples to keep the normal equations well
+    // conditioned
+    let count = samples.len() as f64;
+    let mut mean = [0.0; 3];
+    for sample in samples {
+        for i in 0..3 {
+            mean[i] += sample[i] as f64 / count;
+        }
+    }
+    let scale = samples
+        .iter()
+        .flat_map(|s| (0..3).map(move |i| (s[i] as f64 - mean[i]).abs()))
+        .fold(0.0, f64::max);
+    if scale <= f64::EPSILON {
+        bail!("Magnetometer readings never change");
+    }
+
+    let mut normal = [[0.0; 9]; 9];
+    let mut rhs = [0.0; 9];
+    for sample in samples {
+        let [x, y, z] = [0, 1, 2].map(|i| (sample[i] as f64 - mean[i]) / scale);
+        let row = [
+            x * x,
+            y * y,
+            z * z,
+            2.0 * x * y,
+            2.0 * x * z,
+            2.0 * y * z,
+            2.0 * x,
+            2.0 * y,
+            2.0 * z,
+        ];
+        for i in 0..9 {
+            rhs[i] += row[i];
+            for j in 0..9 {
+                normal[i][j] += row[i] * row[j];
+            }
+        }
+    }
+    let not_an_ellipsoid =
+        || anyhow!("Samples don't outline an ellipsoid; rotate through more orientations");
+    let p = solve(normal, rhs).ok_or_else(not_an_ellipsoid)?;
+
+    let quadric = [[p[0], p[3], p[4]], [p[3], p[1], p[5]], [p[4], p[5], p[2]]];
+    let (values, vectors) = symmetric_eigen(quadric);
+    if values.iter().any(|&v| v <= 0.0) {
+        return Err(not_an_ellipsoid());
+    }
+
+    // Q c = -[g, h, i], solved through the eigen decomposition
+    let linear = [p[6], p[7], p[8]];
+    let center = compose(&vectors, values.map(|v| -1.0 / v)).map(|row| dot(row, linear));
+    let level = 1.0 + dot(center, linear.map(|l| -l));
+    if level <= 0.0 {
+        return Err(not_an_ellipsoid());
+    }
+
+    // Back to sensor units: x_normalized = (x - mean) / scale
+    let values = values.map(|v| v / level / (scale * scale));
+    let radius = (values[0] * values[1] * values[2]).powf(-1.0 / 6.0);
+    let soft_iron = compose(&vectors, values.map(|v| v.sqrt() * radius));
+
+    let mut calibration = MagCalibration {
+        offset: [0, 1, 2].map(|i| (mean[i] + scale * center[i]) as f32),
+        soft_iron: soft_iron.map(|row| row.map(|v| v as f32)),
+        field_strength: radius as f32,
+        quality: FitQuality {
+            rms_error: 0.0,
+            coverage: 0.0,
+            samples: 0,
+        },
+    };
+    calibration.quality = calibration.evaluate(samples);
+    Ok(calibration)
+}
+
+/// Gyro bias learned at each sensor temperature
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GyroBias {
+    /// Sorted by temperature
+    pub points: Vec<BiasPoint>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BiasPoint {
+    /// Raw sensor temperature reading
+    pub temperature: i16,
+    /// Rate reported at rest, in rad/s
+    pub bias: [f32; 3],
+}
+
+impl GyroBias {
+    /// Bias at a temperature, interpolated between the learned points and
+    /// held at the nearest one outside their range
+    pub fn at(&self, temperature: Option<i16>) -> Option<[f32; 3]> {
+        let (first, last) = (self.points.first()?, self.points.last()?);
+        let Some(t) = temperature else {
+            let count = self.points.len() as f32;
+            return Some(
+                [0, 1, 2].map(|i| self.points.iter().map(|p| p.bias[i]).sum::<f32>() / count),
+            );
+        };
+        if t <= first.temperature {
+            return Some(first.bias);
+        }
+        if t >= last.temperature {
+            return Some(last.bias);
+        }
+        let upper = self.points.iter().position(|p| p.temperature >= t)?;
+        let (a, b) = (self.points[upper - 1], self.points[upper]);
+        let f = (t - a.temperature) as f32 / (b.temperature - a.temperature) as f32;
+        Some([0, 1, 2].map(|i| a.bias[i] + (b.bias[i] - a.bias[i]) * f))
+    }
+
+    /// Move the bias at a temperature towards a measured rest rate
+    fn refine(&mut self, temperature: i16, rate: [f32; 3], weight: f32) {
+        match self
+            .points
+            .binary_search_by_key(&temperature, |p| p.temperature)
+        {
+            Ok(index) => {
+                let bias = &mut self.points[index].bias;
+                for (b, r) in bias.iter_mut().zip(rate) {
+                    *b += (r - *b) * weight;
+                }
+            }
+            Err(index) => self.points.insert(
+                index,
+                BiasPoint {
+                    temperature,
+                    bias: rate,
+                },
+            ),
+        }
+    }
+}
+
+/// Stillness detection and background bias learning
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GyroBiasSettings {
+    /// Learn the bias while the controller is still
+    pub enabled: bool,
+    /// Samples that must all look still
+    pub window_samples: usize,
+    /// Largest gyro variance (rad²/s²) that counts as still
+    pub gyro_variance: f32,
+    /// Largest accelerometer variance, relative to gravity squared
+    pub accel_variance: f32,
+    /// Rates above this (rad/s) are motion, not bias
+    pub max_bias: f32,
+    /// Weight of each still sample in the learned bias
+    pub learning_rate: f32,
+}
+
+impl Default for GyroBiasSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            window_samples: 40,
+            gyro_variance: 0.0004,
+            accel_variance: 0.0004,
+            max_bias: 0.05,
+            learning_rate: 0.01,
+        }
+    }
+}
+
+/// Learns the gyro bias whenever the controller lies still and removes it
+/// from every sample
+pub struct GyroBiasEstimator {
+    settings: GyroBiasSettings,
+    bias: GyroBias,
+    /// Recent (gyro, accel) samples
+    window: VecDeque<([f32; 3], [f32; 3])>,
+    still: bool,
+    /// The bias changed during the current still period
+    refined: bool,
+}
+
+impl GyroBiasEstimator {
+    pub fn new(settings: GyroBiasSettings) -> Self {
+        Self {
+            settings,
+            bias: GyroBias::default(),
+            window: VecDeque::new(),
+            still: false,
+            refined: false,
+        }
+    }
+
+    pub fn set_settings(&mut self, settings: &GyroBiasSettings) {
+        if self.settings != *settings {
+            self.settings = settings.clone();
+        }
+    }
+
+    /// Start from a persisted bias, e.g. after connecting another controller
+    pub fn set_bias(&mut self, bias: GyroBias) {
+        self.bias = bias;
+        self.window.clear();
+        self.still = false;
+        self.refined = false;
+    }
+
+    pub fn bias(&self) -> &GyroBias {
+        &self.bias
+    }
+
+    pub fn is_still(&self) -> bool {
+        self.still
+    }
+
+    /// Feed a raw sample; true when a still period that refined the bias
+    /// just ended, so the bias is worth persisting
+    pub fn update(&mut self, data: &ControllerData) -> bool {
+        let gyro = [data.gyro_x, data.gyro_y, data.gyro_z];
+        let accel = [data.accel_x, data.accel_y, data.accel_z];
+        self.window.push_back((gyro, accel));
+        while self.window.len() > self.settings.window_samples.max(2) {
+            self.window.pop_front();
+        }
+
+        let was_still = self.still;
+        self.still = self.window.len() >= self.settings.window_samples && self.looks_still();
+
+        if self.still && self.settings.enabled {
+            if let Some(temperature) = data.temperature {
+                let (mean, _) = mean_and_variance(self.window.iter().map(|(g, _)| *g));
+                let weight = if was_still {
+                    self.settings.learning_rate
+                } else {
+                    // A whole window of rest is a better estimate than an
+                    // old one made at another point in time
+                    self.settings.learning_rate * self.settings.window_samples as f32
+                };
+                self.bias.refine(temperature, mean, weight.min(1.0));
+                self.refined = true;
+            }
+        }
+
+        if was_still && !self.still && self.refined {
+            self.refined = false;
+            return true;
+        }
+        false
+    }
+
+    /// Subtract the bias for the sample's temperature from its gyro rates
+    pub fn correct(&self, data: &mut ControllerData) {
+        if let Some(bias) = self.bias.at(data.temperature) {
+            data.gyro_x -= bias[0];
+            data.gyro_y -= bias[1];
+            data.gyro_z -= bias[2];
+        }
+    }
+
+    fn looks_still(&self) -> bool {
+        let (gyro_mean, gyro_variance) = mean_and_variance(self.window.iter().map(|(g, _)| *g));
+        let (accel_mean, accel_variance) = mean_and_variance(self.window.iter().map(|(_, a)| *a));
+        let gravity_squared = accel_mean.iter().map(|a| a * a).sum::<f32>();
+        gyro_variance <= self.settings.gyro_variance
+            && accel_variance <= self.settings.accel_variance * gravity_squared
+            && norm(gyro_mean) <= self.settings.max_bias
+    }
+}
+
+/// Per-axis accelerometer correction: `corrected = (raw - offset) * scale`,
+/// in g
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccelCalibration {
+    pub offset: [f32; 3],
+    pub scale: [f32; 3],
+    /// Largest deviation of the corrected gravity from 1 g over the six
+    /// positions
+    pub gravity_error: f32,
+}
+
+impl AccelCalibration {
+    pub fn apply(&self, raw: [f32; 3]) -> [f32; 3] {
+        [0, 1, 2].map(|i| (raw[i] - self.offset[i]) * self.scale[i])
+    }
+}
+
+/// One of the six resting positions: the axis pointing up, and its sign
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Face {
+    pub axis: usize,
+    pub up: bool,
+}
+
+impl Face {
+    pub const ALL: [Face; 6] = [
+        Face { axis: 0, up: true },
+        Face { axis: 0, up: false },
+        Face { axis: 1, up: true },
+        Face { axis: 1, up: false },
+        Face { axis: 2, up: true },
+        Face { axis: 2, up: false },
+    ];
+
+    fn index(self) -> usize {
+        self.axis * 2 + usize::from(!self.up)
+    }
+}
+
+impl std::fmt::Display for Face {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sign = if self.up { '+' } else { '-' };
+        write!(f, "{}{} up", sign, ["X", "Y", "Z"][self.axis])
+    }
+}
+
+/// Six-position accelerometer calibration: captures the average reading
+/// once the controller rests on a face not captured yet
+#[derive(Debug, Default)]
+pub struct AccelCalibrator {
+    window: VecDeque<[f32; 3]>,
+    captured: [Option<[f32; 3]>; 6],
+}
+
+impl AccelCalibrator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a raw sample; returns the face if this completed its capture
+    pub fn add(&mut self, data: &ControllerData) -> Option<Face> {
+        self.window
+            .push_back([data.accel_x, data.accel_y, data.accel_z]);
+        while self.window.len() > ACCEL_WINDOW {
+            self.window.pop_front();
+        }
+        if self.window.len() < ACCEL_WINDOW {
+            return None;
+        }
+
+        let (mean, variance) = mean_and_variance(self.window.iter().copied());
+        let gravity = norm(mean);
+        if variance > ACCEL_REST_VARIANCE * gravity * gravity {
+            return None;
+        }
+        let axis = (0..3).max_by(|&a, &b| mean[a].abs().total_cmp(&mean[b].abs()))?;
+        if mean[axis].abs() < FACE_ALIGNMENT * gravity {
+            return None;
+        }
+        let face = Face {
+            axis,
+            up: mean[axis] > 0.0,
+        };
+        let slot = &mut self.captured[face.index()];
+        if slot.is_some() {
+            return None;
+        }
+        *slot = Some(mean);
+        Some(face)
+    }
+
+    pub fn is_captured(&self, face: Face) -> bool {
+        self.captured[face.index()].is_some()
+    }
+
+    pub fn captured_count(&self) -> usize {
+        self.captured.iter().flatten().count()
+    }
+
+    /// Whether the controller currently rests still enough to capture
+    pub fn is_resting(&self) -> bool {
+        if self.window.len() < ACCEL_WINDOW {
+            return false;
+        }
+        let (mean, variance) = mean_and_variance(self.window.iter().copied());
+        variance <= ACCEL_REST_VARIANCE * norm(mean).powi(2)
+    }
+
+    /// Offset and scale from the six positions, validated against gravity
+    pub fn fit(&self) -> anyhow::Result<AccelCalibration> {
+        let reading = |face: Face| {
+            self.captured[face.index()].ok_or_else(|| anyhow!("{} position is missing", face))
+        };
+
+        let mut offset = [0.0; 3];
+        let mut scale = [1.0; 3];
+        for axis in 0..3 {
+            let plus = reading(Face { axis, up: true })?[axis];
+            let minus = reading(Face { axis, up: false })?[axis];
+            let gain = (plus - minus) / 2.0;
+            if !ACCEL_GAIN_RANGE.contains(&gain) {
+                bail!(
+                    "{} axis reads {:.2} g for gravity; was the controller resting flat?",
+                    ["X", "Y", "Z"][axis],
+                    gain
+                );
+            }
+            offset[axis] = (plus + minus) / 2.0;
+            scale[axis] = 1.0 / gain;
+        }
+
+        let mut calibration = AccelCalibration {
+            offset,
+            scale,
+            gravity_error: 0.0,
+        };
+        for face in Face::ALL {
+            let error = (norm(calibration.apply(reading(face)?)) - 1.0).abs();
+            if error > MAX_GRAVITY_ERROR {
+                bail!(
+                    "Gravity comes out {:.0}% off in the {} position; redo it resting flat",
+                    error * 100.0,
+                    face
+                );
+            }
+            calibration.gravity_error = calibration.gravity_error.max(error);
+        }
+        Ok(calibration)
+    }
+}
+
+/// Applies a controller's calibration to every sample before any other
+/// consumer sees it, and keeps learning its gyro bias
+pub struct ImuPreprocessor {
+    address: Option<u64>,
+    accelerometer: Option<AccelCalibration>,
+    magnetometer: Option<MagCalibration>,
+    gyro_bias: GyroBiasEstimator,
+}
+
+impl ImuPreprocessor {
+    pub fn new(settings: GyroBiasSettings) -> Self {
+        Self {
+            address: None,
+            accelerometer: None,
+            magnetometer: None,
+            gyro_bias: GyroBiasEstimator::new(settings),
+        }
+    }
+
+    pub fn set_settings(&mut self, settings: &GyroBiasSettings) {
+        self.gyro_bias.set_settings(settings);
+    }
+
+    /// Use a controller's stored calibration. The learned gyro bias is only
+    /// taken over when the controller changes, so learning continues.
+    pub fn set_device(&mut self, address: Option<u64>, calibration: Option<&DeviceCalibration>) {
+        if self.address != address {
+            self.address = address;
+            self.gyro_bias
+                .set_bias(calibration.map(|c| c.gyro_bias.clone()).unwrap_or_default());
+        }
+        self.accelerometer = calibration.and_then(|c| c.accelerometer.clone());
+        self.magnetometer = calibration.and_then(|c| c.magnetometer.clone());
+    }
+
+    pub fn gyro_bias(&self) -> &GyroBiasEstimator {
+        &self.gyro_bias
+    }
+
+    /// Whether the magnetometer calibration is good enough to fuse; poor
+    /// fits saved by earlier versions do not count
+    pub fn has_magnetometer_calibration(&self) -> bool {
+        self.magnetometer
+            .as_ref()
+            .is_some_and(|c| c.quality.is_good())
+    }
+
+    /// Correct a raw sample in place; true when the learned gyro bias
+    /// changed enough to persist
+    pub fn process(&mut self, data: &mut ControllerData) -> bool {
+        if let Some(calibration) = &self.accelerometer {
+            [data.accel_x, data.accel_y, data.accel_z] =
+                calibration.apply([data.accel_x, data.accel_y, data.accel_z]);
+        }
+        // Bias learning needs the raw rates
+        let refined = self.gyro_bias.update(data);
+        self.gyro_bias.correct(data);
+        if let Some(calibration) = &self.magnetometer {
+            calibration.apply_to(data);
+        }
+        refined
+    }
+}
+
+/// Per-axis mean and the total variance summed over the axes
+fn mean_and_variance(samples: impl Iterator<Item = [f32; 3]> + Clone) -> ([f32; 3], f32) {
+    let count = samples.clone().count().max(1) as f32;
+    let mut mean = [0.0; 3];
+    for sample in samples.clone() {
+        for (m, v) in mean.iter_mut().zip(sample) {
+            *m += v / count;
+        }
+    }
+    let variance = samples
+        .map(|s| (0..3).map(|i| (s[i] - mean[i]).powi(2)).sum::<f32>())
+        .sum::<f32>()
+        / count;
+    (mean, variance)
+}
+
+/// Fraction of direction bins hit by at least one vector
+fn coverage(vectors: impl IntoIterator<Item = [f32; 3]>) -> f32 {
+    let mut hit = [false; AZIMUTH_BINS * ELEVATION_BINS];
+    for v in vectors {
+        let length = norm(v);
+        if length <= f32::EPSILON {
+            continue;
+        }
+        let azimuth = v[1].atan2(v[0]) / std::f32::consts::TAU + 0.5;
+        let sector = ((azimuth * AZIMUTH_BINS as f32) as usize).min(AZIMUTH_BINS - 1);
+        // z is uniformly distributed over a sphere, so equal z bands have equal area
+        let elevation = (v[2] / length + 1.0) / 2.0;
+        let band = ((elevation * ELEVATION_BINS as f32) as usize).min(ELEVATION_BINS - 1);
+        hit[band * AZIMUTH_BINS + sector] = true;
+    }
+    hit.iter().filter(|&&h| h).count() as f32 / hit.len() as f32
+}
+
+/// Gaussian elimination with partial pivoting; `None` if singular
+fn solve<const N: usize>(mut a: [[f64; N]; N], mut b: [f64; N]) -> Option<[f64; N]> {
+    let magnitude = a.iter().flatten().fold(0.0_f64, |max, v| max.max(v.abs()));
+    for col in 0..N {
+        let pivot = (col..N).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+        if a[pivot][col].abs() <= magnitude * 1e-12 {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+        let pivot_row = a[col];
+        for row in col + 1..N {
+            let factor = a[row][col] / pivot_row[col];
+            for (value, pivot) in a[row][col..].iter_mut().zip(&pivot_row[col..]) {
+                *value -= factor * pivot;
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+    let mut x = [0.0; N];
+    for row in (0..N).rev() {
+        let sum: f64 = (row + 1..N).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - sum) / a[row][row];
+    }
+    Some(x)
+}
+
+/// Eigenvalues and eigenvectors (as columns) of a symmetric 3x3 matrix,
+/// by cyclic Jacobi rotations
+fn symmetric_eigen(mut a: [[f64; 3]; 3]) -> ([f64; 3], [[f64; 3]; 3]) {
+    let mut vectors = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+    for _ in 0..50 {
+        let off_diagonal = a[0][1].powi(2) + a[0][2].powi(2) + a[1][2].powi(2);
+        if off_diagonal <= 1e-30 {
+            break;
+        }
+        for (p, q) in [(0, 1), (0, 2), (1, 2)] {
+            if a[p][q].abs() <= f64::MIN_POSITIVE {
+                continue;
+            }
+            let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+            let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+            let c = 1.0 / (t * t + 1.0).sqrt();
+            let s = t * c;
+            for row in &mut a {
+                let (akp, akq) = (row[p], row[q]);
+                row[p] = c * akp - s * akq;
+                row[q] = s * akp + c * akq;
+            }
+            let (row_p, row_q) = (a[p], a[q]);
+            a[p] = [0, 1, 2].map(|k| c * row_p[k] - s * row_q[k]);
+            a[q] = [0, 1, 2].map(|k| s * row_p[k] + c * row_q[k]);
+            for row in &mut vectors {
+                let (vp, vq) = (row[p], row[q]);
+                row[p] = c * vp - s * vq;
+                row[q] = s * vp + c * vq;
+            }
+        }
+    }
+    ([a[0][0], a[1][1], a[2][2]], vectors)
+}
+
+/// `V diag(d) Vᵀ`
+fn compose(vectors: &[[f64; 3]; 3], diagonal: [f64; 3]) -> [[f64; 3]; 3] {
+    let mut m = [[0.0; 3]; 3];
+    for (i, row) in m.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..3)
+                .map(|k| vectors[i][k] * diagonal[k] * vectors[j][k])
+                .sum();
+        }
+    }
+    m
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn norm(v: [f32; 3]) -> f32 {
+    (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIELD: f32 = 45.0;
+    const HARD_IRON: [f32; 3] = [12.0, -30.0, 7.0];
+    const SOFT_IRON: [[f32; 3]; 3] = [[1.25, 0.08, -0.05], [0.08, 0.85, 0.1], [-0.05, 0.1, 1.1]];
+
+    /// Evenly spread unit vectors (Fibonacci lattice)
+    fn sphere(count: usize) -> Vec<[f32; 3]> {
+        let golden_angle = std::f32::consts::PI * (3.0 - 5.0_f32.sqrt());
+        (0..count)
+            .map(|i| {
+                let z = 1.0 - 2.0 * (i as f32 + 0.5) / count as f32;
+                let r = (1.0 - z * z).sqrt();
+                let (sin, cos) = (golden_angle * i as f32).sin_cos();
+                [r * cos, r * sin, z]
+            })
+            .collect()
+    }
+
+    /// What the sensor reports for a true field direction
+    fn distort(direction: [f32; 3]) -> [f32; 3] {
+        let field = direction.map(|d| d * FIELD);
+        [0, 1, 2].map(|i| {
+            let row = SOFT_IRON[i];
+            row[0] * field[0] + row[1] * field[1] + row[2] * field[2] + HARD_IRON[i]
+        })
+    }
+
+    #[test]
+    fn test_fit_recovers_distorted_sphere() {
+        let directions = sphere(500);
+        let samples: Vec<_> = directions.iter().map(|&d| distort(d)).collect();
+        let calibration = fit_ellipsoid(&samples).unwrap();
+
+        for (found, truth) in calibration.offset.iter().zip(HARD_IRON) {
+            assert!(
+                (found - truth).abs() < 0.01,
+                "offset {:?}",
+                calibration.offset
+            );
+        }
+        // A symmetric distortion is undone without rotating the field
+        for (&direction, &sample) in directions.iter().zip(&samples) {
+            let corrected = calibration.apply(sample);
+            let cosine = (0..3).map(|i| corrected[i] * direction[i]).sum::<f32>() / norm(corrected);
+            assert!(cosine > 0.99999, "direction off by {}", cosine.acos());
+            assert!((norm(corrected) / calibration.field_strength - 1.0).abs() < 1e-3);
+        }
+        assert!(calibration.quality.rms_error < 1e-3);
+        assert_eq!(calibration.quality.coverage, 1.0);
+        assert!(calibration.quality.is_good());
+    }
+
+    #[test]
+    fn test_quality_reflects_noise_and_coverage() {
+        // Upper hemisphere only, with about 2% measurement noise
+        let samples: Vec<_> = sphere(600)
+            .into_iter()
+            .filter(|d| d[2] > 0.0)
+            .enumerate()
+            .map(|(i, d)| {
+                let noise = ((i * 7919) % 101) as f32 / 50.0 - 1.0;
+                distort(d).map(|v| v + noise * 0.03 * FIELD)
+            })
+            .collect();
+        let calibration = fit_ellipsoid(&samples).unwrap();
+
+        assert!(
+            calibration.quality.rms_error > 0.005 && calibration.quality.rms_error < 0.05,
+            "rms error {}",
+            calibration.quality.rms_error
+        );
+        assert!(calibration.quality.coverage < 0.75);
+        assert!(!calibration.quality.is_good());
+
+        // A poor fit is not used for heading correction
+        let mut preprocessor = ImuPreprocessor::new(GyroBiasSettings::default());
+        let device = DeviceCalibration {
+            magnetometer: Some(calibration),
+            ..DeviceCalibration::new(1)
+        };
+        preprocessor.set_device(Some(1), Some(&device));
+        assert!(!preprocessor.has_magnetometer_calibration());
+    }
+
+    #[test]
+    fn test_degenerate_samples_are_rejected() {
+        // Spinning about one axis only traces a flat ring
+        let ring: Vec<_> = (0..200)
+            .map(|i| {
+                let (sin, cos) = (i as f32 * 0.05).sin_cos();
+                distort([cos * 0.8, sin * 0.8, 0.6])
+            })
+            .collect();
+        assert!(fit_ellipsoid(&ring).is_err());
+        assert!(fit_ellipsoid(&ring[..10]).is_err());
+
+        let mut calibrator = MagCalibrator::new();
+        for _ in 0..50 {
+            calibrator.add_sample(distort([1.0, 0.0, 0.0]));
+        }
+        assert_eq!(calibrator.sample_count(), 1);
+    }
+
+    fn imu_sample(gyro: [f32; 3], accel: [f32; 3], temperature: i16) -> ControllerData {
+        ControllerData {
+            gyro_x: gyro[0],
+            gyro_y: gyro[1],
+            gyro_z: gyro[2],
+            accel_x: accel[0],
+            accel_y: accel[1],
+            accel_z: accel[2],
+            temperature: Some(temperature),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_gyro_bias_is_learned_only_at_rest() {
+        let bias = [0.03, -0.012, 0.008];
+        let noise = |i: usize, axis: usize| ((i * 31 + axis * 17) % 13) as f32 / 6.0 - 1.0;
+        let mut estimator = GyroBiasEstimator::new(GyroBiasSettings::default());
+
+        // Waving the controller around teaches nothing
+        for i in 0..200 {
+            let angle = i as f32 * 0.1;
+            let gyro = [angle.sin() * 2.0, angle.cos(), 0.5];
+            let accel = [angle.sin() * 0.5, 0.1, 1.0];
+            assert!(!estimator.update(&imu_sample(gyro, accel, 30)));
+        }
+        assert!(!estimator.is_still());
+        assert_eq!(estimator.bias().at(Some(30)), None);
+
+        // Lying on the table: bias plus sensor noise
+        for i in 0..300 {
+            let gyro = [0, 1, 2].map(|axis| bias[axis] + noise(i, axis) * 0.005);
+            let accel = [0, 1, 2].map(|axis| [0.0, 0.0, 1.0][axis] + noise(i, axis) * 0.003);
+            assert!(!estimator.update(&imu_sample(gyro, accel, 30)));
+        }
+        assert!(estimator.is_still());
+        let learned = estimator.bias().at(Some(30)).unwrap();
+        for axis in 0..3 {
+            assert!((learned[axis] - bias[axis]).abs() < 0.002, "{:?}", learned);
+        }
+
+        // Picking it up ends the still period and asks for persisting
+        assert!(estimator.update(&imu_sample([1.0, 0.0, 0.0], [0.3, 0.0, 1.0], 30)));
+        let mut data = imu_sample(bias, [0.0, 0.0, 1.0], 30);
+        estimator.correct(&mut data);
+        assert!(data.gyro_x.abs() < 0.002 && data.gyro_y.abs() < 0.002);
+    }
+
+    #[test]
+    fn test_slow_steady_turn_is_not_learned_as_bias() {
+        let mut estimator = GyroBiasEstimator::new(GyroBiasSettings::default());
+
+        // Turning about gravity at 0.1 rad/s leaves the accelerometer still
+        for _ in 0..300 {
+            assert!(!estimator.update(&imu_sample([0.1, 0.0, 0.0], [1.0, 0.0, 0.0], 30)));
+        }
+        assert_eq!(estimator.bias().at(Some(30)), None);
+        let mut data = imu_sample([0.1, 0.0, 0.0], [1.0, 0.0, 0.0], 30);
+        estimator.correct(&mut data);
+        assert_eq!(data.gyro_x, 0.1);
+    }
+
+    #[test]
+    fn test_six_position_accel_calibration() {
+        let offset = [0.03, -0.05, 0.02];
+        let gain = [1.04, 0.97, 1.01];
+        let mut calibrator = AccelCalibrator::new();
+        let mut rest = |gravity: [f32; 3]| {
+            let raw = [0, 1, 2].map(|i| gravity[i] * gain[i] + offset[i]);
+            let data = imu_sample([0.0; 3], raw, 30);
+            (0..ACCEL_WINDOW).find_map(|_| calibrator.add(&data))
+        };
+
+        // Tilted halfway between two faces: nothing is captured
+        let diagonal = std::f32::consts::FRAC_1_SQRT_2;
+        assert_eq!(rest([diagonal, 0.0, diagonal]), None);
+        for face in Face::ALL {
+            let mut gravity = [0.0; 3];
+            gravity[face.axis] = if face.up { 1.0 } else { -1.0 };
+            assert_eq!(rest(gravity), Some(face));
+        }
+        assert_eq!(calibrator.captured_count(), 6);
+
+        let calibration = calibrator.fit().unwrap();
+        for axis in 0..3 {
+            assert!((calibration.offset[axis] - offset[axis]).abs() < 1e-4);
+            assert!((calibration.scale[axis] * gain[axis] - 1.0).abs() < 1e-4);
+        }
+        assert!(calibration.gravity_error < 1e-4);
+        let corrected = calibration.apply([0.7 * gain[0] + offset[0], offset[1], offset[2]]);
+        assert!((corrected[0] - 0.7).abs() < 1e-4);
+
+        // A reading of half gravity means it wasn't resting properly
+        let mut calibrator = AccelCalibrator::new();
+        for face in Face::ALL {
+            let mut gravity = [0.0; 3];
+            gravity[face.axis] = if face.up { 1.0 } else { -0.5 };
+            let data = imu_sample([0.0; 3], gravity, 30);
+            for _ in 0..ACCEL_WINDOW {
+                calibrator.add(&data);
+            }
+        }
+        assert!(calibrator.fit().is_err());
+    }
+
+    #[test]
+    fn test_gyro_bias_follows_temperature() {
+        let mut bias = GyroBias::default();
+        bias.refine(40, [0.04, 0.0, -0.02], 1.0);
+        bias.refine(20, [0.0, 0.0, 0.02], 1.0);
+
+        assert_eq!(bias.at(Some(20)), Some([0.0, 0.0, 0.02]));
+        assert_eq!(bias.at(Some(30)), Some([0.02, 0.0, 0.0]));
+        // Held at the nearest point outside the learned range
+        assert_eq!(bias.at(Some(50)), Some([0.04, 0.0, -0.02]));
+        assert_eq!(bias.at(Some(0)), Some([0.0, 0.0, 0.02]));
+        assert_eq!(bias.at(None), Some([0.02, 0.0, 0.0]));
+    }
+}
@@ -0,0 +1,261 @@
+//! Circular Touchpad Motion
+//!
+//! Turns angular travel of the finger around the pad center into discrete
+//! rotation steps, like the click wheel of an iPod. Works on the normalized
+//! `processed_touchpad_x/y` coordinates, where y grows downwards so an
+//! increasing angle is a clockwise motion.
+
+use crate::domain::models::ControllerData;
+use serde::{Deserialize, Serialize};
+
+/// Touches closer to the center than this have no meaningful angle
+const MIN_RADIUS: f64 = 0.35;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Rotation {
+    Clockwise,
+    CounterClockwise,
+}
+
+impl Rotation {
+    fn reversed(self) -> Self {
+        match self {
+            Rotation::Clockwise => Rotation::CounterClockwise,
+            Rotation::CounterClockwise => Rotation::Clockwise,
+        }
+    }
+}
+
+/// Scroll ring configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CircularSettings {
+    pub enabled: bool,
+    /// Angular travel per step (degrees)
+    pub degrees_per_step: f64,
+    /// Swap clockwise and counter-clockwise
+    pub reverse: bool,
+    /// Only touches that start on the outer ring rotate, so the rest of
+    /// the pad keeps its normal function
+    pub edge_ring_only: bool,
+    /// Inner radius of the ring (0-1 of the pad radius)
+    pub ring_radius: f64,
+}
+
+impl Default for CircularSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            degrees_per_step: 20.0,
+            reverse: false,
+            edge_ring_only: true,
+            ring_radius: 0.85,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct CircularMotion {
+    settings: CircularSettings,
+    touching: bool,
+    /// The current touch is handled as circular motion
+    engaged: bool,
+    last_angle: Option<f64>,
+    accumulated: f64,
+    rotated: bool,
+}
+
+impl CircularMotion {
+    pub fn new(settings: CircularSettings) -> Self {
+        Self {
+            settings,
+            ..Self::default()
+        }
+    }
+
+    pub fn set_settings(&mut self, settings: &CircularSettings) {
+        self.settings = settings.clone();
+    }
+
+    /// Whether the current touch has turned the scroll ring, in which case
+    /// it should no longer move the cursor or scroll linearly
+    ///
+    /// Only true once a rotation step fired, so touches that merely start
+    /// on the ring keep their normal function.
+    pub fn is_engaged(&self) -> bool {
+        self.touching && self.rotated
+    }
+
+    /// Whether the current (or last) touch produced rotation steps
+    pub fn has_rotated(&self) -> bool {
+        self.rotated
+    }
+
+    /// Feed a touchpad sample and return the completed rotation steps
+    pub fn process(&mut self, data: &ControllerData) -> Vec<Rotation> {
+        let (x, y) = (data.processed_touchpad_x, data.processed_touchpad_y);
+        let radius = x.hypot(y);
+
+        if !data.touchpad_touched || !self.settings.enabled {
+            self.touching = false;
+            self.engaged = false;
+            self.last_angle = None;
+            self.accumulated = 0.0;
+            return Vec::new();
+        }
+        if !self.touching {
+            self.touching = true;
+            self.rotated = false;
+            self.engaged = !self.settings.edge_ring_only || radius >= self.settings.ring_radius;
+        }
+        if !self.engaged {
+            return Vec::new();
+        }
+
+        let min_radius = if self.settings.edge_ring_only {
+            self.settings.ring_radius
+        } else {
+            MIN_RADIUS
+        };
+        if radius < min_radius {
+            // Crossing the center is not a rotation
+            self.last_angle = None;
+            self.accumulated = 0.0;
+            return Vec::new();
+        }
+
+        let angle = y.atan2(x).to_degrees();
+        let Some(last) = self.last_angle.replace(angle) else {
+            return Vec::new();
+        };
+
+        let mut delta = angle - last;
+        if delta > 180.0 {
+            delta -= 360.0;
+        } else if delta < -180.0 {
+            delta += 360.0;
+        }
+        self.accumulated += delta;
+
+        let step = self.settings.degrees_per_step.max(1.0);
+        let mut steps = Vec::new();
+        while self.accumulated >= step {
+            self.accumulated -= step;
+            steps.push(Rotation::Clockwise);
+        }
+        while self.accumulated <= -step {
+            self.accumulated += step;
+            steps.push(Rotation::CounterClockwise);
+        }
+        if self.settings.reverse {
+            steps.iter_mut().for_each(|r| *r = r.reversed());
+        }
+        self.rotated |= !steps.is_empty();
+        steps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(angle_degrees: f64, radius: f64) -> ControllerData {
+        let angle = angle_degrees.to_radians();
+        ControllerData {
+            touchpad_touched: true,
+            processed_touchpad_x: radius * angle.cos(),
+            processed_touchpad_y: radius * angle.sin(),
+            ..Default::default()
+        }
+    }
+
+    fn settings(degrees_per_step: f64) -> CircularSettings {
+        CircularSettings {
+            degrees_per_step,
+            ..CircularSettings::default()
+        }
+    }
+
+    #[test]
+    fn test_steps_follow_direction_across_wraparound() {
+        let mut motion = CircularMotion::new(settings(30.0));
+        let mut steps = Vec::new();
+
+        // Clockwise from 150 through 180/-180 to 250 degrees
+        for angle in (150..=250).step_by(10) {
+            steps.extend(motion.process(&sample(angle as f64, 0.9)));
+        }
+        assert_eq!(steps, [Rotation::Clockwise; 3]);
+        assert!(motion.has_rotated());
+
+        steps.clear();
+        for angle in (170..=250).rev().step_by(10) {
+            steps.extend(motion.process(&sample(angle as f64, 0.9)));
+        }
+        assert_eq!(steps, [Rotation::CounterClockwise; 2]);
+    }
+
+    #[test]
+    fn test_center_touches_do_not_rotate() {
+        let mut motion = CircularMotion::new(CircularSettings {
+            edge_ring_only: false,
+            ..settings(30.0)
+        });
+        for angle in (0..=360).step_by(10) {
+            assert!(motion.process(&sample(angle as f64, 0.1)).is_empty());
+        }
+        assert!(!motion.has_rotated());
+    }
+
+    #[test]
+    fn test_edge_ring_only_requires_touch_on_ring() {
+        let mut motion = CircularMotion::new(settings(20.0));
+
+        // Starts inside the ring: ignored even when it moves outwards
+        let mut steps = motion.process(&sample(0.0, 0.5));
+        for angle in (0..=90).step_by(10) {
+            steps.extend(motion.process(&sample(angle as f64, 0.9)));
+        }
+        assert!(steps.is_empty());
+        assert!(!motion.is_engaged());
+
+        motion.process(&ControllerData::default());
+        for angle in (0..=90).step_by(10) {
+            steps.extend(motion.process(&sample(angle as f64, 0.9)));
+        }
+        assert_eq!(steps.len(), 4);
+        assert!(motion.is_engaged());
+    }
+
+    #[test]
+    fn test_straight_drag_from_the_rim_is_not_captured() {
+        let mut motion = CircularMotion::new(CircularSettings::default());
+
+        // Drag across the pad starting right on the rim
+        for step in 0..=14 {
+            let x = 0.9 - step as f64 * 0.1;
+            let data = ControllerData {
+                touchpad_touched: true,
+                processed_touchpad_x: x,
+                processed_touchpad_y: 0.05,
+                ..Default::default()
+            };
+            assert!(motion.process(&data).is_empty());
+            // Not engaged, so the touch still moves the cursor
+            assert!(!motion.is_engaged());
+        }
+    }
+
+    #[test]
+    fn test_reverse_swaps_direction() {
+        let mut motion = CircularMotion::new(CircularSettings {
+            reverse: true,
+            ..settings(45.0)
+        });
+        let mut steps = Vec::new();
+        for angle in (0..=50).step_by(10) {
+            steps.extend(motion.process(&sample(angle as f64, 0.9)));
+        }
+        assert_eq!(steps, [Rotation::CounterClockwise]);
+    }
+}
@@ -1,13 +1,22 @@
+use crate::domain::filters::{FilterSettings, PointFilter, SampleTimer};
 use crate::domain::models::ControllerData;
 use crate::domain::settings::SettingsService;
-use std::collections::VecDeque;
+use crate::domain::transfer::SubPixel;
 use std::sync::{Arc, Mutex};
 
+/// Cursor pixels per normalized touchpad unit (half the pad width) at
+/// sensitivity and curve gain 1
+const PIXELS_PER_UNIT: f64 = 100.0;
+/// Joystick cursor speed (pixels per second) at full deflection past the
+/// edge threshold, at sensitivity 1
+const JOYSTICK_PIXELS_PER_SECOND: f64 = 330.0;
+
 pub struct TouchpadProcessor {
     settings: Arc<Mutex<SettingsService>>,
     pub last_processed_pos: Option<(f64, f64)>,
-    delta_buffer_x: VecDeque<f64>,
-    delta_buffer_y: VecDeque<f64>,
+    filter: PointFilter,
+    timer: SampleTimer,
+    sub_pixel: SubPixel,
 }
 
 impl TouchpadProcessor {
@@ -15,8 +24,9 @@ impl TouchpadProcessor {
         Self {
             settings,
             last_processed_pos: None,
-            delta_buffer_x: VecDeque::new(),
-            delta_buffer_y: VecDeque::new(),
+            filter: PointFilter::new(FilterSettings::default()),
+            timer: SampleTimer::default(),
+            sub_pixel: SubPixel::default(),
         }
     }
 
@@ -25,11 +35,12 @@ impl TouchpadProcessor {
         let settings = self.settings.lock().unwrap();
         let calibration = &settings.get().touchpad_calibration;
 
-        // Reset buffers if touch ended
+        // Reset smoothing if touch ended
         if !data.touchpad_touched {
             self.last_processed_pos = None;
-            self.delta_buffer_x.clear();
-            self.delta_buffer_y.clear();
+            self.filter.reset();
+            self.timer.reset();
+            self.sub_pixel.reset();
 
             // Still process coordinates for display/debug
         }
@@ -54,6 +65,12 @@ impl TouchpadProcessor {
         // Clamp to [-1, 1]
         data.processed_touchpad_x = data.processed_touchpad_x.clamp(-1.0, 1.0);
         data.processed_touchpad_y = data.processed_touchpad_y.clamp(-1.0, 1.0);
+
+        // Turn the pad the way the controller is held
+        [data.processed_touchpad_x, data.processed_touchpad_y] = settings
+            .get()
+            .axes
+            .map_touchpad([data.processed_touchpad_x, data.processed_touchpad_y]);
     }
 
     /// Calculate mouse delta from touchpad movement with smoothing, deadzone, and acceleration
@@ -63,53 +80,41 @@ impl TouchpadProcessor {
             return None;
         }
 
-        // Correct for controller orientation (90 degree rotation often seen in Gear VR implementations)
-        // If "Top" area moves it "Left", we need to rotate.
-        // Let's assume standard orientation for now but refine based on user report.
-        let current_x = data.processed_touchpad_x;
-        let current_y = data.processed_touchpad_y;
+        // Positions are already turned to the grip (see `process`)
+        let settings_guard = self.settings.lock().unwrap();
+        let settings = settings_guard.get();
+        let sensitivity = settings_guard.mouse_sensitivity();
+
+        // Smooth the finger position; deltas follow from it
+        let dt = self.timer.interval(data.timestamp);
+        self.filter.set_settings(&settings.touchpad_filter);
+        let [current_x, current_y] = self
+            .filter
+            .filter([data.processed_touchpad_x, data.processed_touchpad_y], dt);
 
         let mut total_dx = 0.0;
         let mut total_dy = 0.0;
 
-        let settings_guard = self.settings.lock().unwrap();
-        let settings = settings_guard.get();
-        let sensitivity = settings.mouse_sensitivity;
-
         // 1. RELATIVE MOVEMENT (Trackpad Mode)
         if let Some((last_x, last_y)) = self.last_processed_pos {
-            let mut rel_dx = current_x - last_x;
-            let mut rel_dy = current_y - last_y;
-
-            // Apply Smoothing to relative movement
-            if settings.enable_smoothing {
-                self.delta_buffer_x.push_back(rel_dx);
-                self.delta_buffer_y.push_back(rel_dy);
-                while self.delta_buffer_x.len() > settings.smoothing_factor {
-                    self.delta_buffer_x.pop_front();
-                    self.delta_buffer_y.pop_front();
-                }
-                rel_dx = self.delta_buffer_x.iter().sum::<f64>() / self.delta_buffer_x.len() as f64;
-                rel_dy = self.delta_buffer_y.iter().sum::<f64>() / self.delta_buffer_y.len() as f64;
-            }
-
-            // Apply Acceleration
-            if settings.enable_acceleration {
-                let power = settings.acceleration_power;
-                rel_dx = rel_dx.signum() * rel_dx.abs().powf(power);
-                rel_dy = rel_dy.signum() * rel_dy.abs().powf(power);
-            }
-
-            let scale_factor = 800.0; // Adjusted for sensitivity
-            total_dx += rel_dx * sensitivity * scale_factor;
-            total_dy += rel_dy * sensitivity * scale_factor;
+            let rel_dx = current_x - last_x;
+            let rel_dy = current_y - last_y;
+
+            // Apply the transfer curve
+            let speed = rel_dx.hypot(rel_dy) / dt as f64;
+            let [dx, dy] = settings
+                .touchpad_transfer
+                .apply([rel_dx, rel_dy], speed, sensitivity);
+            total_dx += dx * PIXELS_PER_UNIT;
+            total_dy += dy * PIXELS_PER_UNIT;
         }
         self.last_processed_pos = Some((current_x, current_y));
 
         // 2. ABSOLUTE MOVEMENT (Joystick Mode)
-        // If finger is held near the edges (abs > 0.7), add continuous movement
+        // If finger is held near the edges (abs > 0.6), add continuous movement
+        // for as long as this sample lasted
         let joy_threshold = 0.6;
-        let joy_speed = 5.0; // Base speed for continuous movement
+        let joy_speed = JOYSTICK_PIXELS_PER_SECOND * dt as f64;
 
         if current_x.abs() > joy_threshold {
             total_dx +=
@@ -120,10 +125,9 @@ impl TouchpadProcessor {
                 current_y.signum() * (current_y.abs() - joy_threshold) * joy_speed * sensitivity;
         }
 
-        if total_dx.abs() < 0.1 && total_dy.abs() < 0.1 {
-            return None;
+        match self.sub_pixel.take([total_dx, total_dy]) {
+            (0, 0) => None,
+            delta => Some(delta),
         }
-
-        Some((total_dx as i32, total_dy as i32))
     }
 }
@@ -0,0 +1,292 @@
+//! Connection Diagnostics
+//!
+//! Timing and outcome of every step of a connection attempt, kept in a
+//! bounded history so failures can be triaged after the fact.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Individual steps of `BleConnection::connect`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConnectionStep {
+    DeviceOpen,
+    GattSession,
+    SystemPairingLookup,
+    PairingCheck,
+    GhostDeviceCleanup,
+    ServiceDiscovery,
+    AccessRequest,
+    CharacteristicDiscovery,
+    NotificationSubscription,
+    InitCommands,
+}
+
+impl ConnectionStep {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConnectionStep::DeviceOpen => "Device open",
+            ConnectionStep::GattSession => "GattSession",
+            ConnectionStep::SystemPairingLookup => "System pairing lookup",
+            ConnectionStep::PairingCheck => "Pairing check",
+            ConnectionStep::GhostDeviceCleanup => "Ghost device cleanup",
+            ConnectionStep::ServiceDiscovery => "Service discovery",
+            ConnectionStep::AccessRequest => "Access request",
+            ConnectionStep::CharacteristicDiscovery => "Characteristic discovery",
+            ConnectionStep::NotificationSubscription => "Notification subscription",
+            ConnectionStep::InitCommands => "Init commands",
+        }
+    }
+}
+
+/// Result of a single step
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepRecord {
+    pub step: ConnectionStep,
+    pub duration_ms: u64,
+    pub success: bool,
+    /// Error message on failure, or extra information (e.g. access status)
+    pub detail: Option<String>,
+}
+
+/// A complete connection attempt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionAttempt {
+    pub address: u64,
+    pub started_at_unix_ms: u64,
+    pub total_ms: u64,
+    pub success: bool,
+    pub error: Option<String>,
+    pub steps: Vec<StepRecord>,
+    #[serde(skip)]
+    started: Option<Instant>,
+}
+
+impl ConnectionAttempt {
+    /// Start timing a new attempt
+    pub fn begin(address: u64) -> Self {
+        let started_at_unix_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+
+        Self {
+            address,
+            started_at_unix_ms,
+            total_ms: 0,
+            success: false,
+            error: None,
+            steps: Vec::new(),
+            started: Some(Instant::now()),
+        }
+    }
+
+    /// Record a step that took `duration`
+    pub fn record(
+        &mut self,
+        step: ConnectionStep,
+        duration: Duration,
+        success: bool,
+        detail: Option<String>,
+    ) {
+        self.steps.push(StepRecord {
+            step,
+            duration_ms: duration.as_millis() as u64,
+            success,
+            detail,
+        });
+    }
+
+    /// Stop timing and store the overall outcome
+    pub fn finish(&mut self, error: Option<String>) {
+        if let Some(started) = self.started.take() {
+            self.total_ms = started.elapsed().as_millis() as u64;
+        }
+        self.success = error.is_none();
+        self.error = error;
+    }
+
+    /// The slowest recorded step, if any
+    pub fn slowest_step(&self) -> Option<&StepRecord> {
+        self.steps.iter().max_by_key(|s| s.duration_ms)
+    }
+
+    /// The first step that failed, if any
+    pub fn first_failure(&self) -> Option<&StepRecord> {
+        self.steps.iter().find(|s| !s.success)
+    }
+}
+
+/// Ring buffer holding the most recent connection attempts
+#[derive(Debug, Clone)]
+pub struct ConnectionHistory {
+    attempts: VecDeque<ConnectionAttempt>,
+    capacity: usize,
+}
+
+impl ConnectionHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            attempts: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Add an attempt, evicting the oldest one when full
+    pub fn push(&mut self, attempt: ConnectionAttempt) {
+        while self.attempts.len() >= self.capacity {
+            self.attempts.pop_front();
+        }
+        self.attempts.push_back(attempt);
+    }
+
+    /// Change the capacity, dropping the oldest attempts if needed
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+        while self.attempts.len() > self.capacity {
+            self.attempts.pop_front();
+        }
+    }
+
+    /// Attempts from newest to oldest
+    pub fn iter_newest_first(&self) -> impl Iterator<Item = &ConnectionAttempt> {
+        self.attempts.iter().rev()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.attempts.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.attempts.clear();
+    }
+
+    /// Export all attempts (oldest first) as pretty JSON
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        let attempts: Vec<&ConnectionAttempt> = self.attempts.iter().collect();
+        Ok(serde_json::to_string_pretty(&attempts)?)
+    }
+
+    /// Export all attempts as a human readable report
+    pub fn to_report(&self) -> String {
+        let mut report = String::from("Gear VR Controller - Connection Diagnostics\n");
+
+        for (i, attempt) in self.iter_newest_first().enumerate() {
+            let _ = writeln!(
+                report,
+                "\n#{} {:#X} at {} ms (unix): {} in {} ms",
+                i + 1,
+                attempt.address,
+                attempt.started_at_unix_ms,
+                if attempt.success { "OK" } else { "FAILED" },
+                attempt.total_ms
+            );
+            if let Some(error) = &attempt.error {
+                let _ = writeln!(report, "   error: {}", error);
+            }
+            for step in &attempt.steps {
+                let _ = writeln!(
+                    report,
+                    "   {:<28} {:>6} ms  {}{}",
+                    step.step.name(),
+                    step.duration_ms,
+                    if step.success { "ok" } else { "FAIL" },
+                    step.detail
+                        .as_ref()
+                        .map(|d| format!(" ({})", d))
+                        .unwrap_or_default()
+                );
+            }
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attempt(address: u64, error: Option<&str>) -> ConnectionAttempt {
+        let mut attempt = ConnectionAttempt::begin(address);
+        attempt.record(
+            ConnectionStep::DeviceOpen,
+            Duration::from_millis(120),
+            true,
+            None,
+        );
+        attempt.record(
+            ConnectionStep::ServiceDiscovery,
+            Duration::from_millis(45),
+            error.is_none(),
+            error.map(str::to_string),
+        );
+        attempt.finish(error.map(str::to_string));
+        attempt.started_at_unix_ms = 1_700_000_000_000;
+        attempt.total_ms = 170;
+        attempt
+    }
+
+    fn addresses(history: &ConnectionHistory) -> Vec<u64> {
+        history.iter_newest_first().map(|a| a.address).collect()
+    }
+
+    #[test]
+    fn test_oldest_attempts_are_evicted() {
+        let mut history = ConnectionHistory::new(3);
+        for address in 1..=5 {
+            history.push(attempt(address, None));
+        }
+        assert_eq!(addresses(&history), [5, 4, 3]);
+
+        history.set_capacity(2);
+        assert_eq!(addresses(&history), [5, 4]);
+        history.push(attempt(6, None));
+        assert_eq!(addresses(&history), [6, 5]);
+
+        // Zero keeps the latest attempt
+        history.set_capacity(0);
+        assert_eq!(addresses(&history), [6]);
+    }
+
+    #[test]
+    fn test_export_formats() {
+        let mut history = ConnectionHistory::new(10);
+        history.push(attempt(0xAB, None));
+        history.push(attempt(0xCD, Some("Service not found")));
+
+        let failed = &history.iter_newest_first().next().unwrap();
+        assert_eq!(
+            failed.first_failure().unwrap().step,
+            ConnectionStep::ServiceDiscovery
+        );
+        assert_eq!(
+            failed.slowest_step().unwrap().step,
+            ConnectionStep::DeviceOpen
+        );
+
+        // JSON lists the attempts oldest first
+        let json: serde_json::Value = serde_json::from_str(&history.to_json().unwrap()).unwrap();
+        assert_eq!(json[0]["address"], 0xAB);
+        assert_eq!(json[1]["error"], "Service not found");
+        assert_eq!(json[1]["steps"][1]["step"], "ServiceDiscovery");
+        assert_eq!(json[1]["steps"][1]["duration_ms"], 45);
+
+        // The report lists them newest first
+        let report = history.to_report();
+        let expected = "\
+Gear VR Controller - Connection Diagnostics
+
+#1 0xCD at 1700000000000 ms (unix): FAILED in 170 ms
+   error: Service not found
+   Device open                     120 ms  ok
+   Service discovery                45 ms  FAIL (Service not found)
+
+#2 0xAB at 1700000000000 ms (unix): OK in 170 ms
+   Device open                     120 ms  ok
+   Service discovery                45 ms  ok
+";
+        assert_eq!(report, expected);
+    }
+}
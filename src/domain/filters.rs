@@ -0,0 +1,347 @@
+//! Signal Filters
+//!
+//! Low-pass filters that take the jitter out of pointer input. A moving
+//! average lags by half its window at every speed. The One Euro filter
+//! (Casiez et al., CHI 2012) instead raises its cutoff frequency with the
+//! speed of the signal: slow, precise movements are smoothed heavily while
+//! fast ones pass with little lag. `min_cutoff` sets the smoothing at rest
+//! and `beta` how quickly it opens up with speed.
+//!
+//! Filters work on positions: the touchpad coordinates, or the integrated
+//! gyro rates for the air mouse.
+
+use crate::domain::models::ControllerData;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::f64::consts::TAU;
+use tracing::warn;
+
+/// Sample interval assumed when the controller timestamps are unusable
+pub const DEFAULT_DT: f32 = 0.015;
+/// Longer gaps (e.g. after a reconnect) are not trusted
+const MAX_DT: f32 = 0.1;
+/// Cutoff (Hz) for the speed estimate that drives the One Euro cutoff
+const DERIVATIVE_CUTOFF: f64 = 1.0;
+/// Samples kept by the tuning preview, about three seconds
+const PREVIEW_SAMPLES: usize = 200;
+/// Unusable intervals in a row before the timer complains
+const FALLBACK_WARN_AFTER: u32 = 100;
+
+/// Time between controller packets, from the controller's own timestamps
+///
+/// Timestamps are in seconds (see `protocol::parse_raw_bytes`). The
+/// controller clock wraps about every 72 minutes; that one negative
+/// interval falls back to `DEFAULT_DT` like any other unusable one, and a
+/// long run of them is logged since motion then no longer follows the
+/// real packet rate.
+#[derive(Debug, Default)]
+pub struct SampleTimer {
+    last_timestamp: Option<f64>,
+    /// Unusable intervals in a row
+    fallbacks: u32,
+}
+
+impl SampleTimer {
+    /// Seconds since the previous packet; `DEFAULT_DT` for the first one and
+    /// for gaps that are negative or too long to trust
+    pub fn interval(&mut self, timestamp: f64) -> f32 {
+        let Some(last) = self.last_timestamp.replace(timestamp) else {
+            return DEFAULT_DT;
+        };
+        let dt = (timestamp - last) as f32;
+        if dt > 0.0 && dt <= MAX_DT {
+            self.fallbacks = 0;
+            return dt;
+        }
+        self.fallbacks += 1;
+        if self.fallbacks == FALLBACK_WARN_AFTER {
+            warn!(
+                "{} controller timestamps in a row unusable (last step {} s), timing with a fixed {} s",
+                FALLBACK_WARN_AFTER, dt, DEFAULT_DT
+            );
+        }
+        DEFAULT_DT
+    }
+
+    pub fn reset(&mut self) {
+        self.last_timestamp = None;
+        self.fallbacks = 0;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FilterKind {
+    Off,
+    MovingAverage,
+    OneEuro,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FilterSettings {
+    pub kind: FilterKind,
+    /// Moving average window, in samples
+    pub window: usize,
+    /// One Euro cutoff frequency at rest, in Hz
+    pub min_cutoff: f64,
+    /// One Euro cutoff increase per unit of speed
+    pub beta: f64,
+}
+
+impl Default for FilterSettings {
+    fn default() -> Self {
+        Self {
+            kind: FilterKind::OneEuro,
+            window: 5,
+            min_cutoff: 1.0,
+            beta: 5.0,
+        }
+    }
+}
+
+impl FilterSettings {
+    /// Air mouse defaults: gyro angles move a few radians per second
+    pub fn imu() -> Self {
+        Self {
+            beta: 2.0,
+            ..Self::default()
+        }
+    }
+}
+
+/// Speed-adaptive low-pass filter for one signal
+#[derive(Debug, Clone)]
+pub struct OneEuroFilter {
+    pub min_cutoff: f64,
+    pub beta: f64,
+    /// Last filtered value and its smoothed speed
+    previous: Option<(f64, f64)>,
+}
+
+impl OneEuroFilter {
+    pub fn new(min_cutoff: f64, beta: f64) -> Self {
+        Self {
+            min_cutoff,
+            beta,
+            previous: None,
+        }
+    }
+
+    /// Filter a sample taken `dt` seconds after the previous one
+    pub fn filter(&mut self, value: f64, dt: f64) -> f64 {
+        let Some((last, last_speed)) = self.previous else {
+            self.previous = Some((value, 0.0));
+            return value;
+        };
+        let speed = lerp(
+            last_speed,
+            (value - last) / dt,
+            smoothing(DERIVATIVE_CUTOFF, dt),
+        );
+        let cutoff = self.min_cutoff + self.beta * speed.abs();
+        let filtered = lerp(last, value, smoothing(cutoff, dt));
+        self.previous = Some((filtered, speed));
+        filtered
+    }
+}
+
+/// Exponential smoothing factor of a first-order low-pass at `cutoff` Hz
+fn smoothing(cutoff: f64, dt: f64) -> f64 {
+    let tau = 1.0 / (TAU * cutoff.max(f64::EPSILON));
+    1.0 / (1.0 + tau / dt)
+}
+
+fn lerp(from: f64, to: f64, t: f64) -> f64 {
+    from + (to - from) * t
+}
+
+#[derive(Debug, Clone)]
+enum FilterState {
+    Off,
+    MovingAverage(VecDeque<[f64; 2]>),
+    OneEuro([OneEuroFilter; 2]),
+}
+
+/// Filters a 2D position with the kind chosen in the settings
+#[derive(Debug, Clone)]
+pub struct PointFilter {
+    settings: FilterSettings,
+    state: FilterState,
+}
+
+impl PointFilter {
+    pub fn new(settings: FilterSettings) -> Self {
+        let state = Self::state_for(&settings);
+        Self { settings, state }
+    }
+
+    pub fn set_settings(&mut self, settings: &FilterSettings) {
+        if self.settings == *settings {
+            return;
+        }
+        if self.settings.kind != settings.kind {
+            self.state = Self::state_for(settings);
+        }
+        if let FilterState::OneEuro(filters) = &mut self.state {
+            for filter in filters {
+                filter.min_cutoff = settings.min_cutoff;
+                filter.beta = settings.beta;
+            }
+        }
+        self.settings = settings.clone();
+    }
+
+    pub fn filter(&mut self, point: [f64; 2], dt: f32) -> [f64; 2] {
+        match &mut self.state {
+            FilterState::Off => point,
+            FilterState::MovingAverage(window) => {
+                window.push_back(point);
+                while window.len() > self.settings.window.max(1) {
+                    window.pop_front();
+                }
+                let count = window.len() as f64;
+                [0, 1].map(|axis| window.iter().map(|p| p[axis]).sum::<f64>() / count)
+            }
+            FilterState::OneEuro(filters) => {
+                [0, 1].map(|axis| filters[axis].filter(point[axis], dt as f64))
+            }
+        }
+    }
+
+    /// Forget the history, e.g. when the finger lifts
+    pub fn reset(&mut self) {
+        self.state = Self::state_for(&self.settings);
+    }
+
+    fn state_for(settings: &FilterSettings) -> FilterState {
+        match settings.kind {
+            FilterKind::Off => FilterState::Off,
+            FilterKind::MovingAverage => FilterState::MovingAverage(VecDeque::new()),
+            FilterKind::OneEuro => FilterState::OneEuro(
+                [0, 1].map(|_| OneEuroFilter::new(settings.min_cutoff, settings.beta)),
+            ),
+        }
+    }
+}
+
+/// Input a filter can be tuned for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterSource {
+    Touchpad,
+    Imu,
+}
+
+/// Raw and filtered history of one source, for tuning the filter live
+pub struct FilterPreview {
+    pub source: FilterSource,
+    /// (raw, filtered) horizontal position
+    history: VecDeque<(f64, f64)>,
+    filter: PointFilter,
+    timer: SampleTimer,
+    /// Integrated gyro angles for the IMU source
+    angle: [f64; 2],
+}
+
+impl FilterPreview {
+    pub fn new(source: FilterSource) -> Self {
+        Self {
+            source,
+            history: VecDeque::new(),
+            filter: PointFilter::new(FilterSettings::default()),
+            timer: SampleTimer::default(),
+            angle: [0.0; 2],
+        }
+    }
+
+    /// Preview another source, starting over
+    pub fn set_source(&mut self, source: FilterSource) {
+        if self.source != source {
+            *self = Self::new(source);
+        }
+    }
+
+    pub fn push(&mut self, data: &ControllerData, settings: &FilterSettings) {
+        let dt = self.timer.interval(data.timestamp);
+        self.filter.set_settings(settings);
+        let raw = match self.source {
+            FilterSource::Touchpad if data.touchpad_touched => {
+                [data.processed_touchpad_x, data.processed_touchpad_y]
+            }
+            FilterSource::Touchpad => {
+                self.filter.reset();
+                return;
+            }
+            FilterSource::Imu => {
+                self.angle[0] += data.gyro_x as f64 * dt as f64;
+                self.angle[1] += data.gyro_y as f64 * dt as f64;
+                self.angle
+            }
+        };
+        let filtered = self.filter.filter(raw, dt);
+        self.history.push_back((raw[0], filtered[0]));
+        while self.history.len() > PREVIEW_SAMPLES {
+            self.history.pop_front();
+        }
+    }
+
+    pub fn history(&self) -> impl Iterator<Item = (f64, f64)> + '_ {
+        self.history.iter().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f64 = 0.01;
+
+    /// Jittery signal: a value plus alternating noise
+    fn jitter(i: usize) -> f64 {
+        if i.is_multiple_of(2) {
+            0.01
+        } else {
+            -0.01
+        }
+    }
+
+    #[test]
+    fn test_one_euro_smooths_at_rest_and_tracks_fast_motion() {
+        // At rest the jitter is mostly removed
+        let mut filter = OneEuroFilter::new(1.0, 5.0);
+        let mut spread: f64 = 0.0;
+        for i in 0..200 {
+            let filtered = filter.filter(0.5 + jitter(i), DT);
+            if i > 100 {
+                spread = spread.max((filtered - 0.5).abs());
+            }
+        }
+        assert!(spread < 0.002, "spread {}", spread);
+
+        // A fast sweep is followed closely, unlike with a moving average of
+        // comparable smoothing
+        let mut filter = OneEuroFilter::new(1.0, 5.0);
+        let mut average = PointFilter::new(FilterSettings {
+            kind: FilterKind::MovingAverage,
+            window: 20,
+            ..FilterSettings::default()
+        });
+        let (mut euro_lag, mut average_lag) = (0.0, 0.0);
+        for i in 0..100 {
+            let value = i as f64 * 0.05; // 5 units per second
+            euro_lag = value - filter.filter(value, DT);
+            average_lag = value - average.filter([value, 0.0], DT as f32)[0];
+        }
+        assert!(euro_lag < 0.05, "lag {}", euro_lag);
+        assert!(euro_lag < average_lag / 5.0);
+    }
+
+    #[test]
+    fn test_sample_timer_uses_controller_timestamps() {
+        let mut timer = SampleTimer::default();
+        assert_eq!(timer.interval(1.0), DEFAULT_DT);
+        assert!((timer.interval(1.005) - 0.005).abs() < 1e-6);
+        // Out of order or after a long gap
+        assert_eq!(timer.interval(1.0), DEFAULT_DT);
+        assert_eq!(timer.interval(5.0), DEFAULT_DT);
+    }
+}
@@ -1,80 +1,160 @@
+//! Touchpad Gestures
+//!
+//! Recognizes taps, double taps, tap-and-hold and directional strokes on the
+//! touchpad. Strokes are split by where they start and how fast they end:
+//! from the rim inwards is an edge swipe, a fast stroke is a flick and
+//! anything else a swipe. Works on the normalized `processed_touchpad_x/y`
+//! coordinates, where y grows downwards.
+
+use crate::domain::buttons::{Clock, SystemClock};
+use crate::domain::input_mapper::InputEvent;
 use crate::domain::models::ControllerData;
-use crate::domain::settings::SettingsService;
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
-use std::f64::consts::PI;
-use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tracing::{debug, trace};
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum GestureDirection {
-    None,
     Up,
     Down,
     Left,
     Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+}
+
+impl GestureDirection {
+    pub const ALL: [GestureDirection; 8] = [
+        GestureDirection::Up,
+        GestureDirection::Down,
+        GestureDirection::Left,
+        GestureDirection::Right,
+        GestureDirection::UpLeft,
+        GestureDirection::UpRight,
+        GestureDirection::DownLeft,
+        GestureDirection::DownRight,
+    ];
+}
+
+/// Stationary touches
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TouchGesture {
+    /// Short touch without movement
+    Tap,
+    /// Second tap shortly after a first one (replaces the second `Tap`)
+    DoubleTap,
+    /// Touch held in place past the hold threshold (suppresses `Tap`)
+    Hold,
+}
+
+/// Recognition thresholds, distances in normalized pad units (range 2.0)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GestureSettings {
+    /// Minimum stroke length for a swipe
+    pub min_swipe_distance: f64,
+    /// Half-width of the cone around each axis (degrees); strokes between
+    /// the cones are diagonals. 45 disables diagonals.
+    pub direction_tolerance: f64,
+    /// Samples at the end of a stroke used to measure its speed
+    pub sample_count: usize,
+    /// End speed (units per second) from which a stroke is a flick
+    pub flick_velocity: f64,
+    /// Strokes starting at least this far from the center are edge swipes
+    pub edge_radius: f64,
+    /// Maximum movement that still counts as a tap or hold
+    pub tap_distance: f64,
+    pub tap_max_ms: u64,
+    pub double_tap_ms: u64,
+    pub hold_ms: u64,
+}
+
+impl Default for GestureSettings {
+    fn default() -> Self {
+        Self {
+            min_swipe_distance: 0.2,
+            direction_tolerance: 30.0,
+            sample_count: 5,
+            flick_velocity: 4.0,
+            edge_radius: 0.8,
+            tap_distance: 0.1,
+            tap_max_ms: 200,
+            double_tap_ms: 300,
+            hold_ms: 600,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
 struct TouchpadPoint {
     x: f64,
     y: f64,
-    is_touched: bool,
+    at: Instant,
 }
 
-pub struct GestureRecognizer {
-    settings: Arc<Mutex<SettingsService>>,
+impl TouchpadPoint {
+    fn distance(&self, other: &TouchpadPoint) -> f64 {
+        (other.x - self.x).hypot(other.y - self.y)
+    }
+}
+
+pub struct GestureRecognizer<C: Clock = SystemClock> {
+    clock: C,
+    settings: GestureSettings,
+    /// Most recent samples of the current touch
     points: VecDeque<TouchpadPoint>,
     start_point: Option<TouchpadPoint>,
-    is_gesture_in_progress: bool,
+    /// Furthest the current touch got from its start
+    max_travel: f64,
+    hold_fired: bool,
+    last_tap_at: Option<Instant>,
+}
 
-    // Constants
-    sample_count: usize,
-    min_gesture_distance: f64,
+impl GestureRecognizer<SystemClock> {
+    pub fn new(settings: GestureSettings) -> Self {
+        Self::with_clock(settings, SystemClock)
+    }
 }
 
-impl GestureRecognizer {
-    pub fn new(settings: Arc<Mutex<SettingsService>>) -> Self {
+impl<C: Clock> GestureRecognizer<C> {
+    pub fn with_clock(settings: GestureSettings, clock: C) -> Self {
         Self {
+            clock,
             settings,
             points: VecDeque::new(),
             start_point: None,
-            is_gesture_in_progress: false,
-            sample_count: 5,
-            min_gesture_distance: 0.2, // Normalized distance (range 2.0)
+            max_travel: 0.0,
+            hold_fired: false,
+            last_tap_at: None,
         }
     }
 
-    fn get_recognition_threshold(&self) -> f64 {
-        if let Ok(settings_guard) = self.settings.lock() {
-            let settings = settings_guard.get();
-            // Scale threshold inversely with sensitivity
-            // Base sensitivity is 2.0.
-            let scale_factor = settings.mouse_sensitivity.max(0.1) / 2.0;
-            self.min_gesture_distance / scale_factor
-        } else {
-            self.min_gesture_distance
+    /// Update the thresholds
+    pub fn set_settings(&mut self, settings: &GestureSettings) {
+        if &self.settings != settings {
+            self.settings = settings.clone();
         }
     }
 
-    pub fn process(&mut self, data: &ControllerData) -> Option<GestureDirection> {
+    /// Feed a touchpad sample, returns the gesture it completed
+    pub fn process(&mut self, data: &ControllerData) -> Option<InputEvent> {
         let point = TouchpadPoint {
             x: data.processed_touchpad_x,
             y: data.processed_touchpad_y,
-            is_touched: data.touchpad_touched,
+            at: self.clock.now(),
         };
 
-        if !self.is_gesture_in_progress && point.is_touched {
-            self.start_gesture(point);
-            None
-        } else if self.is_gesture_in_progress {
-            if point.is_touched {
-                self.update_gesture(point);
+        match (self.start_point, data.touchpad_touched) {
+            (None, true) => {
+                self.start_gesture(point);
                 None
-            } else {
-                self.end_gesture()
             }
-        } else {
-            None
+            (Some(start), true) => self.update_gesture(start, point),
+            (Some(start), false) => self.end_gesture(start, point.at),
+            (None, false) => None,
         }
     }
 
@@ -83,97 +163,230 @@ impl GestureRecognizer {
         self.start_point = Some(point);
         self.points.clear();
         self.points.push_back(point);
-        self.is_gesture_in_progress = true;
+        self.max_travel = 0.0;
+        self.hold_fired = false;
     }
 
-    fn update_gesture(&mut self, point: TouchpadPoint) {
+    fn update_gesture(&mut self, start: TouchpadPoint, point: TouchpadPoint) -> Option<InputEvent> {
         self.points.push_back(point);
-        if self.points.len() > self.sample_count {
+        if self.points.len() > self.settings.sample_count.max(2) {
             self.points.pop_front();
         }
+        self.max_travel = self.max_travel.max(start.distance(&point));
+
+        let held = point.at.duration_since(start.at);
+        if !self.hold_fired
+            && self.max_travel <= self.settings.tap_distance
+            && held >= Duration::from_millis(self.settings.hold_ms)
+        {
+            self.hold_fired = true;
+            self.last_tap_at = None;
+            debug!("Gesture recognized: hold");
+            return Some(InputEvent::Touch(TouchGesture::Hold));
+        }
+        None
     }
 
-    fn end_gesture(&mut self) -> Option<GestureDirection> {
-        let mut result = GestureDirection::None;
+    fn end_gesture(&mut self, start: TouchpadPoint, now: Instant) -> Option<InputEvent> {
+        self.start_point = None;
+        let points: Vec<TouchpadPoint> = self.points.drain(..).collect();
 
-        if self.points.len() >= 2 {
-            if let Some(start) = self.start_point {
-                // Use the last point in buffer as end point
-                if let Some(end) = self.points.back() {
-                    result = self.calculate_direction(start, *end);
-                }
-            }
+        if self.hold_fired {
+            return None;
         }
 
-        if result != GestureDirection::None {
-            debug!("Gesture recognized: {:?}", result);
-        } else {
-            trace!("Gesture ended without recognition (too short or unclear)");
+        let duration = now.duration_since(start.at);
+        if self.max_travel <= self.settings.tap_distance {
+            if duration > Duration::from_millis(self.settings.tap_max_ms) {
+                trace!("Gesture ended without recognition (touch too long)");
+                return None;
+            }
+            let double_tap_window = Duration::from_millis(self.settings.double_tap_ms);
+            let event = match self.last_tap_at.take() {
+                Some(last) if now.duration_since(last) <= double_tap_window => {
+                    TouchGesture::DoubleTap
+                }
+                _ => {
+                    self.last_tap_at = Some(now);
+                    TouchGesture::Tap
+                }
+            };
+            debug!("Gesture recognized: {:?}", event);
+            return Some(InputEvent::Touch(event));
         }
+        self.last_tap_at = None;
 
-        self.is_gesture_in_progress = false;
-        self.points.clear();
+        let end = *points.last()?;
+        let distance = start.distance(&end);
+        if distance < self.settings.min_swipe_distance {
+            trace!(
+                "Gesture rejected: distance {:.2} < threshold {:.2}",
+                distance,
+                self.settings.min_swipe_distance
+            );
+            return None;
+        }
+        let direction = direction(
+            end.x - start.x,
+            end.y - start.y,
+            self.settings.direction_tolerance,
+        );
 
-        if result != GestureDirection::None {
-            Some(result)
+        let from_edge = start.x.hypot(start.y) >= self.settings.edge_radius
+            && end.x.hypot(end.y) < start.x.hypot(start.y);
+        let velocity = end_velocity(&points);
+        let event = if from_edge {
+            InputEvent::EdgeSwipe(direction)
+        } else if velocity >= self.settings.flick_velocity {
+            InputEvent::Flick(direction)
         } else {
-            None
-        }
+            InputEvent::Gesture(direction)
+        };
+
+        debug!(
+            "Gesture check: Dist={:.2}, Velocity={:.2}, Result={}",
+            distance, velocity, event
+        );
+        Some(event)
     }
+}
 
-    fn calculate_direction(&self, start: TouchpadPoint, end: TouchpadPoint) -> GestureDirection {
-        let dx = end.x - start.x;
-        // Invert Y because screen Y (down is positive) vs standard math (up is positive)?
-        // Touchpad Y: 0 (top) to 315 (bottom).
-        // Normalized Y: -1 (top) to 1 (bottom)?
-        // Let's check normalization in controller.rs.
-        // center = 157. (y - center) / range.
-        // If y=0 (top), norm = -1. If y=315 (bottom), norm = 1.
-        // So Y increases downwards.
-        // Math.Atan2(y, x).
-        // If gesture is UP (swiping from bottom to top), end.y < start.y -> dy is negative.
-        // If gesture is DOWN (swiping top to bottom), dy is positive.
-        let dy = end.y - start.y;
+/// Speed over the buffered samples at the end of a stroke (units per second)
+fn end_velocity(points: &[TouchpadPoint]) -> f64 {
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return 0.0;
+    };
+    let elapsed = last.at.duration_since(first.at).as_secs_f64();
+    if elapsed > 0.0 {
+        first.distance(last) / elapsed
+    } else {
+        0.0
+    }
+}
 
-        let distance = (dx * dx + dy * dy).sqrt();
+/// Direction of a stroke; y grows downwards, so an upward swipe has a
+/// negative `dy`
+fn direction(dx: f64, dy: f64, tolerance: f64) -> GestureDirection {
+    let degrees = dy.atan2(dx).to_degrees().rem_euclid(360.0);
+    let tolerance = tolerance.clamp(0.0, 45.0);
+    let near = |axis: f64| {
+        let offset = (degrees - axis).abs();
+        offset.min(360.0 - offset) <= tolerance
+    };
 
-        // TODO: Get sensitivity from settings if needed to scale threshold
-        let threshold = self.get_recognition_threshold();
+    if near(0.0) {
+        GestureDirection::Right
+    } else if near(90.0) {
+        GestureDirection::Down
+    } else if near(180.0) {
+        GestureDirection::Left
+    } else if near(270.0) {
+        GestureDirection::Up
+    } else if degrees < 90.0 {
+        GestureDirection::DownRight
+    } else if degrees < 180.0 {
+        GestureDirection::DownLeft
+    } else if degrees < 270.0 {
+        GestureDirection::UpLeft
+    } else {
+        GestureDirection::UpRight
+    }
+}
 
-        if distance < threshold {
-            trace!(
-                "Gesture rejected: distance {:.2} < threshold {:.2}",
-                distance,
-                threshold
-            );
-            return GestureDirection::None;
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::buttons::FakeClock;
 
-        let angle = dy.atan2(dx);
-        let mut degrees = angle * 180.0 / PI;
+    fn recognizer() -> (GestureRecognizer<FakeClock>, FakeClock) {
+        let clock = FakeClock::new();
+        (
+            GestureRecognizer::with_clock(GestureSettings::default(), clock.clone()),
+            clock,
+        )
+    }
 
-        if degrees < 0.0 {
-            degrees += 360.0;
+    fn touch(x: f64, y: f64) -> ControllerData {
+        ControllerData {
+            touchpad_touched: true,
+            processed_touchpad_x: x,
+            processed_touchpad_y: y,
+            ..Default::default()
         }
+    }
 
-        let tolerance = 30.0; // +/- 30 degrees (total 60 degree cone)
+    /// Stroke from `from` to `to` in ten samples `interval_ms` apart
+    fn stroke(
+        recognizer: &mut GestureRecognizer<FakeClock>,
+        clock: &FakeClock,
+        from: (f64, f64),
+        to: (f64, f64),
+        interval_ms: u64,
+    ) -> Option<InputEvent> {
+        for i in 0..=10 {
+            let t = i as f64 / 10.0;
+            let x = from.0 + (to.0 - from.0) * t;
+            let y = from.1 + (to.1 - from.1) * t;
+            assert_eq!(recognizer.process(&touch(x, y)), None);
+            clock.advance(interval_ms);
+        }
+        recognizer.process(&ControllerData::default())
+    }
 
-        let direction = if degrees >= (360.0 - tolerance) || degrees < tolerance {
-            GestureDirection::Right
-        } else if degrees >= (90.0 - tolerance) && degrees < (90.0 + tolerance) {
-            GestureDirection::Down
-        } else if degrees >= (180.0 - tolerance) && degrees < (180.0 + tolerance) {
-            GestureDirection::Left
-        } else if degrees >= (270.0 - tolerance) && degrees < (270.0 + tolerance) {
-            GestureDirection::Up
-        } else {
-            GestureDirection::None
+    #[test]
+    fn test_tap_double_tap_and_hold() {
+        let (mut recognizer, clock) = recognizer();
+        let mut tap = || {
+            recognizer.process(&touch(0.0, 0.0));
+            clock.advance(50);
+            let event = recognizer.process(&ControllerData::default());
+            clock.advance(100);
+            event
         };
+        assert_eq!(tap(), Some(InputEvent::Touch(TouchGesture::Tap)));
+        assert_eq!(tap(), Some(InputEvent::Touch(TouchGesture::DoubleTap)));
+        assert_eq!(tap(), Some(InputEvent::Touch(TouchGesture::Tap)));
 
-        debug!(
-            "Gesture check: Dist={:.2}, Angle={:.1}°, Result={:?}",
-            distance, degrees, direction
+        clock.advance(1000);
+        recognizer.process(&touch(0.0, 0.0));
+        clock.advance(700);
+        assert_eq!(
+            recognizer.process(&touch(0.02, 0.0)),
+            Some(InputEvent::Touch(TouchGesture::Hold))
+        );
+        assert_eq!(recognizer.process(&ControllerData::default()), None);
+    }
+
+    #[test]
+    fn test_swipe_flick_and_edge_swipe() {
+        let (mut recognizer, clock) = recognizer();
+
+        // 0.6 units in 200 ms is 3 units/s
+        assert_eq!(
+            stroke(&mut recognizer, &clock, (0.0, 0.3), (0.0, -0.3), 20),
+            Some(InputEvent::Gesture(GestureDirection::Up))
+        );
+        assert_eq!(
+            stroke(&mut recognizer, &clock, (-0.3, -0.3), (0.2, 0.2), 5),
+            Some(InputEvent::Flick(GestureDirection::DownRight))
         );
-        direction
+        assert_eq!(
+            stroke(&mut recognizer, &clock, (0.9, 0.0), (0.3, 0.0), 20),
+            Some(InputEvent::EdgeSwipe(GestureDirection::Left))
+        );
+    }
+
+    #[test]
+    fn test_direction_cones() {
+        assert_eq!(direction(1.0, 0.3, 22.5), GestureDirection::Right);
+        assert_eq!(direction(-1.0, -0.8, 22.5), GestureDirection::UpLeft);
+        assert_eq!(direction(0.5, -1.0, 22.5), GestureDirection::UpRight);
+        // A 45 degree tolerance leaves no room for diagonals
+        assert_eq!(direction(-1.0, -0.8, 45.0), GestureDirection::Left);
+
+        // The default keeps the classic 30 degree cones, so a stroke 27
+        // degrees off the axis is still a plain swipe
+        let tolerance = GestureSettings::default().direction_tolerance;
+        assert_eq!(direction(1.0, -0.5, tolerance), GestureDirection::Right);
     }
 }
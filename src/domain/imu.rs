@@ -1,141 +1,145 @@
 //! IMU (Inertial Measurement Unit) Processor
 //!
-//! Processes gyroscope and accelerometer data for air-mouse style control.
+//! Processes gyroscope and accelerometer data for air-mouse style control,
+//! and maps the fused orientation onto the screen for laser pointing.
 
+use crate::domain::filters::{FilterSettings, PointFilter, SampleTimer};
 use crate::domain::models::ControllerData;
+use crate::domain::orientation::Orientation;
 use crate::domain::settings::SettingsService;
+use crate::domain::transfer::SubPixel;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
 use std::sync::{Arc, Mutex};
 
+/// Cursor pixels per radian of rotation at sensitivity and curve gain 1
+const PIXELS_PER_RADIAN: f64 = 3300.0;
+/// Rotation rate (rad/s) taken off every movement to hide sensor noise
+/// left after bias correction
+const GYRO_DEAD_ZONE: f32 = 0.03;
+/// Pointing direction in body coordinates. In the usual grip body x points
+/// down and y to the left (the air mouse turns about x and y), so z runs
+/// out of the controller's tip.
+const POINTER_FORWARD: [f32; 3] = [0.0, 0.0, 1.0];
+
+/// Laser pointer configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PointerSettings {
+    /// Horizontal angle (degrees) covered by the screen; the vertical one
+    /// follows from the screen's aspect ratio
+    pub fov_degrees: f32,
+    /// Span the whole virtual desktop instead of the primary monitor
+    pub all_monitors: bool,
+}
+
+impl Default for PointerSettings {
+    fn default() -> Self {
+        Self {
+            fov_degrees: 40.0,
+            all_monitors: false,
+        }
+    }
+}
+
+/// Screen area in virtual desktop pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScreenBounds {
+    pub left: i32,
+    pub top: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
 /// IMU Processor for air-mouse and motion-based control
 pub struct ImuProcessor {
     settings: Arc<Mutex<SettingsService>>,
 
-    // Calibration offsets (gyro drift compensation)
-    gyro_offset_x: f32,
-    gyro_offset_y: f32,
-    gyro_offset_z: f32,
-
-    // Accumulated rotation for absolute positioning (optional)
+    // Pointing direction relative to the center, for absolute positioning
     accumulated_yaw: f32,
     accumulated_pitch: f32,
-
-    // Smoothing buffers
-    gyro_buffer_x: Vec<f32>,
-    gyro_buffer_y: Vec<f32>,
-    buffer_size: usize,
-
-    // Calibration state
-    calibration_samples: Vec<(f32, f32, f32)>,
-    is_calibrating: bool,
-    calibration_target: usize,
+    /// Yaw and pitch that point at the screen center, `None` to take the
+    /// next orientation
+    pointer_center: Option<(f32, f32)>,
+    last_pointer_pos: Option<(i32, i32)>,
+
+    // Smoothing: the filter runs on the integrated gyro angles, whose
+    // change per sample is the smoothed rate
+    filter: PointFilter,
+    timer: SampleTimer,
+    gyro_angle: [f64; 2],
+    filtered_angle: Option<[f64; 2]>,
+    sub_pixel: SubPixel,
 }
 
 impl ImuProcessor {
     pub fn new(settings: Arc<Mutex<SettingsService>>) -> Self {
         Self {
             settings,
-            gyro_offset_x: 0.0,
-            gyro_offset_y: 0.0,
-            gyro_offset_z: 0.0,
             accumulated_yaw: 0.0,
             accumulated_pitch: 0.0,
-            gyro_buffer_x: Vec::new(),
-            gyro_buffer_y: Vec::new(),
-            buffer_size: 3,
-            calibration_samples: Vec::new(),
-            is_calibrating: false,
-            calibration_target: 50, // 50 samples for calibration
+            pointer_center: None,
+            last_pointer_pos: None,
+            filter: PointFilter::new(FilterSettings::imu()),
+            timer: SampleTimer::default(),
+            gyro_angle: [0.0; 2],
+            filtered_angle: None,
+            sub_pixel: SubPixel::default(),
         }
     }
 
-    /// Start gyro calibration - controller should be still
-    pub fn start_calibration(&mut self) {
-        self.calibration_samples.clear();
-        self.is_calibrating = true;
-        tracing::info!("IMU Calibration started - keep controller still");
-    }
-
-    /// Check if calibration is complete
-    pub fn is_calibrating(&self) -> bool {
-        self.is_calibrating
-    }
-
-    /// Get calibration progress (0.0 to 1.0)
-    pub fn calibration_progress(&self) -> f32 {
-        self.calibration_samples.len() as f32 / self.calibration_target as f32
-    }
-
     /// Process IMU data and return mouse delta for air-mouse mode
     pub fn calculate_airmouse_delta(&mut self, data: &ControllerData) -> Option<(i32, i32)> {
-        // Handle calibration
-        if self.is_calibrating {
-            self.calibration_samples
-                .push((data.gyro_x, data.gyro_y, data.gyro_z));
-
-            if self.calibration_samples.len() >= self.calibration_target {
-                self.finish_calibration();
-            }
-            return None;
-        }
-
-        // Apply calibration offset
-        let gyro_x = data.gyro_x - self.gyro_offset_x;
-        let gyro_y = data.gyro_y - self.gyro_offset_y;
-        let _gyro_z = data.gyro_z - self.gyro_offset_z;
+        // Gyro bias is already removed by the GyroBiasEstimator
+        let gyro_x = data.gyro_x;
+        let gyro_y = data.gyro_y;
 
         // For air-mouse:
-        // - Gyro Y (pitch) controls vertical mouse movement
-        // - Gyro Z (yaw) controls horizontal mouse movement
-        // Controller orientation matters - adjust mapping based on how user holds it
+        // - Gyro X controls horizontal mouse movement
+        // - Gyro Y controls vertical mouse movement
+        // Inverted per axis to suit the grip (AxisSettings)
 
-        // Get sensitivity from settings
-        let sensitivity = {
+        // Get sensitivity, curve, smoothing and axes from settings
+        let (sensitivity, transfer, filter, axes) = {
             let s = self.settings.lock().unwrap();
-            s.get().mouse_sensitivity
+            (
+                s.mouse_sensitivity(),
+                s.get().imu_transfer.clone(),
+                s.get().imu_filter.clone(),
+                s.get().axes.clone(),
+            )
         };
 
         // Apply smoothing
-        self.gyro_buffer_x.push(gyro_x);
-        self.gyro_buffer_y.push(gyro_y);
-
-        while self.gyro_buffer_x.len() > self.buffer_size {
-            self.gyro_buffer_x.remove(0);
-            self.gyro_buffer_y.remove(0);
-        }
-
-        let smoothed_x: f32 =
-            self.gyro_buffer_x.iter().sum::<f32>() / self.gyro_buffer_x.len() as f32;
-        let smoothed_y: f32 =
-            self.gyro_buffer_y.iter().sum::<f32>() / self.gyro_buffer_y.len() as f32;
-
-        // Dead zone to filter noise
-        let dead_zone = 0.5; // Adjust based on gyro noise level
-        let dx = if smoothed_x.abs() > dead_zone {
-            smoothed_x
-        } else {
-            0.0
-        };
-        let dy = if smoothed_y.abs() > dead_zone {
-            smoothed_y
-        } else {
-            0.0
-        };
-
-        if dx.abs() < 0.01 && dy.abs() < 0.01 {
+        let dt = self.timer.interval(data.timestamp);
+        self.filter.set_settings(&filter);
+        self.gyro_angle[0] += gyro_x as f64 * dt as f64;
+        self.gyro_angle[1] += gyro_y as f64 * dt as f64;
+        let filtered = self.filter.filter(self.gyro_angle, dt);
+        let previous = self.filtered_angle.replace(filtered).unwrap_or(filtered);
+        let smoothed_x = ((filtered[0] - previous[0]) / dt as f64) as f32;
+        let smoothed_y = ((filtered[1] - previous[1]) / dt as f64) as f32;
+
+        // Soft dead zone: subtracted from the rate, so slow turns start
+        // smoothly from zero instead of jumping in at the threshold
+        let rate = smoothed_x.hypot(smoothed_y);
+        if rate <= GYRO_DEAD_ZONE {
             return None;
         }
-
-        // Scale factor for converting gyro units to pixels
-        // Gyro values are in radians/second after scaling
-        // Typical gyro range: -2000 to +2000 deg/s raw, scaled down
-        let scale = 50.0 * sensitivity as f32;
-
-        // Map gyro axes to mouse axes
-        // This mapping may need adjustment based on controller orientation
-        let mouse_dx = (dx * scale) as i32;
-        let mouse_dy = (dy * scale) as i32;
-
-        Some((mouse_dx, mouse_dy))
+        let scale = (rate - GYRO_DEAD_ZONE) / rate;
+        let (dx, dy) = (smoothed_x * scale, smoothed_y * scale);
+
+        // Gyro values are in radians/second; the curve's speed is the rate
+        let [dx, dy] = axes.map_imu([dx as f64, dy as f64]);
+        let [dx, dy] = transfer.apply([dx, dy], dx.hypot(dy), sensitivity);
+
+        // The rotation during this sample (rate * dt) moves the cursor,
+        // whatever the packet rate
+        let scale = PIXELS_PER_RADIAN * dt as f64;
+        match self.sub_pixel.take([dx * scale, dy * scale]) {
+            (0, 0) => None,
+            delta => Some(delta),
+        }
     }
 
     /// Process IMU for tilt-based scrolling
@@ -158,51 +162,179 @@ impl ImuProcessor {
         }
     }
 
-    /// Detect shake gesture using accelerometer
-    pub fn detect_shake(&mut self, data: &ControllerData) -> bool {
-        // Calculate acceleration magnitude
-        let magnitude = (data.accel_x * data.accel_x
-            + data.accel_y * data.accel_y
-            + data.accel_z * data.accel_z)
-            .sqrt();
-
-        // Shake threshold (significantly above gravity ~1.0)
-        let shake_threshold = 2.5;
-
-        magnitude > shake_threshold
+    /// Cursor position for laser pointing, `None` if it did not move
+    ///
+    /// The heading and elevation of the pointing direction relative to the
+    /// center direction are mapped linearly through the field of view;
+    /// pointing beyond it pins the cursor to the screen edge. They come from
+    /// the controller's forward axis in the earth frame rather than from the
+    /// Euler angles, which are degenerate in this grip (body x is vertical,
+    /// so the Euler pitch sits near +/-90 degrees).
+    pub fn calculate_pointer_position(
+        &mut self,
+        orientation: &Orientation,
+        settings: &PointerSettings,
+        bounds: ScreenBounds,
+    ) -> Option<(i32, i32)> {
+        let forward = orientation.quaternion.rotate(POINTER_FORWARD);
+        // Counter-clockwise from above, and positive when pointing down
+        let heading = forward[1].atan2(forward[0]);
+        let elevation = -forward[2].clamp(-1.0, 1.0).asin();
+        let (center_yaw, center_pitch) = *self.pointer_center.get_or_insert((heading, elevation));
+
+        // Yaw wraps around at +/-180 degrees
+        let yaw = (heading - center_yaw + PI).rem_euclid(2.0 * PI) - PI;
+        let pitch = elevation - center_pitch;
+        // Cursor axes: right is negative yaw
+        let axes = self.settings.lock().unwrap().get().axes.clone();
+        let [x, y] = axes.map_imu([-yaw as f64, pitch as f64]);
+        self.accumulated_yaw = -x as f32;
+        self.accumulated_pitch = y as f32;
+
+        let position = pointer_position(
+            self.accumulated_yaw,
+            self.accumulated_pitch,
+            settings.fov_degrees,
+            bounds,
+        );
+        if self.last_pointer_pos.replace(position) == Some(position) {
+            return None;
+        }
+        Some(position)
     }
 
     /// Reset accumulated rotation (re-center)
+    ///
+    /// The laser pointer takes the next orientation as the screen center.
     pub fn reset_orientation(&mut self) {
         self.accumulated_yaw = 0.0;
         self.accumulated_pitch = 0.0;
+        self.pointer_center = None;
+        self.last_pointer_pos = None;
         tracing::info!("IMU orientation reset");
     }
+}
 
-    fn finish_calibration(&mut self) {
-        if self.calibration_samples.is_empty() {
-            self.is_calibrating = false;
-            return;
-        }
+/// Screen pixel for a direction relative to the center (radians). Yaw is
+/// counter-clockwise seen from above, so positive yaw points left; positive
+/// pitch points down.
+fn pointer_position(yaw: f32, pitch: f32, fov_degrees: f32, bounds: ScreenBounds) -> (i32, i32) {
+    let width = bounds.width.max(1) as f32;
+    let height = bounds.height.max(1) as f32;
+    let horizontal_fov = fov_degrees.clamp(1.0, 179.0).to_radians();
+    let vertical_fov = horizontal_fov * height / width;
+
+    let x = width / 2.0 - yaw / horizontal_fov * width;
+    let y = height / 2.0 + pitch / vertical_fov * height;
+    (
+        bounds.left + (x.round() as i32).clamp(0, bounds.width.max(1) - 1),
+        bounds.top + (y.round() as i32).clamp(0, bounds.height.max(1) - 1),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pointer_maps_field_of_view_onto_bounds() {
+        // Secondary monitor left of the primary one
+        let bounds = ScreenBounds {
+            left: -1920,
+            top: 0,
+            width: 1920,
+            height: 1080,
+        };
+        let fov = 40.0;
+        let half_fov = 20.0_f32.to_radians();
+
+        assert_eq!(pointer_position(0.0, 0.0, fov, bounds), (-960, 540));
+        // Pointing left and up by half the field of view hits the corner
+        assert_eq!(
+            pointer_position(half_fov, -half_fov * 1080.0 / 1920.0, fov, bounds),
+            (-1920, 0)
+        );
+        // Beyond the field of view the cursor stays on the edge
+        assert_eq!(pointer_position(-1.0, 1.0, fov, bounds), (-1, 1079));
+    }
+
+    #[test]
+    fn test_slow_turns_move_the_cursor() {
+        use crate::domain::settings::{Settings, SettingsService};
+
+        // Total cursor travel turning at `rate` rad/s for one second
+        let travel = |rate: f32| {
+            let settings = SettingsService::in_memory(Settings::default());
+            let mut imu = ImuProcessor::new(Arc::new(Mutex::new(settings)));
+            let mut total = 0;
+            for i in 0..100 {
+                let data = ControllerData {
+                    gyro_x: rate,
+                    timestamp: i as f64 * 0.01,
+                    ..ControllerData::default()
+                };
+                if let Some((dx, _)) = imu.calculate_airmouse_delta(&data) {
+                    total += dx;
+                }
+            }
+            total
+        };
 
-        // Calculate average offset
-        let count = self.calibration_samples.len() as f32;
-        let sum_x: f32 = self.calibration_samples.iter().map(|(x, _, _)| x).sum();
-        let sum_y: f32 = self.calibration_samples.iter().map(|(_, y, _)| y).sum();
-        let sum_z: f32 = self.calibration_samples.iter().map(|(_, _, z)| z).sum();
+        // Noise stays put, a slow, deliberate turn moves
+        assert_eq!(travel(0.02), 0);
+        let slow = travel(0.1);
+        assert!(slow > 0, "moved {}", slow);
+        assert!(travel(0.2) > slow * 2);
+    }
 
-        self.gyro_offset_x = sum_x / count;
-        self.gyro_offset_y = sum_y / count;
-        self.gyro_offset_z = sum_z / count;
+    #[test]
+    fn test_pointer_follows_fused_orientation_in_the_grip() {
+        use crate::domain::orientation::{FusionSettings, OrientationEstimator, Quaternion};
+        use crate::domain::settings::{Settings, SettingsService};
+
+        const DT: f32 = 0.01;
+        let bounds = ScreenBounds {
+            left: 0,
+            top: 0,
+            width: 1920,
+            height: 1080,
+        };
+        let pointer = PointerSettings::default();
+
+        // Turn (body rates, rad/s) for 0.3 s after pointing level at the
+        // center, then read the cursor
+        let point = |rate: [f32; 3]| {
+            let settings = SettingsService::in_memory(Settings::default());
+            let mut imu = ImuProcessor::new(Arc::new(Mutex::new(settings)));
+            let mut estimator = OrientationEstimator::new(FusionSettings::default());
+            // Held level: body x points down, y left, z forward (north)
+            let mut truth = Quaternion::from_axis_angle([0.0, 1.0, 0.0], PI / 2.0);
+            let mut position = None;
+            for i in 0..130 {
+                let gyro = if i < 100 { [0.0; 3] } else { rate };
+                let speed = gyro.iter().map(|g| g * g).sum::<f32>().sqrt();
+                let step = Quaternion::from_axis_angle(gyro, speed * DT);
+                truth = (truth * step).normalized();
+                let accel = truth.conjugate().rotate([0.0, 0.0, 1.0]);
+                let orientation = estimator.update_with(gyro, accel, None, DT);
+                if let Some(p) = imu.calculate_pointer_position(&orientation, &pointer, bounds) {
+                    position = Some(p);
+                }
+            }
+            position.unwrap()
+        };
 
-        self.is_calibrating = false;
-        self.calibration_samples.clear();
+        let (x, y) = point([0.0; 3]);
+        assert!((x - 960).abs() <= 2 && (y - 540).abs() <= 2, "{:?}", (x, y));
 
-        tracing::info!(
-            "IMU Calibration complete. Offsets: ({:.4}, {:.4}, {:.4})",
-            self.gyro_offset_x,
-            self.gyro_offset_y,
-            self.gyro_offset_z
-        );
+        // Turning right (positive gyro X, as for the air mouse)
+        let (x, y) = point([1.0, 0.0, 0.0]);
+        assert!(x > 1300, "{:?}", (x, y));
+        assert!((y - 540).abs() < 20, "{:?}", (x, y));
+
+        // Tipping up (negative gyro Y)
+        let (x, y) = point([0.0, -1.0, 0.0]);
+        assert!(y < 200, "{:?}", (x, y));
+        assert!((x - 960).abs() < 20, "{:?}", (x, y));
     }
 }
@@ -0,0 +1,619 @@
+//! Input Mapping
+//!
+//! Resolves controller events to actions through a binding table stored in
+//! the settings. A binding applies either to one control mode or to all of
+//! them; mode-specific bindings take precedence.
+
+use crate::domain::actions::{Action, Key, MouseButton};
+use crate::domain::circular::Rotation;
+use crate::domain::gestures::{GestureDirection, TouchGesture};
+use crate::domain::media::MediaCommand;
+use crate::domain::models::ControlMode;
+use crate::domain::motion::MotionGesture;
+use crate::domain::settings::SettingsService;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Version of the defaults in `BindingTable::default`; bump it when a
+/// feature adds default bindings and teach `BindingTable::migrate` the step
+pub const BINDINGS_VERSION: u32 = 1;
+
+/// Physical buttons of the controller
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum ControllerButton {
+    Trigger,
+    TouchpadButton,
+    Back,
+    Home,
+    VolumeUp,
+    VolumeDown,
+}
+
+/// How a button event was produced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ButtonTrigger {
+    Press,
+    Release,
+    /// Short press and release
+    Tap,
+    /// Second tap shortly after a first one (replaces the second `Tap`)
+    DoubleTap,
+    /// Held past the long press threshold (suppresses `Tap`)
+    LongPress,
+    /// Fires on press and then repeatedly while held
+    Repeat,
+}
+
+/// Controller event that can be bound to actions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InputEvent {
+    Button(ControllerButton, ButtonTrigger),
+    /// Two buttons pressed together, in `ControllerButton` order
+    Chord(ControllerButton, ControllerButton),
+    /// Touchpad swipe
+    Gesture(GestureDirection),
+    /// Fast, short swipe; falls back to the `Gesture` binding if unbound
+    Flick(GestureDirection),
+    /// Swipe starting on the rim of the touchpad; falls back to the
+    /// `Gesture` binding if unbound
+    EdgeSwipe(GestureDirection),
+    Touch(TouchGesture),
+    /// Shape drawn on the touchpad, by `Shape::id`
+    Shape(u32),
+    /// Gesture made by moving the controller
+    Motion(MotionGesture),
+    /// One step of circular motion around the touchpad center
+    Circle(Rotation),
+}
+
+impl InputEvent {
+    /// Events offered when creating a binding in the UI
+    pub fn bindable() -> Vec<InputEvent> {
+        let mut events = Vec::new();
+        for button in ControllerButton::ALL {
+            for trigger in [
+                ButtonTrigger::Press,
+                ButtonTrigger::Release,
+                ButtonTrigger::Tap,
+                ButtonTrigger::DoubleTap,
+                ButtonTrigger::LongPress,
+                ButtonTrigger::Repeat,
+            ] {
+                events.push(InputEvent::Button(button, trigger));
+            }
+        }
+        for (i, first) in ControllerButton::ALL.iter().enumerate() {
+            for second in &ControllerButton::ALL[i + 1..] {
+                events.push(InputEvent::Chord(*first, *second));
+            }
+        }
+        for gesture in [
+            TouchGesture::Tap,
+            TouchGesture::DoubleTap,
+            TouchGesture::Hold,
+        ] {
+            events.push(InputEvent::Touch(gesture));
+        }
+        for direction in GestureDirection::ALL {
+            events.push(InputEvent::Gesture(direction));
+        }
+        for direction in GestureDirection::ALL {
+            events.push(InputEvent::Flick(direction));
+        }
+        for direction in GestureDirection::ALL {
+            events.push(InputEvent::EdgeSwipe(direction));
+        }
+        for rotation in [Rotation::Clockwise, Rotation::CounterClockwise] {
+            events.push(InputEvent::Circle(rotation));
+        }
+        for gesture in MotionGesture::ALL {
+            events.push(InputEvent::Motion(gesture));
+        }
+        events
+    }
+
+    /// Event whose binding applies when this one is unbound
+    ///
+    /// An unbound double tap counts as a second tap, so quick repeated
+    /// presses still act twice.
+    pub fn fallback(&self) -> Option<InputEvent> {
+        match self {
+            InputEvent::Flick(direction) | InputEvent::EdgeSwipe(direction) => {
+                Some(InputEvent::Gesture(*direction))
+            }
+            InputEvent::Button(button, ButtonTrigger::DoubleTap) => {
+                Some(InputEvent::Button(*button, ButtonTrigger::Tap))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for InputEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputEvent::Button(button, trigger) => write!(f, "{:?} {:?}", button, trigger),
+            InputEvent::Chord(first, second) => write!(f, "{:?} + {:?}", first, second),
+            InputEvent::Gesture(direction) => write!(f, "Swipe {:?}", direction),
+            InputEvent::Flick(direction) => write!(f, "Flick {:?}", direction),
+            InputEvent::EdgeSwipe(direction) => write!(f, "Edge Swipe {:?}", direction),
+            InputEvent::Touch(gesture) => write!(f, "Touchpad {:?}", gesture),
+            InputEvent::Shape(id) => write!(f, "Shape #{}", id),
+            InputEvent::Motion(gesture) => write!(f, "Motion {:?}", gesture),
+            InputEvent::Circle(rotation) => write!(f, "Circle {:?}", rotation),
+        }
+    }
+}
+
+/// Actions to run when `event` occurs in `mode`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Binding {
+    /// Mode the binding applies to, `None` for every mode
+    pub mode: Option<ControlMode>,
+    pub event: InputEvent,
+    pub actions: Vec<Action>,
+}
+
+impl Binding {
+    pub fn new(mode: Option<ControlMode>, event: InputEvent, actions: Vec<Action>) -> Self {
+        Self {
+            mode,
+            event,
+            actions,
+        }
+    }
+}
+
+/// Serializable list of bindings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct BindingTable {
+    pub bindings: Vec<Binding>,
+}
+
+impl BindingTable {
+    pub fn empty() -> Self {
+        Self {
+            bindings: Vec::new(),
+        }
+    }
+
+    /// Actions bound to `event` in `mode` (empty if unbound)
+    pub fn resolve(&self, mode: ControlMode, event: InputEvent) -> &[Action] {
+        self.bindings
+            .iter()
+            .find(|b| b.mode == Some(mode) && b.event == event)
+            .or_else(|| {
+                self.bindings
+                    .iter()
+                    .find(|b| b.mode.is_none() && b.event == event)
+            })
+            .map(|b| b.actions.as_slice())
+            .unwrap_or(&[])
+    }
+
+    pub fn rename_macro(&mut self, old: &str, new: &str) {
+        for binding in &mut self.bindings {
+            binding
+                .actions
+                .iter_mut()
+                .for_each(|a| a.rename_macro(old, new));
+        }
+    }
+
+    /// Drop the `PlayMacro` steps of a deleted macro, and the bindings that
+    /// did nothing else
+    pub fn forget_macro(&mut self, name: &str) {
+        self.bindings.retain_mut(|binding| {
+            let was_bound = !binding.actions.is_empty();
+            binding.actions.retain_mut(|a| a.forget_macro(name));
+            !was_bound || !binding.actions.is_empty()
+        });
+    }
+
+    /// Add the default bindings of features introduced since the table was
+    /// saved at `from_version`
+    ///
+    /// Runs once per version step, so bindings the user deletes afterwards
+    /// stay deleted. Defaults never replace a binding for the same mode and
+    /// event.
+    pub fn migrate(&mut self, from_version: u32) {
+        if from_version < 1 {
+            // Circling the touchpad edge scrolls
+            self.add_missing(Self::scroll_ring_defaults());
+            // Wrist twists change slides
+            self.add_missing(Self::motion_defaults());
+            // Laser pointer mode and recentering on a long Home press
+            self.add_missing(Self::pointer_defaults());
+            // Media mode
+            self.add_missing(Self::media_defaults());
+        }
+    }
+
+    /// Add the `defaults` that have no binding for their mode and event yet
+    fn add_missing(&mut self, defaults: Vec<Binding>) {
+        for binding in defaults {
+            if !self
+                .bindings
+                .iter()
+                .any(|b| b.mode == binding.mode && b.event == binding.event)
+            {
+                self.bindings.push(binding);
+            }
+        }
+    }
+
+    /// Clockwise scrolls down, like turning a page wheel
+    fn scroll_ring_defaults() -> Vec<Binding> {
+        vec![
+            Binding::new(
+                None,
+                InputEvent::Circle(Rotation::Clockwise),
+                vec![Action::Scroll(-1)],
+            ),
+            Binding::new(
+                None,
+                InputEvent::Circle(Rotation::CounterClockwise),
+                vec![Action::Scroll(1)],
+            ),
+        ]
+    }
+
+    /// Twist the wrist to change slides
+    fn motion_defaults() -> Vec<Binding> {
+        let presentation = Some(ControlMode::Presentation);
+        vec![
+            Binding::new(
+                presentation,
+                InputEvent::Motion(MotionGesture::TwistRight),
+                vec![Action::KeyPress(Key::Right.into())],
+            ),
+            Binding::new(
+                presentation,
+                InputEvent::Motion(MotionGesture::TwistLeft),
+                vec![Action::KeyPress(Key::Left.into())],
+            ),
+        ]
+    }
+
+    /// Click where you point and step through slides; a long press on
+    /// Home recenters in every mode
+    fn pointer_defaults() -> Vec<Binding> {
+        use ButtonTrigger::*;
+        use ControllerButton::*;
+
+        let pointer = Some(ControlMode::Pointer);
+        let button = |button, trigger, action| {
+            Binding::new(pointer, InputEvent::Button(button, trigger), vec![action])
+        };
+        vec![
+            button(Trigger, Press, Action::MouseDown(MouseButton::Left)),
+            button(Trigger, Release, Action::MouseUp(MouseButton::Left)),
+            button(TouchpadButton, Tap, Action::KeyPress(Key::Right.into())),
+            button(Back, Tap, Action::KeyPress(Key::Left.into())),
+            Binding::new(
+                None,
+                InputEvent::Button(Home, LongPress),
+                vec![Action::Recenter],
+            ),
+        ]
+    }
+
+    fn media_defaults() -> Vec<Binding> {
+        use ButtonTrigger::*;
+        use ControllerButton::*;
+
+        let media = Some(ControlMode::Media);
+        let button = |button, trigger, action| {
+            Binding::new(media, InputEvent::Button(button, trigger), vec![action])
+        };
+        vec![
+            button(Trigger, Tap, Action::Media(MediaCommand::PlayPause)),
+            button(TouchpadButton, Tap, Action::Media(MediaCommand::Next)),
+            button(Back, Tap, Action::Media(MediaCommand::Previous)),
+            button(VolumeUp, Repeat, Action::KeyPress(Key::VolumeUp.into())),
+            button(VolumeDown, Repeat, Action::KeyPress(Key::VolumeDown.into())),
+            Binding::new(
+                media,
+                InputEvent::Gesture(GestureDirection::Right),
+                vec![Action::Media(MediaCommand::Seek(10))],
+            ),
+            Binding::new(
+                media,
+                InputEvent::Gesture(GestureDirection::Left),
+                vec![Action::Media(MediaCommand::Seek(-10))],
+            ),
+            Binding::new(
+                media,
+                InputEvent::Circle(Rotation::Clockwise),
+                vec![Action::KeyPress(Key::VolumeUp.into())],
+            ),
+            Binding::new(
+                media,
+                InputEvent::Circle(Rotation::CounterClockwise),
+                vec![Action::KeyPress(Key::VolumeDown.into())],
+            ),
+        ]
+    }
+
+    /// Bind `event` in `mode` to `actions`, replacing the previous binding
+    /// for exactly that mode and event
+    pub fn set(&mut self, mode: Option<ControlMode>, event: InputEvent, actions: Vec<Action>) {
+        match self
+            .bindings
+            .iter_mut()
+            .find(|b| b.mode == mode && b.event == event)
+        {
+            Some(binding) => binding.actions = actions,
+            None => self.bindings.push(Binding::new(mode, event, actions)),
+        }
+    }
+}
+
+impl Default for BindingTable {
+    /// The classic hard-wired behavior of each mode
+    fn default() -> Self {
+        use ButtonTrigger::*;
+        use ControllerButton::*;
+
+        let button = |mode, button, trigger, actions| {
+            Binding::new(Some(mode), InputEvent::Button(button, trigger), actions)
+        };
+        let gesture =
+            |direction, actions| Binding::new(None, InputEvent::Gesture(direction), actions);
+
+        let mut bindings = Vec::new();
+
+        for mode in [ControlMode::Mouse, ControlMode::Touchpad] {
+            bindings.extend([
+                button(
+                    mode,
+                    Trigger,
+                    Press,
+                    vec![Action::MouseDown(MouseButton::Left)],
+                ),
+                button(
+                    mode,
+                    Trigger,
+                    Release,
+                    vec![Action::MouseUp(MouseButton::Left)],
+                ),
+                button(
+                    mode,
+                    TouchpadButton,
+                    Press,
+                    vec![Action::MouseDown(MouseButton::Right)],
+                ),
+                button(
+                    mode,
+                    TouchpadButton,
+                    Release,
+                    vec![Action::MouseUp(MouseButton::Right)],
+                ),
+                button(
+                    mode,
+                    Back,
+                    Tap,
+                    vec![Action::MouseClick(MouseButton::Right)],
+                ),
+            ]);
+        }
+
+        bindings.extend([
+            button(
+                ControlMode::Mouse,
+                VolumeUp,
+                Repeat,
+                vec![Action::KeyPress(Key::VolumeUp.into())],
+            ),
+            button(
+                ControlMode::Mouse,
+                VolumeDown,
+                Repeat,
+                vec![Action::KeyPress(Key::VolumeDown.into())],
+            ),
+            button(
+                ControlMode::Touchpad,
+                VolumeUp,
+                Repeat,
+                vec![Action::Scroll(1)],
+            ),
+            button(
+                ControlMode::Touchpad,
+                VolumeDown,
+                Repeat,
+                vec![Action::Scroll(-1)],
+            ),
+        ]);
+
+        let presentation = ControlMode::Presentation;
+        bindings.extend([
+            // Next / previous slide
+            button(
+                presentation,
+                Trigger,
+                Press,
+                vec![Action::KeyPress(Key::Right.into())],
+            ),
+            button(
+                presentation,
+                TouchpadButton,
+                Press,
+                vec![Action::KeyPress(Key::Left.into())],
+            ),
+            button(
+                presentation,
+                Back,
+                Tap,
+                vec![Action::KeyPress(Key::Left.into())],
+            ),
+            button(
+                presentation,
+                VolumeUp,
+                Repeat,
+                vec![Action::KeyPress(Key::VolumeUp.into())],
+            ),
+            button(
+                presentation,
+                VolumeDown,
+                Repeat,
+                vec![Action::KeyPress(Key::VolumeDown.into())],
+            ),
+        ]);
+
+        bindings.extend([
+            gesture(GestureDirection::Up, vec![Action::Scroll(1)]),
+            gesture(GestureDirection::Down, vec![Action::Scroll(-1)]),
+            gesture(
+                GestureDirection::Left,
+                vec![Action::KeyPress(Key::Alt.into())],
+            ),
+            gesture(
+                GestureDirection::Right,
+                vec![Action::KeyPress(Key::Alt.into())],
+            ),
+        ]);
+
+        bindings.extend(Self::scroll_ring_defaults());
+        bindings.extend(Self::motion_defaults());
+        bindings.extend(Self::pointer_defaults());
+        bindings.extend(Self::media_defaults());
+
+        Self { bindings }
+    }
+}
+
+/// Looks up the actions for controller events in the current settings,
+/// including the active profile
+pub struct InputMapper {
+    settings: Arc<Mutex<SettingsService>>,
+}
+
+impl InputMapper {
+    pub fn new(settings: Arc<Mutex<SettingsService>>) -> Self {
+        Self { settings }
+    }
+
+    /// Actions bound to `event` in `mode`, or to its fallback event
+    pub fn map(&self, mode: ControlMode, event: InputEvent) -> Vec<Action> {
+        self.settings
+            .lock()
+            .map(|s| {
+                let actions = s.resolve_binding(mode, event);
+                match event.fallback() {
+                    Some(fallback) if actions.is_empty() => s.resolve_binding(mode, fallback),
+                    _ => actions,
+                }
+                .to_vec()
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRIGGER_PRESS: InputEvent =
+        InputEvent::Button(ControllerButton::Trigger, ButtonTrigger::Press);
+
+    #[test]
+    fn test_defaults_match_classic_behavior() {
+        let table = BindingTable::default();
+        assert_eq!(
+            table.resolve(ControlMode::Mouse, TRIGGER_PRESS),
+            [Action::MouseDown(MouseButton::Left)]
+        );
+        assert_eq!(
+            table.resolve(ControlMode::Presentation, TRIGGER_PRESS),
+            [Action::KeyPress(Key::Right.into())]
+        );
+        assert!(table
+            .resolve(ControlMode::Settings, TRIGGER_PRESS)
+            .is_empty());
+        assert_eq!(
+            table.resolve(
+                ControlMode::Touchpad,
+                InputEvent::Button(ControllerButton::VolumeUp, ButtonTrigger::Repeat)
+            ),
+            [Action::Scroll(1)]
+        );
+        assert_eq!(
+            table.resolve(
+                ControlMode::Settings,
+                InputEvent::Gesture(GestureDirection::Down)
+            ),
+            [Action::Scroll(-1)]
+        );
+    }
+
+    #[test]
+    fn test_mode_binding_overrides_global_binding() {
+        let event = InputEvent::Gesture(GestureDirection::Up);
+        let table = BindingTable {
+            bindings: vec![
+                Binding::new(None, event, vec![Action::Scroll(1)]),
+                Binding::new(
+                    Some(ControlMode::Presentation),
+                    event,
+                    vec![Action::KeyPress(Key::Up.into())],
+                ),
+            ],
+        };
+        assert_eq!(
+            table.resolve(ControlMode::Presentation, event),
+            [Action::KeyPress(Key::Up.into())]
+        );
+        assert_eq!(
+            table.resolve(ControlMode::Mouse, event),
+            [Action::Scroll(1)]
+        );
+    }
+
+    #[test]
+    fn test_migration_adds_new_defaults_once() {
+        // A table from before the media and pointer modes, recentering, the
+        // scroll ring and motion gestures
+        let mut table = BindingTable::default();
+        table.bindings.retain(|b| {
+            !matches!(
+                b.mode,
+                Some(ControlMode::Media) | Some(ControlMode::Pointer)
+            ) && !matches!(
+                b.event,
+                InputEvent::Circle(_)
+                    | InputEvent::Motion(_)
+                    | InputEvent::Button(ControllerButton::Home, ButtonTrigger::LongPress)
+            )
+        });
+        table.migrate(0);
+        let mut expected = BindingTable::default().bindings;
+        let mut migrated = table.bindings.clone();
+        let key = |b: &Binding| format!("{:?} {}", b.mode, b.event);
+        expected.sort_by_key(key);
+        migrated.sort_by_key(key);
+        assert_eq!(migrated, expected);
+
+        // Present bindings are not duplicated
+        let mut current = BindingTable::default();
+        current.migrate(0);
+        assert_eq!(current, BindingTable::default());
+
+        // Once migrated, deleted defaults stay deleted
+        table
+            .bindings
+            .retain(|b| b.mode != Some(ControlMode::Media));
+        let customized = table.clone();
+        table.migrate(BINDINGS_VERSION);
+        assert_eq!(table, customized);
+    }
+
+    #[test]
+    fn test_table_round_trips_through_json() {
+        let table = BindingTable::default();
+        let json = serde_json::to_string(&table).unwrap();
+        let restored: BindingTable = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, table);
+    }
+}
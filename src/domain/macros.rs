@@ -0,0 +1,228 @@
+//! Macros
+//!
+//! Named action sequences stored in the settings. A [`MacroRecorder`]
+//! captures the actions injected while recording and keeps the pauses
+//! between them as `Delay` steps, so playback reproduces the original
+//! timing.
+
+use crate::domain::actions::Action;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// Gaps shorter than this are not worth a `Delay` step
+const MIN_RECORDED_DELAY_MS: u64 = 10;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Macro {
+    pub name: String,
+    pub steps: Vec<Action>,
+}
+
+impl Macro {
+    pub fn new(name: impl Into<String>, steps: Vec<Action>) -> Self {
+        Self {
+            name: name.into(),
+            steps,
+        }
+    }
+
+    /// Total time spent in `Delay` steps (milliseconds)
+    pub fn duration_ms(&self) -> u64 {
+        self.steps
+            .iter()
+            .map(|step| match step {
+                Action::Delay(ms) => *ms,
+                _ => 0,
+            })
+            .sum()
+    }
+}
+
+/// Records injected actions together with their relative timing
+#[derive(Debug, Default)]
+pub struct MacroRecorder {
+    last_step_at: Option<Instant>,
+    steps: Vec<Action>,
+}
+
+impl MacroRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.last_step_at.is_some()
+    }
+
+    /// Start a new recording, discarding any unfinished one
+    pub fn start(&mut self, now: Instant) {
+        self.last_step_at = Some(now);
+        self.steps.clear();
+    }
+
+    /// Append an action performed at `now`
+    ///
+    /// Only injected input is recorded; mode switches, commands and macro
+    /// control actions are skipped so playback cannot recurse or change state.
+    /// A sequence is skipped if any of its steps would be.
+    pub fn record(&mut self, action: &Action, now: Instant) {
+        let Some(last) = self.last_step_at else {
+            return;
+        };
+        if !Self::is_recordable(action) {
+            return;
+        }
+
+        let gap = now.saturating_duration_since(last).as_millis() as u64;
+        if gap >= MIN_RECORDED_DELAY_MS && !self.steps.is_empty() {
+            self.steps.push(Action::Delay(gap));
+        }
+        self.steps.push(action.clone());
+        self.last_step_at = Some(now);
+    }
+
+    /// Finish the recording and return its steps
+    pub fn stop(&mut self) -> Vec<Action> {
+        self.last_step_at = None;
+        std::mem::take(&mut self.steps)
+    }
+
+    fn is_recordable(action: &Action) -> bool {
+        match action {
+            Action::KeyPress(_)
+            | Action::KeyDown(_)
+            | Action::KeyUp(_)
+            | Action::MouseClick(_)
+            | Action::MouseDown(_)
+            | Action::MouseUp(_)
+            | Action::Scroll(_)
+            | Action::HScroll(_)
+            | Action::Text(_)
+            | Action::Media(_)
+            | Action::Delay(_) => true,
+            Action::Sequence(steps) => steps.iter().all(Self::is_recordable),
+            Action::SwitchMode(_)
+            | Action::RunCommand(_)
+            | Action::PlayMacro(_)
+            | Action::ToggleRecording
+            | Action::CancelMacro
+            | Action::Recenter => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::input_mapper::{BindingTable, ButtonTrigger, ControllerButton, InputEvent};
+    use crate::domain::models::ControlMode;
+    use crate::domain::profiles::{Profile, WindowMatcher};
+    use crate::domain::settings::{Settings, SettingsService};
+    use std::time::Duration;
+
+    #[test]
+    fn test_records_relative_timing() {
+        let start = Instant::now();
+        let mut recorder = MacroRecorder::new();
+        recorder.start(start);
+
+        recorder.record(
+            &"ctrl+c".parse().unwrap(),
+            start + Duration::from_millis(500),
+        );
+        recorder.record(
+            &"mode:mouse".parse().unwrap(),
+            start + Duration::from_millis(600),
+        );
+        recorder.record(
+            &"ctrl+v".parse().unwrap(),
+            start + Duration::from_millis(750),
+        );
+        recorder.record(
+            &"scroll:-1".parse().unwrap(),
+            start + Duration::from_millis(752),
+        );
+
+        let recorded = Macro::new("Paste", recorder.stop());
+        assert_eq!(
+            Action::Sequence(recorded.steps.clone()).to_string(),
+            "ctrl+c; wait:250; ctrl+v; scroll:-1"
+        );
+        assert_eq!(recorded.duration_ms(), 250);
+        assert!(!recorder.is_recording());
+    }
+
+    #[test]
+    fn test_ignores_actions_when_idle() {
+        let mut recorder = MacroRecorder::new();
+        recorder.record(&"click:left".parse().unwrap(), Instant::now());
+        assert!(recorder.stop().is_empty());
+    }
+
+    #[test]
+    fn test_skips_sequences_with_unrecordable_steps() {
+        let start = Instant::now();
+        let mut recorder = MacroRecorder::new();
+        recorder.start(start);
+        recorder.record(&"ctrl+c; wait:50; ctrl+v".parse().unwrap(), start);
+        recorder.record(&"ctrl+s; run:build.bat".parse().unwrap(), start);
+        recorder.record(&"macro:Other; click:left".parse().unwrap(), start);
+        assert_eq!(
+            Action::Sequence(recorder.stop()).to_string(),
+            "ctrl+c; wait:50; ctrl+v"
+        );
+    }
+
+    #[test]
+    fn test_rename_and_delete_follow_every_reference() {
+        let action = |s: &str| s.parse::<Action>().unwrap();
+        let tap = InputEvent::Button(ControllerButton::Trigger, ButtonTrigger::Tap);
+        let back = InputEvent::Button(ControllerButton::Back, ButtonTrigger::Tap);
+
+        let mut profile = Profile::new("Editor", WindowMatcher::Executable("code.exe".into()));
+        profile.bindings.set(None, tap, vec![action("macro:Paste")]);
+        profile
+            .bindings
+            .set(None, back, vec![action("ctrl+a; macro:Paste")]);
+        let mut global = BindingTable::empty();
+        global.set(None, tap, vec![action("macro:Paste")]);
+        let mut settings = SettingsService::in_memory(Settings {
+            input_bindings: global,
+            macros: vec![
+                Macro::new("Paste", vec![action("ctrl+v")]),
+                Macro::new("Twice", vec![action("macro:Paste"), action("macro:Paste")]),
+            ],
+            profiles: vec![profile],
+            ..Settings::default()
+        });
+        let mode = ControlMode::Mouse;
+
+        settings.get_mut().macros[0].name = "Insert".into();
+        settings.rename_macro("Paste", "Insert");
+        let renamed = settings.get();
+        let profile = &renamed.profiles[0].bindings;
+        assert_eq!(
+            renamed.input_bindings.resolve(mode, tap),
+            [action("macro:Insert")]
+        );
+        assert_eq!(profile.resolve(mode, tap), [action("macro:Insert")]);
+        assert_eq!(
+            profile.resolve(mode, back),
+            [action("ctrl+a; macro:Insert")]
+        );
+        assert_eq!(
+            renamed.macros[1].steps,
+            [action("macro:Insert"), action("macro:Insert")]
+        );
+
+        // Bindings that only played the macro go; the rest stay
+        settings.delete_macro("Insert");
+        let deleted = settings.get();
+        let profile = &deleted.profiles[0].bindings;
+        assert!(deleted.input_bindings.bindings.is_empty());
+        assert!(profile.resolve(mode, tap).is_empty());
+        assert_eq!(profile.resolve(mode, back), [action("ctrl+a")]);
+        assert_eq!(deleted.macros.len(), 1);
+        assert!(deleted.macros[0].steps.is_empty());
+    }
+}
@@ -0,0 +1,155 @@
+//! Media Playback Control
+//!
+//! Commands for the media player and an optional [`MediaSession`] that
+//! talks to the OS media session directly. Without a session, commands
+//! fall back to the media and arrow keys.
+
+use crate::domain::actions::{Action, Key};
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MediaCommand {
+    PlayPause,
+    Next,
+    Previous,
+    Stop,
+    /// Jump forward (positive) or backward by a number of seconds
+    Seek(i32),
+}
+
+impl MediaCommand {
+    /// Keyboard equivalent used when no media session is available
+    ///
+    /// Most players seek with the arrow keys, the step size is theirs.
+    pub fn fallback_action(&self) -> Action {
+        let key = match self {
+            MediaCommand::PlayPause => Key::MediaPlayPause,
+            MediaCommand::Next => Key::MediaNext,
+            MediaCommand::Previous => Key::MediaPrevious,
+            MediaCommand::Stop => Key::MediaStop,
+            MediaCommand::Seek(seconds) if *seconds < 0 => Key::Left,
+            MediaCommand::Seek(_) => Key::Right,
+        };
+        Action::KeyPress(key.into())
+    }
+
+    /// Hand the command to `session`, or return the keyboard action to run
+    /// when there is none
+    pub fn route(self, session: Option<&mut dyn MediaSession>) -> Option<Action> {
+        match session {
+            Some(session) => {
+                session.send(self);
+                None
+            }
+            None => Some(self.fallback_action()),
+        }
+    }
+}
+
+impl fmt::Display for MediaCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MediaCommand::PlayPause => f.write_str("playpause"),
+            MediaCommand::Next => f.write_str("next"),
+            MediaCommand::Previous => f.write_str("previous"),
+            MediaCommand::Stop => f.write_str("stop"),
+            MediaCommand::Seek(seconds) => write!(f, "seek{:+}", seconds),
+        }
+    }
+}
+
+impl FromStr for MediaCommand {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_lowercase();
+        if let Some(seconds) = s.strip_prefix("seek") {
+            return Ok(MediaCommand::Seek(seconds.trim().parse()?));
+        }
+        match s.as_str() {
+            "playpause" | "play" | "pause" => Ok(MediaCommand::PlayPause),
+            "next" => Ok(MediaCommand::Next),
+            "previous" | "prev" => Ok(MediaCommand::Previous),
+            "stop" => Ok(MediaCommand::Stop),
+            other => bail!("Unknown media command '{}'", other),
+        }
+    }
+}
+
+/// Track reported by the media session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NowPlaying {
+    pub title: String,
+    pub artist: String,
+    pub playing: bool,
+}
+
+/// Connection to the OS media session
+///
+/// Implementations must not block; commands are carried out in the
+/// background and `now_playing` returns the last known state.
+pub trait MediaSession {
+    fn send(&mut self, command: MediaCommand);
+    fn now_playing(&mut self) -> Option<NowPlaying>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_media_actions_round_trip() {
+        for text in [
+            "media:playpause",
+            "media:previous",
+            "media:seek+10",
+            "media:seek-5",
+        ] {
+            let action: Action = text.parse().unwrap();
+            assert_eq!(action.to_string(), text);
+        }
+        assert_eq!(
+            "media:seek 30".parse::<Action>().unwrap(),
+            Action::Media(MediaCommand::Seek(30))
+        );
+        assert_eq!(
+            MediaCommand::Seek(-10).fallback_action(),
+            Action::KeyPress(Key::Left.into())
+        );
+    }
+
+    #[derive(Default)]
+    struct MockSession {
+        sent: Vec<MediaCommand>,
+    }
+
+    impl MediaSession for MockSession {
+        fn send(&mut self, command: MediaCommand) {
+            self.sent.push(command);
+        }
+
+        fn now_playing(&mut self) -> Option<NowPlaying> {
+            None
+        }
+    }
+
+    #[test]
+    fn test_commands_go_to_the_session_or_fall_back_to_keys() {
+        let mut session = MockSession::default();
+        assert_eq!(MediaCommand::Next.route(Some(&mut session)), None);
+        assert_eq!(MediaCommand::Seek(10).route(Some(&mut session)), None);
+        assert_eq!(session.sent, [MediaCommand::Next, MediaCommand::Seek(10)]);
+
+        assert_eq!(
+            MediaCommand::PlayPause.route(None),
+            Some(Action::KeyPress(Key::MediaPlayPause.into()))
+        );
+        assert_eq!(
+            MediaCommand::Seek(10).route(None),
+            Some(Action::KeyPress(Key::Right.into()))
+        );
+    }
+}
@@ -1,5 +1,23 @@
+pub mod actions;
+pub mod axes;
+pub mod buttons;
+pub mod calibration;
+pub mod circular;
 pub mod controller;
+pub mod diagnostics;
+pub mod filters;
 pub mod gestures;
 pub mod imu;
+pub mod input_mapper;
+pub mod macros;
+pub mod media;
 pub mod models;
+pub mod motion;
+pub mod orientation;
+pub mod power;
+pub mod profiles;
+pub mod scan;
+pub mod scroll;
 pub mod settings;
+pub mod shapes;
+pub mod transfer;
@@ -1,3 +1,4 @@
+use crate::domain::diagnostics::ConnectionAttempt;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Default)]
@@ -34,8 +35,8 @@ pub struct ControllerData {
     pub processed_touchpad_x: f64,
     pub processed_touchpad_y: f64,
 
-    // Timestamp (from controller, milliseconds)
-    pub timestamp: i64,
+    // Timestamp (from controller clock, seconds)
+    pub timestamp: f64,
 
     // Temperature sensor (if available)
     pub temperature: Option<i16>,
@@ -50,6 +51,66 @@ pub struct ScannedDevice {
     pub name: String,
     pub address: u64,
     pub signal_strength: i16,
+    pub advertisement: AdvertisementData,
+}
+
+/// Decoded contents of a BLE advertisement (or scan response)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AdvertisementData {
+    /// Complete or shortened local name
+    pub local_name: Option<String>,
+    /// Advertising flags (AD type 0x01)
+    pub flags: Option<u8>,
+    /// Advertised TX power level in dBm (AD type 0x0A)
+    pub tx_power: Option<i8>,
+    /// Manufacturer specific data as (company id, payload)
+    pub manufacturer_data: Vec<(u16, Vec<u8>)>,
+    /// Service data as (service UUID, payload)
+    pub service_data: Vec<(String, Vec<u8>)>,
+}
+
+impl AdvertisementData {
+    /// LE Limited Discoverable Mode flag
+    pub const FLAG_LIMITED_DISCOVERABLE: u8 = 0x01;
+    /// LE General Discoverable Mode flag
+    pub const FLAG_GENERAL_DISCOVERABLE: u8 = 0x02;
+
+    /// Whether the device advertises itself as discoverable (i.e. in pairing mode)
+    pub fn is_discoverable(&self) -> bool {
+        self.flags.is_some_and(|f| {
+            f & (Self::FLAG_LIMITED_DISCOVERABLE | Self::FLAG_GENERAL_DISCOVERABLE) != 0
+        })
+    }
+
+    /// Merge a later advertisement (e.g. a scan response) into this one.
+    /// Fields present in `other` win, missing fields keep their previous value.
+    pub fn merge(&mut self, other: &AdvertisementData) {
+        if other.local_name.is_some() {
+            self.local_name = other.local_name.clone();
+        }
+        if other.flags.is_some() {
+            self.flags = other.flags;
+        }
+        if other.tx_power.is_some() {
+            self.tx_power = other.tx_power;
+        }
+        for (company_id, data) in &other.manufacturer_data {
+            match self
+                .manufacturer_data
+                .iter_mut()
+                .find(|(id, _)| id == company_id)
+            {
+                Some(existing) => existing.1 = data.clone(),
+                None => self.manufacturer_data.push((*company_id, data.clone())),
+            }
+        }
+        for (uuid, data) in &other.service_data {
+            match self.service_data.iter_mut().find(|(u, _)| u == uuid) {
+                Some(existing) => existing.1 = data.clone(),
+                None => self.service_data.push((uuid.clone(), data.clone())),
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -58,6 +119,14 @@ pub enum AppEvent {
     ConnectionStatus(ConnectionStatus),
     LogMessage(StatusMessage),
     DeviceFound(ScannedDevice),
+    ConnectionAttempt(ConnectionAttempt),
+    AdapterList(Vec<BluetoothAdapterInfo>),
+    /// Radio of the selected adapter, used for connecting
+    AdapterState(AdapterState),
+    /// Radio of the system default adapter, used for scanning
+    ScanAdapterState(AdapterState),
+    /// An adapter was plugged in or removed
+    AdaptersChanged,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -91,6 +160,36 @@ pub enum ConnectionStatus {
     Error,
 }
 
+/// A local Bluetooth adapter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BluetoothAdapterInfo {
+    /// Device id, used to select the adapter
+    pub id: String,
+    pub name: String,
+    pub address: u64,
+    pub supports_le: bool,
+}
+
+/// State of the selected Bluetooth adapter, reported separately from the
+/// controller connection status
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdapterState {
+    Unknown,
+    Ready,
+    RadioOff,
+    /// Adapter removed or not present
+    Unavailable,
+}
+
+impl AdapterState {
+    /// Whether scanning and connecting are possible
+    pub fn is_usable(&self) -> bool {
+        // Unknown is treated as usable so we don't block on adapters that
+        // can't report their radio state
+        matches!(self, AdapterState::Ready | AdapterState::Unknown)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct StatusMessage {
     pub message: String,
@@ -110,15 +209,31 @@ pub enum Tab {
     Home,
     Calibration,
     Settings,
+    Macros,
+    Profiles,
     Debug,
 }
 
+/// Available control modes for the controller
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum ControlMode {
+    #[default]
+    Mouse, // Air Mouse Mode (IMU cursor + TP scroll)
+    Touchpad,     // Laptop Trackpad Mode (TP cursor + Button scroll)
+    Pointer,      // Laser Pointer Mode (absolute IMU cursor)
+    Presentation, // PPT Mode (Buttons only)
+    Media,        // Playback control (Buttons + circular volume)
+    Settings,     // Quick Settings / Calibration
+}
+
 #[derive(Debug, Clone)]
 pub enum BluetoothCommand {
     Connect(u64),
     Disconnect,
     StartScan,
     StopScan,
+    /// Re-enumerate adapters and (re)select the one configured in settings
+    RefreshAdapters,
 }
 
 #[derive(Debug, Clone, Default)]
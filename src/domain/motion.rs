@@ -0,0 +1,390 @@
+//! Motion Gestures
+//!
+//! Detects gestures made with the whole controller from the IMU: quick
+//! flicks (yaw and pitch), wrist twists (roll), shakes and knocks on the
+//! housing. Angular rates are in rad/s and accelerations in g, as parsed
+//! by the protocol. The axes follow the air mouse: positive `gyro_x` moves
+//! the cursor right, positive `gyro_y` moves it down.
+
+use crate::domain::buttons::{Clock, SystemClock};
+use crate::domain::models::ControllerData;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// Weight of each sample in the gravity estimate
+const GRAVITY_SMOOTHING: f32 = 0.05;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MotionGesture {
+    FlickUp,
+    FlickDown,
+    FlickLeft,
+    FlickRight,
+    TwistLeft,
+    TwistRight,
+    Shake,
+    /// Sharp tap against the controller
+    Knock,
+}
+
+impl MotionGesture {
+    pub const ALL: [MotionGesture; 8] = [
+        MotionGesture::FlickUp,
+        MotionGesture::FlickDown,
+        MotionGesture::FlickLeft,
+        MotionGesture::FlickRight,
+        MotionGesture::TwistLeft,
+        MotionGesture::TwistRight,
+        MotionGesture::Shake,
+        MotionGesture::Knock,
+    ];
+}
+
+/// Detection thresholds
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MotionSettings {
+    pub enabled: bool,
+    /// Peak yaw/pitch rate of a flick (rad/s)
+    pub flick_rate: f32,
+    /// Peak roll rate of a twist (rad/s)
+    pub twist_rate: f32,
+    /// Longer rotations are regular pointing, not a flick or twist
+    pub max_flick_ms: u64,
+    /// Quiet time after a flick or twist, swallows the return movement
+    pub cooldown_ms: u64,
+    /// Linear acceleration of a shake stroke (g)
+    pub shake_accel: f32,
+    /// Direction reversals that make a shake
+    pub shake_reversals: usize,
+    pub shake_window_ms: u64,
+    pub shake_cooldown_ms: u64,
+    /// Acceleration spike of a knock (g)
+    pub knock_accel: f32,
+    pub knock_max_ms: u64,
+}
+
+impl Default for MotionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            flick_rate: 6.0,
+            twist_rate: 5.0,
+            max_flick_ms: 300,
+            cooldown_ms: 400,
+            shake_accel: 1.2,
+            shake_reversals: 4,
+            shake_window_ms: 800,
+            shake_cooldown_ms: 1500,
+            knock_accel: 0.8,
+            knock_max_ms: 60,
+        }
+    }
+}
+
+/// Rotation above the flick or twist rate
+#[derive(Debug, Clone, Copy)]
+struct Burst {
+    started: Instant,
+    /// Strongest rate per axis (yaw, pitch, roll), signed
+    peak: [f32; 3],
+}
+
+pub struct MotionRecognizer<C: Clock = SystemClock> {
+    clock: C,
+    settings: MotionSettings,
+    gravity: Option<[f32; 3]>,
+    burst: Option<Burst>,
+    quiet_until: Option<Instant>,
+    /// Start of the current acceleration spike
+    spike_started: Option<Instant>,
+    /// Times and signs of the shake strokes in the window
+    shake_strokes: VecDeque<(Instant, bool)>,
+    shake_quiet_until: Option<Instant>,
+}
+
+impl MotionRecognizer<SystemClock> {
+    pub fn new(settings: MotionSettings) -> Self {
+        Self::with_clock(settings, SystemClock)
+    }
+}
+
+impl<C: Clock> MotionRecognizer<C> {
+    pub fn with_clock(settings: MotionSettings, clock: C) -> Self {
+        Self {
+            clock,
+            settings,
+            gravity: None,
+            burst: None,
+            quiet_until: None,
+            spike_started: None,
+            shake_strokes: VecDeque::new(),
+            shake_quiet_until: None,
+        }
+    }
+
+    pub fn set_settings(&mut self, settings: &MotionSettings) {
+        if &self.settings != settings {
+            self.settings = settings.clone();
+        }
+    }
+
+    /// Feed an IMU sample, returns the gestures it completed
+    pub fn process(&mut self, data: &ControllerData) -> Vec<MotionGesture> {
+        let now = self.clock.now();
+        let accel = [data.accel_x, data.accel_y, data.accel_z];
+        let gravity = self.gravity.get_or_insert(accel);
+        for (g, a) in gravity.iter_mut().zip(accel) {
+            *g += (a - *g) * GRAVITY_SMOOTHING;
+        }
+        let linear = [
+            accel[0] - gravity[0],
+            accel[1] - gravity[1],
+            accel[2] - gravity[2],
+        ];
+        let rates = [data.gyro_x, data.gyro_y, data.gyro_z];
+
+        let mut gestures = Vec::new();
+        gestures.extend(self.detect_shake(linear, now));
+        if !self.is_shaking(now) {
+            gestures.extend(self.detect_rotation(rates, now));
+            gestures.extend(self.detect_knock(linear, rates, now));
+        }
+        for gesture in &gestures {
+            debug!("Motion gesture recognized: {:?}", gesture);
+        }
+        gestures
+    }
+
+    /// A single stroke may still be a knock, a reversal starts a shake
+    fn is_shaking(&self, now: Instant) -> bool {
+        self.shake_quiet_until.is_some_and(|t| now < t) || self.shake_strokes.len() >= 2
+    }
+
+    fn detect_shake(&mut self, linear: [f32; 3], now: Instant) -> Option<MotionGesture> {
+        let window = Duration::from_millis(self.settings.shake_window_ms);
+        while self
+            .shake_strokes
+            .front()
+            .is_some_and(|(at, _)| now.duration_since(*at) > window)
+        {
+            self.shake_strokes.pop_front();
+        }
+        if self.shake_quiet_until.is_some_and(|t| now < t) {
+            return None;
+        }
+
+        let strongest = linear
+            .into_iter()
+            .max_by(|a, b| a.abs().total_cmp(&b.abs()))
+            .unwrap_or(0.0);
+        if strongest.abs() < self.settings.shake_accel {
+            return None;
+        }
+        let positive = strongest > 0.0;
+        if self.shake_strokes.back().map(|(_, sign)| *sign) == Some(positive) {
+            return None;
+        }
+        self.shake_strokes.push_back((now, positive));
+
+        // The first stroke is not a reversal
+        if self.shake_strokes.len() > self.settings.shake_reversals {
+            self.shake_strokes.clear();
+            self.shake_quiet_until =
+                Some(now + Duration::from_millis(self.settings.shake_cooldown_ms));
+            self.burst = None;
+            self.spike_started = None;
+            return Some(MotionGesture::Shake);
+        }
+        None
+    }
+
+    fn detect_rotation(&mut self, rates: [f32; 3], now: Instant) -> Option<MotionGesture> {
+        if self.quiet_until.is_some_and(|t| now < t) {
+            return None;
+        }
+
+        let thresholds = [
+            self.settings.flick_rate,
+            self.settings.flick_rate,
+            self.settings.twist_rate,
+        ];
+        let above = rates.iter().zip(thresholds).any(|(r, t)| r.abs() >= t);
+        // Hysteresis: the burst lasts until every axis is below half its rate
+        let settled = rates.iter().zip(thresholds).all(|(r, t)| r.abs() < t / 2.0);
+
+        match &mut self.burst {
+            None if above => {
+                self.burst = Some(Burst {
+                    started: now,
+                    peak: rates,
+                });
+                None
+            }
+            None => None,
+            Some(burst) if !settled => {
+                for (peak, rate) in burst.peak.iter_mut().zip(rates) {
+                    if rate.abs() > peak.abs() {
+                        *peak = rate;
+                    }
+                }
+                None
+            }
+            Some(burst) => {
+                let burst = *burst;
+                self.burst = None;
+                if now.duration_since(burst.started)
+                    > Duration::from_millis(self.settings.max_flick_ms)
+                {
+                    return None;
+                }
+                self.quiet_until = Some(now + Duration::from_millis(self.settings.cooldown_ms));
+                Some(classify(burst.peak, thresholds))
+            }
+        }
+    }
+
+    fn detect_knock(
+        &mut self,
+        linear: [f32; 3],
+        rates: [f32; 3],
+        now: Instant,
+    ) -> Option<MotionGesture> {
+        let magnitude = linear.iter().map(|a| a * a).sum::<f32>().sqrt();
+        // A knock jolts the housing without turning it
+        let turning = rates
+            .iter()
+            .any(|r| r.abs() >= self.settings.flick_rate / 2.0);
+
+        match self.spike_started {
+            None if magnitude >= self.settings.knock_accel && !turning => {
+                self.spike_started = Some(now);
+                None
+            }
+            Some(started) if magnitude < self.settings.knock_accel / 2.0 => {
+                self.spike_started = None;
+                let short = now.duration_since(started)
+                    <= Duration::from_millis(self.settings.knock_max_ms);
+                (short && !turning).then_some(MotionGesture::Knock)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Gesture for the axis that exceeded its threshold the most
+fn classify(peak: [f32; 3], thresholds: [f32; 3]) -> MotionGesture {
+    let axis = (0..3)
+        .max_by(|&a, &b| {
+            let ratio = |i: usize| peak[i].abs() / thresholds[i].max(f32::EPSILON);
+            ratio(a).total_cmp(&ratio(b))
+        })
+        .unwrap_or(0);
+    match (axis, peak[axis] > 0.0) {
+        (0, true) => MotionGesture::FlickRight,
+        (0, false) => MotionGesture::FlickLeft,
+        (1, true) => MotionGesture::FlickDown,
+        (1, false) => MotionGesture::FlickUp,
+        (_, true) => MotionGesture::TwistRight,
+        (_, false) => MotionGesture::TwistLeft,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::buttons::FakeClock;
+
+    /// Packet interval of the controller
+    const SAMPLE_MS: u64 = 16;
+
+    /// Controller at rest, lying flat
+    fn rest() -> ControllerData {
+        ControllerData {
+            accel_z: 1.0,
+            ..Default::default()
+        }
+    }
+
+    /// Feed `trace` one sample per packet interval
+    fn run(trace: impl IntoIterator<Item = ControllerData>) -> Vec<MotionGesture> {
+        let clock = FakeClock::new();
+        let mut recognizer = MotionRecognizer::with_clock(MotionSettings::default(), clock.clone());
+        let mut gestures = Vec::new();
+        for sample in std::iter::repeat_n(rest(), 10).chain(trace) {
+            gestures.extend(recognizer.process(&sample));
+            clock.advance(SAMPLE_MS);
+        }
+        gestures
+    }
+
+    /// Half sine pulse of angular rate over `samples` packets, then rest
+    fn rotation(axis: usize, peak: f32, samples: usize) -> Vec<ControllerData> {
+        (0..samples)
+            .map(|i| {
+                let rate = peak * (std::f32::consts::PI * (i as f32 + 0.5) / samples as f32).sin();
+                let mut sample = rest();
+                match axis {
+                    0 => sample.gyro_x = rate,
+                    1 => sample.gyro_y = rate,
+                    _ => sample.gyro_z = rate,
+                }
+                sample
+            })
+            .chain(std::iter::repeat_n(rest(), 10))
+            .collect()
+    }
+
+    #[test]
+    fn test_flicks_and_twists_with_return_swallowed() {
+        let mut trace = rotation(0, 9.0, 8);
+        // Wrist returning to the start right after the flick
+        trace.extend(rotation(0, -8.0, 8));
+        trace.extend(std::iter::repeat_n(rest(), 30));
+        trace.extend(rotation(1, -9.0, 8));
+        trace.extend(std::iter::repeat_n(rest(), 30));
+        trace.extend(rotation(2, 8.0, 10));
+        assert_eq!(
+            run(trace),
+            [
+                MotionGesture::FlickRight,
+                MotionGesture::FlickUp,
+                MotionGesture::TwistRight,
+            ]
+        );
+
+        // Slow, sustained turning is pointing
+        assert!(run(rotation(0, 7.0, 40)).is_empty());
+    }
+
+    #[test]
+    fn test_shake_fires_once_per_cooldown() {
+        // 4 Hz back-and-forth along x at 2 g for one second
+        let trace = (0..60).map(|i| {
+            let t = i as f32 * SAMPLE_MS as f32 / 1000.0;
+            ControllerData {
+                accel_x: 2.0 * (2.0 * std::f32::consts::PI * 4.0 * t).sin(),
+                gyro_z: 1.0,
+                ..rest()
+            }
+        });
+        assert_eq!(run(trace), [MotionGesture::Shake]);
+    }
+
+    #[test]
+    fn test_knock_is_a_short_spike() {
+        let knock = ControllerData {
+            accel_y: 1.5,
+            ..rest()
+        };
+        let mut trace = vec![knock.clone(), knock.clone()];
+        trace.extend(std::iter::repeat_n(rest(), 20));
+        assert_eq!(run(trace), [MotionGesture::Knock]);
+
+        // Sustained push, e.g. moving the arm
+        let push = std::iter::repeat_n(knock, 10).chain(std::iter::repeat_n(rest(), 20));
+        assert!(run(push).is_empty());
+    }
+}
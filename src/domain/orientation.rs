@@ -0,0 +1,580 @@
+//! Orientation Estimation (AHRS)
+//!
+//! Fuses gyroscope, accelerometer and optionally magnetometer samples into
+//! a 3-DoF orientation with the Madgwick (gradient descent) or Mahony
+//! (complementary PI) filter. The gyroscope is integrated every sample and
+//! gravity corrects pitch and roll drift; the magnetometer, once
+//! calibrated, does the same for yaw.
+//!
+//! The quaternion rotates body (sensor) coordinates into the earth frame,
+//! whose z axis points up against gravity and whose x axis points to
+//! magnetic north (or the initial heading without magnetometer). Euler
+//! angles use the aerospace ZYX convention, in radians.
+
+use crate::domain::filters::SampleTimer;
+use crate::domain::models::ControllerData;
+use serde::{Deserialize, Serialize};
+use std::ops::Mul;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quaternion {
+    pub w: f32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Quaternion {
+    pub const IDENTITY: Quaternion = Quaternion {
+        w: 1.0,
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+    };
+
+    pub fn from_axis_angle(axis: [f32; 3], angle: f32) -> Self {
+        let norm = norm(axis);
+        if norm <= f32::EPSILON {
+            return Self::IDENTITY;
+        }
+        let (sin, cos) = (angle / 2.0).sin_cos();
+        let s = sin / norm;
+        Self {
+            w: cos,
+            x: axis[0] * s,
+            y: axis[1] * s,
+            z: axis[2] * s,
+        }
+    }
+
+    /// Rotation by yaw, then pitch, then roll
+    pub fn from_euler(euler: EulerAngles) -> Self {
+        Self::from_axis_angle([0.0, 0.0, 1.0], euler.yaw)
+            * Self::from_axis_angle([0.0, 1.0, 0.0], euler.pitch)
+            * Self::from_axis_angle([1.0, 0.0, 0.0], euler.roll)
+    }
+
+    pub fn conjugate(&self) -> Self {
+        Self {
+            w: self.w,
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+
+    pub fn normalized(&self) -> Self {
+        let norm = (self.w * self.w + self.x * self.x + self.y * self.y + self.z * self.z).sqrt();
+        if norm <= f32::EPSILON {
+            return Self::IDENTITY;
+        }
+        Self {
+            w: self.w / norm,
+            x: self.x / norm,
+            y: self.y / norm,
+            z: self.z / norm,
+        }
+    }
+
+    /// Rotate a vector by this quaternion
+    pub fn rotate(&self, v: [f32; 3]) -> [f32; 3] {
+        let p = Quaternion {
+            w: 0.0,
+            x: v[0],
+            y: v[1],
+            z: v[2],
+        };
+        let r = *self * p * self.conjugate();
+        [r.x, r.y, r.z]
+    }
+
+    pub fn to_euler(self) -> EulerAngles {
+        let Quaternion { w, x, y, z } = self;
+        EulerAngles {
+            roll: (2.0 * (w * x + y * z)).atan2(1.0 - 2.0 * (x * x + y * y)),
+            pitch: (2.0 * (w * y - z * x)).clamp(-1.0, 1.0).asin(),
+            yaw: (2.0 * (w * z + x * y)).atan2(1.0 - 2.0 * (y * y + z * z)),
+        }
+    }
+
+    fn scaled(&self, s: f32) -> Self {
+        Self {
+            w: self.w * s,
+            x: self.x * s,
+            y: self.y * s,
+            z: self.z * s,
+        }
+    }
+
+    fn add(&self, other: &Quaternion) -> Self {
+        Self {
+            w: self.w + other.w,
+            x: self.x + other.x,
+            y: self.y + other.y,
+            z: self.z + other.z,
+        }
+    }
+}
+
+impl Mul for Quaternion {
+    type Output = Quaternion;
+
+    fn mul(self, r: Quaternion) -> Quaternion {
+        Quaternion {
+            w: self.w * r.w - self.x * r.x - self.y * r.y - self.z * r.z,
+            x: self.w * r.x + self.x * r.w + self.y * r.z - self.z * r.y,
+            y: self.w * r.y - self.x * r.z + self.y * r.w + self.z * r.x,
+            z: self.w * r.z + self.x * r.y - self.y * r.x + self.z * r.w,
+        }
+    }
+}
+
+/// Orientation as angles (radians)
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct EulerAngles {
+    /// Rotation around the body x axis
+    pub roll: f32,
+    /// Rotation around the body y axis, +/-90 degrees
+    pub pitch: f32,
+    /// Heading around the earth z axis
+    pub yaw: f32,
+}
+
+/// Current orientation of the controller
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Orientation {
+    pub quaternion: Quaternion,
+    pub euler: EulerAngles,
+}
+
+impl From<Quaternion> for Orientation {
+    fn from(quaternion: Quaternion) -> Self {
+        Self {
+            quaternion,
+            euler: quaternion.to_euler(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FusionAlgorithm {
+    Madgwick,
+    Mahony,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FusionSettings {
+    pub algorithm: FusionAlgorithm,
+    /// Madgwick gradient step; higher trusts gravity and north more
+    pub madgwick_beta: f32,
+    /// Mahony proportional gain
+    pub mahony_kp: f32,
+    /// Mahony integral gain, learns the gyro bias (0 disables)
+    pub mahony_ki: f32,
+    /// Correct yaw with the magnetometer; only useful once calibrated
+    pub use_magnetometer: bool,
+}
+
+impl Default for FusionSettings {
+    fn default() -> Self {
+        Self {
+            algorithm: FusionAlgorithm::Madgwick,
+            madgwick_beta: 0.1,
+            mahony_kp: 1.0,
+            mahony_ki: 0.0,
+            use_magnetometer: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct OrientationEstimator {
+    settings: FusionSettings,
+    /// `None` until the first sample aligned it to gravity
+    q: Option<Quaternion>,
+    /// Mahony integral feedback (rad/s)
+    integral: [f32; 3],
+    timer: SampleTimer,
+}
+
+impl OrientationEstimator {
+    pub fn new(settings: FusionSettings) -> Self {
+        Self {
+            settings,
+            q: None,
+            integral: [0.0; 3],
+            timer: SampleTimer::default(),
+        }
+    }
+
+    pub fn set_settings(&mut self, settings: &FusionSettings) {
+        if &self.settings != settings {
+            self.settings = settings.clone();
+            self.integral = [0.0; 3];
+        }
+    }
+
+    /// Start over from the next sample, e.g. after the link was dropped
+    pub fn reset(&mut self) {
+        self.q = None;
+        self.integral = [0.0; 3];
+        self.timer.reset();
+    }
+
+    /// Fuse one controller packet, timing it with the controller timestamp
+    pub fn update(&mut self, data: &ControllerData) -> Orientation {
+        let dt = self.timer.interval(data.timestamp);
+
+        let mag = [data.mag_x, data.mag_y, data.mag_z];
+        let mag = (self.settings.use_magnetometer && norm(mag) > 0.0).then_some(mag);
+        self.update_with(
+            [data.gyro_x, data.gyro_y, data.gyro_z],
+            [data.accel_x, data.accel_y, data.accel_z],
+            mag,
+            dt,
+        )
+    }
+
+    /// Fuse one sample: angular rate (rad/s), acceleration and magnetic
+    /// field in body coordinates (any scale) over `dt` seconds
+    pub fn update_with(
+        &mut self,
+        gyro: [f32; 3],
+        accel: [f32; 3],
+        mag: Option<[f32; 3]>,
+        dt: f32,
+    ) -> Orientation {
+        let mag = mag.filter(|m| norm(*m) > 0.0);
+        let q = match self.q {
+            None => initial_alignment(accel, mag),
+            Some(q) => match self.settings.algorithm {
+                FusionAlgorithm::Madgwick => {
+                    madgwick(q, gyro, accel, mag, dt, self.settings.madgwick_beta)
+                }
+                FusionAlgorithm::Mahony => self.mahony(q, gyro, accel, mag, dt),
+            },
+        };
+        self.q = Some(q);
+        q.into()
+    }
+
+    fn mahony(
+        &mut self,
+        q: Quaternion,
+        gyro: [f32; 3],
+        accel: [f32; 3],
+        mag: Option<[f32; 3]>,
+        dt: f32,
+    ) -> Quaternion {
+        let mut gyro = gyro;
+        if let Some(a) = normalize(accel) {
+            // Error between measured and estimated directions, in body frame
+            let v = q.conjugate().rotate([0.0, 0.0, 1.0]);
+            let mut error = cross(a, v);
+            if let Some(m) = mag.and_then(normalize) {
+                let w = q.conjugate().rotate(earth_field(q, m));
+                error = add(error, cross(m, w));
+            }
+
+            if self.settings.mahony_ki > 0.0 {
+                for (i, e) in self.integral.iter_mut().zip(error) {
+                    *i += self.settings.mahony_ki * e * dt;
+                }
+            } else {
+                self.integral = [0.0; 3];
+            }
+            for axis in 0..3 {
+                gyro[axis] += self.settings.mahony_kp * error[axis] + self.integral[axis];
+            }
+        }
+        integrate(q, rate_derivative(q, gyro), dt)
+    }
+}
+
+/// Orientation that explains the measured gravity and north directly
+fn initial_alignment(accel: [f32; 3], mag: Option<[f32; 3]>) -> Quaternion {
+    let Some(a) = normalize(accel) else {
+        return Quaternion::IDENTITY;
+    };
+    let tilt = EulerAngles {
+        roll: a[1].atan2(a[2]),
+        pitch: (-a[0]).atan2((a[1] * a[1] + a[2] * a[2]).sqrt()),
+        yaw: 0.0,
+    };
+    let yaw = mag
+        .map(|m| {
+            let h = Quaternion::from_euler(tilt).rotate(m);
+            -h[1].atan2(h[0])
+        })
+        .unwrap_or(0.0);
+    Quaternion::from_euler(EulerAngles { yaw, ..tilt })
+}
+
+fn madgwick(
+    q: Quaternion,
+    gyro: [f32; 3],
+    accel: [f32; 3],
+    mag: Option<[f32; 3]>,
+    dt: f32,
+    beta: f32,
+) -> Quaternion {
+    let q_dot = rate_derivative(q, gyro);
+    let Some(a) = normalize(accel) else {
+        return integrate(q, q_dot, dt);
+    };
+    let Quaternion {
+        w: q0,
+        x: q1,
+        y: q2,
+        z: q3,
+    } = q;
+
+    // Objective function and Jacobian for gravity
+    let f = [
+        2.0 * (q1 * q3 - q0 * q2) - a[0],
+        2.0 * (q0 * q1 + q2 * q3) - a[1],
+        2.0 * (0.5 - q1 * q1 - q2 * q2) - a[2],
+    ];
+    let j = [
+        [-2.0 * q2, 2.0 * q3, -2.0 * q0, 2.0 * q1],
+        [2.0 * q1, 2.0 * q0, 2.0 * q3, 2.0 * q2],
+        [0.0, -4.0 * q1, -4.0 * q2, 0.0],
+    ];
+    let mut step = transpose_mul(&j, f);
+
+    // And for the earth magnetic field, reduced to its north and down parts
+    if let Some(m) = mag.and_then(normalize) {
+        let b = earth_field(q, m);
+        let (bx, bz) = (b[0], b[2]);
+        let f = [
+            2.0 * bx * (0.5 - q2 * q2 - q3 * q3) + 2.0 * bz * (q1 * q3 - q0 * q2) - m[0],
+            2.0 * bx * (q1 * q2 - q0 * q3) + 2.0 * bz * (q0 * q1 + q2 * q3) - m[1],
+            2.0 * bx * (q0 * q2 + q1 * q3) + 2.0 * bz * (0.5 - q1 * q1 - q2 * q2) - m[2],
+        ];
+        let j = [
+            [
+                -2.0 * bz * q2,
+                2.0 * bz * q3,
+                -4.0 * bx * q2 - 2.0 * bz * q0,
+                -4.0 * bx * q3 + 2.0 * bz * q1,
+            ],
+            [
+                -2.0 * bx * q3 + 2.0 * bz * q1,
+                2.0 * bx * q2 + 2.0 * bz * q0,
+                2.0 * bx * q1 + 2.0 * bz * q3,
+                -2.0 * bx * q0 + 2.0 * bz * q2,
+            ],
+            [
+                2.0 * bx * q2,
+                2.0 * bx * q3 - 4.0 * bz * q1,
+                2.0 * bx * q0 - 4.0 * bz * q2,
+                2.0 * bx * q1,
+            ],
+        ];
+        let mag_step = transpose_mul(&j, f);
+        for (s, m) in step.iter_mut().zip(mag_step) {
+            *s += m;
+        }
+    }
+
+    // Fixed-size step along the normalized gradient
+    let length = step.iter().map(|s| s * s).sum::<f32>().sqrt();
+    if length <= f32::EPSILON {
+        return integrate(q, q_dot, dt);
+    }
+    let step = Quaternion {
+        w: step[0],
+        x: step[1],
+        y: step[2],
+        z: step[3],
+    };
+    integrate(q, q_dot.add(&step.scaled(-beta / length)), dt)
+}
+
+/// Measured field rotated into the earth frame, with the horizontal part
+/// moved onto the x axis
+fn earth_field(q: Quaternion, m: [f32; 3]) -> [f32; 3] {
+    let h = q.rotate(m);
+    [h[0].hypot(h[1]), 0.0, h[2]]
+}
+
+/// Quaternion derivative for an angular rate in body coordinates
+fn rate_derivative(q: Quaternion, gyro: [f32; 3]) -> Quaternion {
+    let omega = Quaternion {
+        w: 0.0,
+        x: gyro[0],
+        y: gyro[1],
+        z: gyro[2],
+    };
+    (q * omega).scaled(0.5)
+}
+
+fn integrate(q: Quaternion, q_dot: Quaternion, dt: f32) -> Quaternion {
+    q.add(&q_dot.scaled(dt)).normalized()
+}
+
+fn transpose_mul(j: &[[f32; 4]; 3], f: [f32; 3]) -> [f32; 4] {
+    let mut out = [0.0; 4];
+    for (row, value) in j.iter().zip(f) {
+        for (o, entry) in out.iter_mut().zip(row) {
+            *o += entry * value;
+        }
+    }
+    out
+}
+
+fn norm(v: [f32; 3]) -> f32 {
+    (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt()
+}
+
+fn normalize(v: [f32; 3]) -> Option<[f32; 3]> {
+    let n = norm(v);
+    (n > f32::EPSILON).then(|| [v[0] / n, v[1] / n, v[2] / n])
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn add(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 0.01;
+    /// Earth field pointing north and down, as in mid latitudes
+    const EARTH_FIELD: [f32; 3] = [0.5, 0.0, -0.8];
+
+    /// Angle of the rotation between two orientations (radians)
+    fn angle_between(a: &Quaternion, b: &Quaternion) -> f32 {
+        let dot = a.w * b.w + a.x * b.x + a.y * b.y + a.z * b.z;
+        2.0 * dot.abs().min(1.0).acos()
+    }
+
+    /// Accelerometer and magnetometer readings at a true orientation
+    fn sensors(truth: Quaternion) -> ([f32; 3], [f32; 3]) {
+        let to_body = truth.conjugate();
+        (to_body.rotate([0.0, 0.0, 1.0]), to_body.rotate(EARTH_FIELD))
+    }
+
+    fn estimator(algorithm: FusionAlgorithm, use_magnetometer: bool) -> OrientationEstimator {
+        OrientationEstimator::new(FusionSettings {
+            algorithm,
+            use_magnetometer,
+            ..FusionSettings::default()
+        })
+    }
+
+    /// Rotate at `rate` (body frame) for `seconds` from `start`, feeding
+    /// `bias` on top of the true rate; returns the truth and the estimate
+    fn simulate(
+        estimator: &mut OrientationEstimator,
+        start: Quaternion,
+        rate: [f32; 3],
+        bias: [f32; 3],
+        seconds: f32,
+        use_mag: bool,
+    ) -> (Quaternion, Orientation) {
+        let step = Quaternion::from_axis_angle(rate, norm(rate) * DT);
+        let mut truth = start;
+        let (accel, mag) = sensors(truth);
+        let mut estimate = estimator.update_with(rate, accel, use_mag.then_some(mag), DT);
+        for _ in 0..(seconds / DT).round() as usize {
+            truth = (truth * step).normalized();
+            let (accel, mag) = sensors(truth);
+            estimate = estimator.update_with(add(rate, bias), accel, use_mag.then_some(mag), DT);
+        }
+        (truth, estimate)
+    }
+
+    #[test]
+    fn test_tracks_known_rotations() {
+        let start = Quaternion::from_euler(EulerAngles {
+            roll: 0.3,
+            pitch: -0.2,
+            yaw: 1.0,
+        });
+        for algorithm in [FusionAlgorithm::Madgwick, FusionAlgorithm::Mahony] {
+            for rate in [[0.0, 0.0, 1.0], [0.8, 0.0, 0.0], [0.3, -0.5, 0.4]] {
+                let mut estimator = estimator(algorithm, true);
+                let (truth, estimate) = simulate(&mut estimator, start, rate, [0.0; 3], 1.5, true);
+                let error = angle_between(&truth, &estimate.quaternion);
+                assert!(error < 0.03, "{:?} {:?}: error {}", algorithm, rate, error);
+            }
+        }
+
+        // A pure yaw turn without magnetometer shows up in the Euler angles
+        let mut estimator = estimator(FusionAlgorithm::Madgwick, false);
+        let (_, estimate) = simulate(
+            &mut estimator,
+            Quaternion::IDENTITY,
+            [0.0, 0.0, 1.0],
+            [0.0; 3],
+            1.2,
+            false,
+        );
+        assert!(
+            (estimate.euler.yaw - 1.2).abs() < 0.02,
+            "{:?}",
+            estimate.euler
+        );
+        assert!(estimate.euler.roll.abs() < 0.01 && estimate.euler.pitch.abs() < 0.01);
+    }
+
+    #[test]
+    fn test_gravity_and_north_correct_gyro_drift() {
+        let bias = [0.05, 0.0, 0.02];
+        for algorithm in [FusionAlgorithm::Madgwick, FusionAlgorithm::Mahony] {
+            // Without magnetometer only the tilt is held, yaw drifts
+            let mut imu_only = estimator(algorithm, false);
+            let (_, estimate) = simulate(
+                &mut imu_only,
+                Quaternion::IDENTITY,
+                [0.0; 3],
+                bias,
+                20.0,
+                false,
+            );
+            assert!(estimate.euler.roll.abs() < 0.1, "{:?}", estimate.euler);
+            assert!(estimate.euler.yaw > 0.3, "{:?}", estimate.euler);
+
+            let mut with_mag = estimator(algorithm, true);
+            let (_, estimate) = simulate(
+                &mut with_mag,
+                Quaternion::IDENTITY,
+                [0.0; 3],
+                bias,
+                20.0,
+                true,
+            );
+            assert!(estimate.euler.roll.abs() < 0.1, "{:?}", estimate.euler);
+            assert!(
+                estimate.euler.yaw.abs() < 0.1,
+                "{:?} {:?}",
+                algorithm,
+                estimate.euler
+            );
+        }
+    }
+
+    #[test]
+    fn test_euler_round_trip() {
+        let euler = EulerAngles {
+            roll: -0.7,
+            pitch: 0.4,
+            yaw: 2.5,
+        };
+        let back = Quaternion::from_euler(euler).to_euler();
+        assert!((back.roll - euler.roll).abs() < 1e-5);
+        assert!((back.pitch - euler.pitch).abs() < 1e-5);
+        assert!((back.yaw - euler.yaw).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,229 @@
+//! System Sleep/Resume Handling
+//!
+//! The controller link does not survive a system suspend, but the OS may
+//! take a long time to report the disconnect after resume. [`PowerMonitor`]
+//! turns suspend/resume notifications from a [`PowerEventSource`] into
+//! explicit actions: drop the link cleanly before sleeping and reconnect
+//! as soon as the system is back.
+
+/// System power transition
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerEvent {
+    Suspend,
+    Resume,
+}
+
+/// Platform hook delivering power transitions
+pub trait PowerEventSource {
+    /// Next pending event, if any. Must not block.
+    fn poll(&mut self) -> Option<PowerEvent>;
+}
+
+/// Connection state the monitor needs to decide what to do
+#[derive(Debug, Clone, Copy)]
+pub struct LinkSnapshot {
+    /// Connected or in the middle of connecting
+    pub active: bool,
+    pub auto_reconnect: bool,
+    pub last_address: Option<u64>,
+}
+
+/// What the application has to do in response to a power event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerAction {
+    /// Release held buttons/keys and stop injecting input
+    ReleaseInputs,
+    /// Tear down the link without disabling auto-reconnect
+    Disconnect,
+    /// Run the reconnect policy for this address now
+    Reconnect(u64),
+}
+
+/// Tracks whether the system is suspended and what to restore on resume
+pub struct PowerMonitor {
+    source: Box<dyn PowerEventSource>,
+    suspended: bool,
+    /// Address to reconnect to once the system resumes
+    resume_target: Option<u64>,
+}
+
+impl PowerMonitor {
+    pub fn new(source: Box<dyn PowerEventSource>) -> Self {
+        Self {
+            source,
+            suspended: false,
+            resume_target: None,
+        }
+    }
+
+    /// Whether the system is (about to be) suspended
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
+    }
+
+    /// Drain pending power events and return the resulting actions
+    pub fn poll(&mut self, link: LinkSnapshot) -> Vec<PowerAction> {
+        let mut actions = Vec::new();
+        while let Some(event) = self.source.poll() {
+            actions.extend(self.handle(event, link));
+        }
+        actions
+    }
+
+    fn handle(&mut self, event: PowerEvent, link: LinkSnapshot) -> Vec<PowerAction> {
+        match event {
+            PowerEvent::Suspend => {
+                if self.suspended {
+                    return Vec::new();
+                }
+                self.suspended = true;
+                self.resume_target = link
+                    .last_address
+                    .filter(|_| link.auto_reconnect || link.active);
+
+                let mut actions = vec![PowerAction::ReleaseInputs];
+                if link.active {
+                    actions.push(PowerAction::Disconnect);
+                }
+                actions
+            }
+            PowerEvent::Resume => {
+                if self.suspended {
+                    self.suspended = false;
+                    return self
+                        .resume_target
+                        .take()
+                        .map(PowerAction::Reconnect)
+                        .into_iter()
+                        .collect();
+                }
+
+                // Suspend was missed (e.g. hibernation before we registered):
+                // an active link is almost certainly dead, start over.
+                let mut actions = Vec::new();
+                if link.active {
+                    actions.push(PowerAction::ReleaseInputs);
+                    actions.push(PowerAction::Disconnect);
+                    if let Some(address) = link.last_address {
+                        actions.push(PowerAction::Reconnect(address));
+                    }
+                }
+                actions
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+    use std::rc::Rc;
+
+    /// Event source fed directly by the test
+    #[derive(Clone, Default)]
+    struct FakePowerSource {
+        queue: Rc<RefCell<VecDeque<PowerEvent>>>,
+    }
+
+    impl FakePowerSource {
+        fn emit(&self, event: PowerEvent) {
+            self.queue.borrow_mut().push_back(event);
+        }
+    }
+
+    impl PowerEventSource for FakePowerSource {
+        fn poll(&mut self) -> Option<PowerEvent> {
+            self.queue.borrow_mut().pop_front()
+        }
+    }
+
+    const CONNECTED: LinkSnapshot = LinkSnapshot {
+        active: true,
+        auto_reconnect: true,
+        last_address: Some(0xAABB),
+    };
+
+    const DISCONNECTED: LinkSnapshot = LinkSnapshot {
+        active: false,
+        auto_reconnect: true,
+        last_address: Some(0xAABB),
+    };
+
+    fn monitor() -> (PowerMonitor, FakePowerSource) {
+        let source = FakePowerSource::default();
+        (PowerMonitor::new(Box::new(source.clone())), source)
+    }
+
+    #[test]
+    fn test_suspend_disconnects_and_resume_reconnects() {
+        let (mut monitor, source) = monitor();
+
+        source.emit(PowerEvent::Suspend);
+        assert_eq!(
+            monitor.poll(CONNECTED),
+            vec![PowerAction::ReleaseInputs, PowerAction::Disconnect]
+        );
+        assert!(monitor.is_suspended());
+
+        // The link reports disconnected while asleep
+        assert!(monitor.poll(DISCONNECTED).is_empty());
+
+        source.emit(PowerEvent::Resume);
+        assert_eq!(
+            monitor.poll(DISCONNECTED),
+            vec![PowerAction::Reconnect(0xAABB)]
+        );
+        assert!(!monitor.is_suspended());
+    }
+
+    #[test]
+    fn test_suspend_and_resume_in_one_poll() {
+        let (mut monitor, source) = monitor();
+
+        source.emit(PowerEvent::Suspend);
+        source.emit(PowerEvent::Suspend);
+        source.emit(PowerEvent::Resume);
+        assert_eq!(
+            monitor.poll(CONNECTED),
+            vec![
+                PowerAction::ReleaseInputs,
+                PowerAction::Disconnect,
+                PowerAction::Reconnect(0xAABB)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_no_reconnect_without_auto_reconnect() {
+        let (mut monitor, source) = monitor();
+        let manual = LinkSnapshot {
+            active: false,
+            auto_reconnect: false,
+            last_address: Some(0xAABB),
+        };
+
+        source.emit(PowerEvent::Suspend);
+        source.emit(PowerEvent::Resume);
+        assert_eq!(monitor.poll(manual), vec![PowerAction::ReleaseInputs]);
+    }
+
+    #[test]
+    fn test_resume_without_suspend_restarts_active_link() {
+        let (mut monitor, source) = monitor();
+
+        source.emit(PowerEvent::Resume);
+        assert_eq!(
+            monitor.poll(CONNECTED),
+            vec![
+                PowerAction::ReleaseInputs,
+                PowerAction::Disconnect,
+                PowerAction::Reconnect(0xAABB)
+            ]
+        );
+
+        source.emit(PowerEvent::Resume);
+        assert!(monitor.poll(DISCONNECTED).is_empty());
+    }
+}
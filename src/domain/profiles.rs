@@ -0,0 +1,234 @@
+//! Application Profiles
+//!
+//! A profile overrides bindings, control mode and sensitivity while a
+//! matching window is in the foreground. [`ProfileSwitcher`] polls a
+//! [`ForegroundWindowProvider`] and picks the first profile whose matcher
+//! accepts the current window.
+
+use crate::domain::input_mapper::BindingTable;
+use crate::domain::models::ControlMode;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// How often the foreground window is checked
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The window that currently receives input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForegroundWindow {
+    /// File name of the owning executable, e.g. `POWERPNT.EXE`
+    pub executable: String,
+    pub title: String,
+}
+
+/// Source of the foreground window
+pub trait ForegroundWindowProvider {
+    /// Current foreground window, `None` if unknown or owned by this
+    /// application
+    fn foreground(&mut self) -> Option<ForegroundWindow>;
+}
+
+/// Selects windows by executable name or title
+///
+/// Patterns are case-insensitive and support `*` and `?` wildcards.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum WindowMatcher {
+    Executable(String),
+    Title(String),
+}
+
+impl WindowMatcher {
+    pub fn matches(&self, window: &ForegroundWindow) -> bool {
+        match self {
+            WindowMatcher::Executable(pattern) => wildcard_match(pattern, &window.executable),
+            WindowMatcher::Title(pattern) => wildcard_match(pattern, &window.title),
+        }
+    }
+
+    pub fn pattern(&self) -> &str {
+        match self {
+            WindowMatcher::Executable(pattern) | WindowMatcher::Title(pattern) => pattern,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    pub matcher: WindowMatcher,
+    /// Mode to switch to when the profile becomes active
+    #[serde(default)]
+    pub mode: Option<ControlMode>,
+    /// Replaces the global sensitivity while active
+    #[serde(default)]
+    pub mouse_sensitivity: Option<f64>,
+    /// Checked before the global bindings
+    #[serde(default = "BindingTable::empty")]
+    pub bindings: BindingTable,
+}
+
+impl Profile {
+    pub fn new(name: impl Into<String>, matcher: WindowMatcher) -> Self {
+        Self {
+            name: name.into(),
+            matcher,
+            mode: None,
+            mouse_sensitivity: None,
+            bindings: BindingTable::empty(),
+        }
+    }
+}
+
+/// Tracks which profile matches the foreground window
+pub struct ProfileSwitcher {
+    provider: Box<dyn ForegroundWindowProvider>,
+    next_poll: Option<Instant>,
+    active: Option<String>,
+    last_window: Option<ForegroundWindow>,
+}
+
+impl ProfileSwitcher {
+    pub fn new(provider: Box<dyn ForegroundWindowProvider>) -> Self {
+        Self {
+            provider,
+            next_poll: None,
+            active: None,
+            last_window: None,
+        }
+    }
+
+    /// Name of the active profile, `None` when the defaults apply
+    pub fn active(&self) -> Option<&str> {
+        self.active.as_deref()
+    }
+
+    /// Most recent foreground window of another application
+    pub fn last_window(&self) -> Option<&ForegroundWindow> {
+        self.last_window.as_ref()
+    }
+
+    /// Re-check the foreground window (rate limited)
+    ///
+    /// Returns true when the active profile changed. The profile is kept
+    /// while the provider reports no window, e.g. while this application
+    /// has focus, so profiles can be edited without losing them.
+    pub fn poll(&mut self, profiles: &[Profile], now: Instant) -> bool {
+        if self.next_poll.is_some_and(|next| now < next) {
+            return false;
+        }
+        self.next_poll = Some(now + POLL_INTERVAL);
+
+        let Some(window) = self.provider.foreground() else {
+            return false;
+        };
+
+        let matched = profiles
+            .iter()
+            .find(|p| p.matcher.matches(&window))
+            .map(|p| p.name.clone());
+        self.last_window = Some(window);
+
+        if matched == self.active {
+            return false;
+        }
+        self.active = matched;
+        true
+    }
+}
+
+/// Case-insensitive glob match supporting `*` and `?`
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            // Let the last `*` swallow one more character
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, t));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// Provider returning whatever window the test put in place
+    struct FakeForeground(Rc<RefCell<Option<ForegroundWindow>>>);
+
+    impl ForegroundWindowProvider for FakeForeground {
+        fn foreground(&mut self) -> Option<ForegroundWindow> {
+            self.0.borrow().clone()
+        }
+    }
+
+    fn window(executable: &str, title: &str) -> Option<ForegroundWindow> {
+        Some(ForegroundWindow {
+            executable: executable.to_string(),
+            title: title.to_string(),
+        })
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("powerpnt.exe", "POWERPNT.EXE"));
+        assert!(wildcard_match("*PowerPoint*", "Deck.pptx - PowerPoint"));
+        assert!(wildcard_match("vlc?exe", "vlc.exe"));
+        assert!(wildcard_match("*a*b", "xaxxab"));
+        assert!(!wildcard_match("*.pdf", "notes.txt"));
+        assert!(!wildcard_match("vlc", "vlc.exe"));
+    }
+
+    #[test]
+    fn test_switches_to_first_matching_profile() {
+        let current = Rc::new(RefCell::new(window("POWERPNT.EXE", "Deck - PowerPoint")));
+        let mut switcher = ProfileSwitcher::new(Box::new(FakeForeground(current.clone())));
+        let profiles = [
+            Profile::new(
+                "Slides",
+                WindowMatcher::Executable("powerpnt.exe".to_string()),
+            ),
+            Profile::new(
+                "Any Office",
+                WindowMatcher::Title("*PowerPoint*".to_string()),
+            ),
+            Profile::new("Video", WindowMatcher::Executable("vlc.exe".to_string())),
+        ];
+        let start = Instant::now();
+
+        assert!(switcher.poll(&profiles, start));
+        assert_eq!(switcher.active(), Some("Slides"));
+
+        // Rate limited until the next poll interval
+        *current.borrow_mut() = window("vlc.exe", "Movie");
+        assert!(!switcher.poll(&profiles, start + Duration::from_millis(100)));
+        assert!(switcher.poll(&profiles, start + POLL_INTERVAL));
+        assert_eq!(switcher.active(), Some("Video"));
+
+        // Focus on our own window keeps the profile
+        *current.borrow_mut() = None;
+        assert!(!switcher.poll(&profiles, start + POLL_INTERVAL * 2));
+        assert_eq!(switcher.active(), Some("Video"));
+
+        *current.borrow_mut() = window("explorer.exe", "Files");
+        assert!(switcher.poll(&profiles, start + POLL_INTERVAL * 3));
+        assert_eq!(switcher.active(), None);
+    }
+}
@@ -0,0 +1,202 @@
+//! Scan Results Tracking
+//!
+//! Keeps the list of discovered devices fresh: merges repeated
+//! advertisements, smooths RSSI over time, expires devices that have not
+//! been seen recently and orders the list by proximity.
+
+use crate::domain::models::ScannedDevice;
+use std::time::{Duration, Instant};
+
+/// Weight of a new RSSI sample in the exponential moving average
+const RSSI_SMOOTHING_ALPHA: f32 = 0.3;
+
+/// A discovered device with its smoothed signal history
+#[derive(Debug, Clone)]
+pub struct TrackedDevice {
+    pub device: ScannedDevice,
+    /// Exponentially smoothed RSSI in dBm
+    pub smoothed_rssi: f32,
+    /// Number of advertisements received
+    pub sample_count: u32,
+    pub last_seen: Instant,
+}
+
+impl TrackedDevice {
+    /// Estimated path loss in dB (advertised TX power minus smoothed RSSI).
+    /// Lower means closer. Only available if the device advertises TX power.
+    pub fn path_loss(&self) -> Option<f32> {
+        self.device
+            .advertisement
+            .tx_power
+            .map(|tx| tx as f32 - self.smoothed_rssi)
+    }
+}
+
+/// Discovered devices, kept sorted from nearest to farthest
+#[derive(Debug, Default)]
+pub struct ScanResults {
+    devices: Vec<TrackedDevice>,
+}
+
+impl ScanResults {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an advertisement received at `now`
+    pub fn update(&mut self, device: ScannedDevice, now: Instant) {
+        let rssi = device.signal_strength as f32;
+
+        if let Some(existing) = self
+            .devices
+            .iter_mut()
+            .find(|d| d.device.address == device.address)
+        {
+            existing.smoothed_rssi += RSSI_SMOOTHING_ALPHA * (rssi - existing.smoothed_rssi);
+            existing.sample_count += 1;
+            existing.last_seen = now;
+            existing.device.signal_strength = device.signal_strength;
+            existing.device.advertisement.merge(&device.advertisement);
+            if device.name != "Unknown" {
+                existing.device.name = device.name;
+            }
+        } else {
+            self.devices.push(TrackedDevice {
+                device,
+                smoothed_rssi: rssi,
+                sample_count: 1,
+                last_seen: now,
+            });
+        }
+
+        self.sort_by_proximity();
+    }
+
+    /// Drop devices not seen for longer than `max_age`. Returns how many were removed.
+    pub fn prune(&mut self, now: Instant, max_age: Duration) -> usize {
+        let before = self.devices.len();
+        self.devices
+            .retain(|d| now.saturating_duration_since(d.last_seen) <= max_age);
+        before - self.devices.len()
+    }
+
+    /// Strongest device whose address is in `known`, once it has been heard
+    /// at least `min_samples` times (so a single loud packet doesn't win).
+    pub fn strongest_known(&self, known: &[u64], min_samples: u32) -> Option<&TrackedDevice> {
+        // The list is sorted, so the first match is the nearest one
+        self.devices
+            .iter()
+            .find(|d| known.contains(&d.device.address) && d.sample_count >= min_samples)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &TrackedDevice> {
+        self.devices.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.devices.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.devices.clear();
+    }
+
+    fn sort_by_proximity(&mut self) {
+        self.devices
+            .sort_by(|a, b| b.smoothed_rssi.total_cmp(&a.smoothed_rssi));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::models::AdvertisementData;
+
+    fn advert(address: u64, rssi: i16) -> ScannedDevice {
+        ScannedDevice {
+            name: "Unknown".to_string(),
+            address,
+            signal_strength: rssi,
+            advertisement: AdvertisementData::default(),
+        }
+    }
+
+    fn addresses(results: &ScanResults) -> Vec<u64> {
+        results.iter().map(|d| d.device.address).collect()
+    }
+
+    #[test]
+    fn test_rssi_is_smoothed() {
+        let now = Instant::now();
+        let mut results = ScanResults::new();
+        results.update(advert(1, -60), now);
+        results.update(advert(1, -80), now);
+
+        let device = results.iter().next().unwrap();
+        // One sample moves the average 30 % of the way
+        assert_eq!(device.smoothed_rssi, -66.0);
+        assert_eq!(device.device.signal_strength, -80);
+        assert_eq!(device.sample_count, 2);
+    }
+
+    #[test]
+    fn test_sorted_nearest_first() {
+        let now = Instant::now();
+        let mut results = ScanResults::new();
+        results.update(advert(1, -80), now);
+        results.update(advert(2, -50), now);
+        results.update(advert(3, -65), now);
+        assert_eq!(addresses(&results), [2, 3, 1]);
+
+        // A single weak packet doesn't drop the nearest device to the back
+        results.update(advert(2, -90), now);
+        assert_eq!(addresses(&results), [2, 3, 1]);
+        results.update(advert(2, -90), now);
+        assert_eq!(addresses(&results), [3, 2, 1]);
+    }
+
+    #[test]
+    fn test_prune_drops_devices_not_seen_recently() {
+        let start = Instant::now();
+        let mut results = ScanResults::new();
+        results.update(advert(1, -60), start);
+        results.update(advert(2, -70), start + Duration::from_secs(5));
+
+        let max_age = Duration::from_secs(10);
+        assert_eq!(results.prune(start + Duration::from_secs(10), max_age), 0);
+        assert_eq!(results.prune(start + Duration::from_secs(11), max_age), 1);
+        assert_eq!(addresses(&results), [2]);
+        assert_eq!(results.prune(start + Duration::from_secs(16), max_age), 1);
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn test_strongest_known_needs_enough_samples() {
+        let now = Instant::now();
+        let mut results = ScanResults::new();
+        results.update(advert(1, -40), now);
+        results.update(advert(2, -60), now);
+        results.update(advert(2, -60), now);
+        results.update(advert(3, -70), now);
+        results.update(advert(3, -70), now);
+
+        // Unknown devices are never picked, however close
+        let known = [2, 3];
+        assert_eq!(
+            results.strongest_known(&known, 2).map(|d| d.device.address),
+            Some(2)
+        );
+
+        // A device heard only once doesn't qualify yet
+        let known = [1, 3];
+        assert_eq!(
+            results.strongest_known(&known, 2).map(|d| d.device.address),
+            Some(3)
+        );
+        assert_eq!(
+            results.strongest_known(&known, 1).map(|d| d.device.address),
+            Some(1)
+        );
+        assert!(results.strongest_known(&known, 3).is_none());
+    }
+}
@@ -0,0 +1,307 @@
+//! Touchpad Scrolling
+//!
+//! Turns finger movement into high-resolution wheel deltas (120 per notch)
+//! proportional to how fast the finger moves, instead of whole notches. A
+//! flick keeps scrolling after the finger lifts, slowing down with the
+//! configured friction until it stops or the pad is touched again.
+//!
+//! Scrolling can be limited to one axis, or locked to whichever axis the
+//! finger moves along first.
+
+use crate::domain::axes::AxisSettings;
+use crate::domain::filters::SampleTimer;
+use crate::domain::models::ControllerData;
+use crate::domain::transfer::SubPixel;
+use serde::{Deserialize, Serialize};
+
+/// Wheel delta of one notch
+pub const WHEEL_DELTA: f64 = 120.0;
+/// Finger travel (normalized units) that decides the locked axis
+const LOCK_DISTANCE: f64 = 0.05;
+/// Time constant (seconds) of the finger velocity estimate
+const VELOCITY_SMOOTHING: f64 = 0.05;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScrollAxes {
+    Vertical,
+    Horizontal,
+    Both,
+    /// Whichever axis the finger moves along first, until it lifts
+    Locked,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScrollSettings {
+    pub axes: ScrollAxes,
+    /// Notches per unit of finger travel (half the pad width)
+    pub speed: f64,
+    /// Keep scrolling after a flick
+    pub momentum: bool,
+    /// How quickly momentum dies down, per second
+    pub friction: f64,
+    /// Momentum slower than this (units per second) stops
+    pub min_velocity: f64,
+}
+
+impl Default for ScrollSettings {
+    fn default() -> Self {
+        Self {
+            axes: ScrollAxes::Locked,
+            speed: 5.0,
+            momentum: true,
+            friction: 4.0,
+            min_velocity: 0.1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+pub struct ScrollEngine {
+    settings: ScrollSettings,
+    timer: SampleTimer,
+    last_pos: Option<[f64; 2]>,
+    /// Finger start while the locked axis is undecided
+    lock_origin: Option<[f64; 2]>,
+    locked: Option<Axis>,
+    /// Smoothed finger velocity, carried on as momentum after lift-off
+    velocity: [f64; 2],
+    coasting: bool,
+    remainder: SubPixel,
+}
+
+impl ScrollEngine {
+    pub fn new(settings: ScrollSettings) -> Self {
+        Self {
+            settings,
+            timer: SampleTimer::default(),
+            last_pos: None,
+            lock_origin: None,
+            locked: None,
+            velocity: [0.0; 2],
+            coasting: false,
+            remainder: SubPixel::default(),
+        }
+    }
+
+    pub fn set_settings(&mut self, settings: &ScrollSettings) {
+        if self.settings != *settings {
+            self.settings = settings.clone();
+        }
+    }
+
+    /// Wheel deltas `(horizontal, vertical)` for this packet
+    ///
+    /// Positive vertical scrolls up and positive horizontal scrolls right,
+    /// as with a physical wheel. While `active` is false nothing scrolls
+    /// and momentum is dropped.
+    pub fn update(
+        &mut self,
+        data: &ControllerData,
+        axes: &AxisSettings,
+        active: bool,
+    ) -> (i32, i32) {
+        let dt = self.timer.interval(data.timestamp) as f64;
+        if !active {
+            self.stop();
+            return (0, 0);
+        }
+
+        let motion = if data.touchpad_touched {
+            self.coasting = false;
+            let pos = [data.processed_touchpad_x, data.processed_touchpad_y];
+            let Some(last) = self.last_pos.replace(pos) else {
+                self.lock_origin = Some(pos);
+                return (0, 0);
+            };
+            let delta = [pos[0] - last[0], pos[1] - last[1]];
+            let blend = 1.0 - (-dt / VELOCITY_SMOOTHING).exp();
+            self.velocity = [0, 1].map(|axis| {
+                let velocity = self.velocity[axis];
+                velocity + (delta[axis] / dt - velocity) * blend
+            });
+            self.decide_lock(pos);
+            delta
+        } else {
+            if self.last_pos.take().is_some() {
+                // Lift-off: fling with the last finger velocity
+                self.coasting = self.settings.momentum;
+            }
+            if !self.coasting {
+                self.stop();
+                return (0, 0);
+            }
+            let decay = (-self.settings.friction.max(0.0) * dt).exp();
+            self.velocity = self.velocity.map(|v| v * decay);
+            if self.velocity[0].hypot(self.velocity[1]) < self.settings.min_velocity {
+                self.stop();
+                return (0, 0);
+            }
+            self.velocity.map(|v| v * dt)
+        };
+
+        let [dx, dy] = axes.map_scroll(self.restrict(motion));
+        // Moving the finger down scrolls down, like dragging a scrollbar
+        let scale = self.settings.speed * WHEEL_DELTA;
+        self.remainder.take([dx * scale, -dy * scale])
+    }
+
+    /// Drop any momentum and the current touch
+    pub fn stop(&mut self) {
+        self.last_pos = None;
+        self.lock_origin = None;
+        self.locked = None;
+        self.velocity = [0.0; 2];
+        self.coasting = false;
+        self.remainder.reset();
+    }
+
+    fn decide_lock(&mut self, pos: [f64; 2]) {
+        let Some(origin) = self.lock_origin else {
+            return;
+        };
+        let [dx, dy] = [pos[0] - origin[0], pos[1] - origin[1]];
+        if dx.hypot(dy) >= LOCK_DISTANCE {
+            self.locked = Some(if dx.abs() > dy.abs() {
+                Axis::Horizontal
+            } else {
+                Axis::Vertical
+            });
+            self.lock_origin = None;
+        }
+    }
+
+    fn restrict(&self, [x, y]: [f64; 2]) -> [f64; 2] {
+        match (self.settings.axes, self.locked) {
+            (ScrollAxes::Both, _) => [x, y],
+            (ScrollAxes::Vertical, _) | (ScrollAxes::Locked, Some(Axis::Vertical)) => [0.0, y],
+            (ScrollAxes::Horizontal, _) | (ScrollAxes::Locked, Some(Axis::Horizontal)) => [x, 0.0],
+            // Not decided yet
+            (ScrollAxes::Locked, None) => [0.0, 0.0],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn touch(y: f64, ms: i64) -> ControllerData {
+        touch_at([0.01, y], ms)
+    }
+
+    fn touch_at([x, y]: [f64; 2], ms: i64) -> ControllerData {
+        ControllerData {
+            touchpad_touched: true,
+            processed_touchpad_x: x,
+            processed_touchpad_y: y,
+            ..lift(ms)
+        }
+    }
+
+    fn lift(ms: i64) -> ControllerData {
+        ControllerData {
+            timestamp: ms as f64 / 1000.0,
+            ..ControllerData::default()
+        }
+    }
+
+    /// Total scroll of a drag right and slightly down, 10 ms per packet
+    fn drag(engine: &mut ScrollEngine) -> (i32, i32) {
+        let axes = AxisSettings::default();
+        let mut total = (0, 0);
+        for i in 0..20 {
+            let pos = [i as f64 * 0.02, i as f64 * 0.01];
+            let (h, v) = engine.update(&touch_at(pos, i * 10), &axes, true);
+            total = (total.0 + h, total.1 + v);
+        }
+        total
+    }
+
+    #[test]
+    fn test_scrolls_with_finger_speed_and_coasts_after_lift_off() {
+        let axes = AxisSettings::default();
+        let mut engine = ScrollEngine::new(ScrollSettings::default());
+
+        // Finger moves down 2 units a second (0.02 per 10 ms packet) and
+        // scrolls down, locked to the vertical axis
+        let mut total = (0, 0);
+        for i in 0..20 {
+            let (h, v) = engine.update(&touch(i as f64 * 0.02, i * 10), &axes, true);
+            total = (total.0 + h, total.1 + v);
+        }
+        assert_eq!(total.0, 0);
+        // 17 moves of 0.02 units at 5 notches per unit, once the first
+        // 0.05 units have decided the axis
+        assert!((total.1 + 204).abs() <= 1, "scrolled {}", total.1);
+
+        // After lift-off it keeps going, slower and slower, then stops
+        let mut deltas = Vec::new();
+        for i in 20..300 {
+            deltas.push(engine.update(&lift(i * 10), &axes, true).1);
+        }
+        assert!(deltas[0] < 0);
+        assert!(deltas[0].abs() >= deltas[50].abs());
+        assert_eq!(*deltas.last().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_axis_modes() {
+        let scroll = |axes: ScrollAxes| {
+            drag(&mut ScrollEngine::new(ScrollSettings {
+                axes,
+                ..ScrollSettings::default()
+            }))
+        };
+
+        let (h, v) = scroll(ScrollAxes::Both);
+        assert!(h > 0 && v < 0, "{:?}", (h, v));
+        assert!((h + 2 * v).abs() <= 2, "{:?}", (h, v));
+        assert_eq!(scroll(ScrollAxes::Vertical), (0, v));
+        assert_eq!(scroll(ScrollAxes::Horizontal), (h, 0));
+
+        // Mostly sideways, so the lock picks the horizontal axis
+        let (locked, none) = scroll(ScrollAxes::Locked);
+        assert!(locked > 0 && locked < h, "{}", locked);
+        assert_eq!(none, 0);
+    }
+
+    #[test]
+    fn test_no_coasting_without_momentum() {
+        let axes = AxisSettings::default();
+        let mut engine = ScrollEngine::new(ScrollSettings {
+            momentum: false,
+            ..ScrollSettings::default()
+        });
+        assert_ne!(drag(&mut engine), (0, 0));
+        for i in 20..40 {
+            assert_eq!(engine.update(&lift(i * 10), &axes, true), (0, 0));
+        }
+    }
+
+    #[test]
+    fn test_touching_again_stops_coasting() {
+        let axes = AxisSettings::default();
+        let mut engine = ScrollEngine::new(ScrollSettings::default());
+        drag(&mut engine);
+        assert_ne!(engine.update(&lift(200), &axes, true), (0, 0));
+        assert_ne!(engine.update(&lift(210), &axes, true), (0, 0));
+
+        // A resting finger catches the scroll
+        for i in 22..50 {
+            assert_eq!(
+                engine.update(&touch_at([0.3, 0.3], i * 10), &axes, true),
+                (0, 0)
+            );
+        }
+        // and lifting it again does not pick the old momentum back up
+        for i in 50..60 {
+            assert_eq!(engine.update(&lift(i * 10), &axes, true), (0, 0));
+        }
+    }
+}
@@ -1,4 +1,20 @@
-use crate::domain::models::TouchpadCalibration;
+use crate::domain::actions::Action;
+use crate::domain::axes::AxisSettings;
+use crate::domain::buttons::ButtonTimings;
+use crate::domain::calibration::{DeviceCalibration, GyroBiasSettings};
+use crate::domain::circular::CircularSettings;
+use crate::domain::filters::{FilterKind, FilterSettings};
+use crate::domain::gestures::GestureSettings;
+use crate::domain::imu::PointerSettings;
+use crate::domain::input_mapper::{BindingTable, InputEvent, BINDINGS_VERSION};
+use crate::domain::macros::Macro;
+use crate::domain::models::{ControlMode, TouchpadCalibration};
+use crate::domain::motion::MotionSettings;
+use crate::domain::orientation::FusionSettings;
+use crate::domain::profiles::Profile;
+use crate::domain::scroll::ScrollSettings;
+use crate::domain::shapes::ShapeSettings;
+use crate::domain::transfer::{PointerTransfer, TransferCurve};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
@@ -67,6 +83,9 @@ fn default_rotation() -> String {
 pub struct Settings {
     pub mouse_sensitivity: f64,
     pub touchpad_calibration: TouchpadCalibration,
+    /// IMU calibrations per controller address
+    #[serde(default)]
+    pub device_calibrations: Vec<DeviceCalibration>,
     pub known_bluetooth_addresses: Vec<u64>,
     pub last_connected_address: Option<u64>,
     pub enable_touchpad: bool,
@@ -79,10 +98,68 @@ pub struct Settings {
 
     // Phase 2: Input Polish Settings
     pub dead_zone: f64,
-    pub enable_smoothing: bool,
-    pub smoothing_factor: usize,
-    pub enable_acceleration: bool,
-    pub acceleration_power: f64,
+    /// Smoothing of the touchpad position
+    #[serde(default)]
+    pub touchpad_filter: FilterSettings,
+    /// Touchpad smoothing from files written before `touchpad_filter`; only
+    /// read to migrate, never written
+    #[serde(default, skip_serializing)]
+    pub(crate) enable_smoothing: Option<bool>,
+    #[serde(default, skip_serializing)]
+    pub(crate) smoothing_factor: Option<usize>,
+    /// Smoothing of the air mouse rotation
+    #[serde(default = "FilterSettings::imu")]
+    pub imu_filter: FilterSettings,
+    /// Touchpad cursor speed curve and gains
+    #[serde(default = "PointerTransfer::touchpad")]
+    pub touchpad_transfer: PointerTransfer,
+    /// Air mouse cursor speed curve and gains
+    #[serde(default)]
+    pub imu_transfer: PointerTransfer,
+    /// Touchpad acceleration from files written before `touchpad_transfer`;
+    /// only read to migrate, never written
+    #[serde(default, skip_serializing)]
+    pub(crate) enable_acceleration: Option<bool>,
+    #[serde(default, skip_serializing)]
+    pub(crate) acceleration_power: Option<f64>,
+
+    // Input Mapping
+    #[serde(default)]
+    pub input_bindings: BindingTable,
+    /// Defaults version `input_bindings` was last migrated to; missing in
+    /// files written before versioning
+    #[serde(default)]
+    pub bindings_version: u32,
+    #[serde(default)]
+    pub button_timings: ButtonTimings,
+    #[serde(default)]
+    pub scroll_ring: CircularSettings,
+    /// Touchpad scrolling in air mouse mode
+    #[serde(default)]
+    pub scrolling: ScrollSettings,
+    #[serde(default)]
+    pub gestures: GestureSettings,
+    #[serde(default)]
+    pub shape_gestures: ShapeSettings,
+    #[serde(default)]
+    pub motion_gestures: MotionSettings,
+    #[serde(default)]
+    pub fusion: FusionSettings,
+    #[serde(default)]
+    pub gyro_bias: GyroBiasSettings,
+    #[serde(default)]
+    pub pointer: PointerSettings,
+    /// How the controller is held
+    #[serde(default)]
+    pub axes: AxisSettings,
+    #[serde(default)]
+    pub macros: Vec<Macro>,
+    /// Talk to the OS media session in Media mode instead of sending keys
+    #[serde(default = "default_true")]
+    pub media_session_enabled: bool,
+    /// Per-application overrides, first match wins
+    #[serde(default)]
+    pub profiles: Vec<Profile>,
 
     // Advanced BLE Settings
     #[serde(default = "default_service_uuid")]
@@ -93,10 +170,22 @@ pub struct Settings {
     pub ble_command_char_uuid: String,
     #[serde(default = "default_false")]
     pub debug_show_all_devices: bool,
+    /// Device id of the Bluetooth adapter to connect through (None = system
+    /// default); scanning always uses the system default
+    #[serde(default)]
+    pub bluetooth_adapter_id: Option<String>,
+
+    // Scan Settings
+    #[serde(default = "default_scan_device_timeout_secs")]
+    pub scan_device_timeout_secs: u64,
+    #[serde(default = "default_false")]
+    pub scan_auto_connect: bool,
 
     // Debug Settings
     #[serde(default = "default_false")]
     pub debug_raw_data_logging: bool,
+    #[serde(default = "default_connection_history_size")]
+    pub connection_history_size: usize,
 
     // Pairing Settings
     #[serde(default = "default_pairing_max_retries")]
@@ -110,6 +199,7 @@ impl Default for Settings {
         Self {
             mouse_sensitivity: 2.0,
             touchpad_calibration: TouchpadCalibration::default(),
+            device_calibrations: Vec::new(),
             known_bluetooth_addresses: Vec::new(),
             last_connected_address: None,
             enable_touchpad: true,
@@ -118,19 +208,46 @@ impl Default for Settings {
             log_settings: LogSettings::default(),
             // Defaults based on C# implementation
             dead_zone: 0.1, // 10%
-            enable_smoothing: true,
-            smoothing_factor: 5, // 5 samples
-            enable_acceleration: true,
-            acceleration_power: 1.5,
+            touchpad_filter: FilterSettings::default(),
+            enable_smoothing: None,
+            smoothing_factor: None,
+            imu_filter: FilterSettings::imu(),
+            touchpad_transfer: PointerTransfer::touchpad(),
+            imu_transfer: PointerTransfer::default(),
+            enable_acceleration: None,
+            acceleration_power: None,
+
+            // Input Mapping
+            input_bindings: BindingTable::default(),
+            bindings_version: BINDINGS_VERSION,
+            button_timings: ButtonTimings::default(),
+            scroll_ring: CircularSettings::default(),
+            scrolling: ScrollSettings::default(),
+            gestures: GestureSettings::default(),
+            shape_gestures: ShapeSettings::default(),
+            motion_gestures: MotionSettings::default(),
+            fusion: FusionSettings::default(),
+            gyro_bias: GyroBiasSettings::default(),
+            pointer: PointerSettings::default(),
+            axes: AxisSettings::default(),
+            macros: Vec::new(),
+            media_session_enabled: true,
+            profiles: Vec::new(),
 
             // Advanced BLE Settings
             ble_service_uuid: default_service_uuid(),
             ble_data_char_uuid: default_data_uuid(),
             ble_command_char_uuid: default_command_uuid(),
             debug_show_all_devices: false,
+            bluetooth_adapter_id: None,
+
+            // Scan Settings
+            scan_device_timeout_secs: default_scan_device_timeout_secs(),
+            scan_auto_connect: false,
 
             // Debug Settings
             debug_raw_data_logging: false,
+            connection_history_size: default_connection_history_size(),
 
             // Pairing Settings
             pairing_max_retries: default_pairing_max_retries(),
@@ -139,6 +256,32 @@ impl Default for Settings {
     }
 }
 
+impl Settings {
+    /// Carry settings from older files over to the fields that replaced them
+    fn migrate_legacy(&mut self) {
+        // The moving average keeps the feel these files were tuned with,
+        // rather than switching them to the One Euro default
+        if let Some(enabled) = self.enable_smoothing.take() {
+            if let Some(window) = self.smoothing_factor.take() {
+                self.touchpad_filter.window = window;
+            }
+            self.touchpad_filter.kind = if enabled {
+                FilterKind::MovingAverage
+            } else {
+                FilterKind::Off
+            };
+        }
+        if let Some(enabled) = self.enable_acceleration.take() {
+            let exponent = self.acceleration_power.take().unwrap_or(1.5);
+            self.touchpad_transfer.curve = if enabled {
+                TransferCurve::Power { exponent }
+            } else {
+                TransferCurve::Linear
+            };
+        }
+    }
+}
+
 fn default_service_uuid() -> String {
     "4f63756c-7573-2054-6872-65656d6f7465".to_string()
 }
@@ -148,6 +291,12 @@ fn default_data_uuid() -> String {
 fn default_command_uuid() -> String {
     "c8c51726-81bc-483b-a052-f7a14ea3d282".to_string()
 }
+fn default_scan_device_timeout_secs() -> u64 {
+    10
+}
+fn default_connection_history_size() -> usize {
+    10
+}
 fn default_pairing_max_retries() -> u32 {
     3
 }
@@ -158,19 +307,37 @@ fn default_pairing_retry_delay_ms() -> u64 {
 pub struct SettingsService {
     settings: Settings,
     settings_path: PathBuf,
+    /// Name of the profile matching the foreground window (not persisted)
+    active_profile: Option<String>,
 }
 
 impl SettingsService {
     pub fn new() -> anyhow::Result<Self> {
         let settings_path = Self::get_settings_path()?;
-        let settings = Self::load_from_file(&settings_path).unwrap_or_default();
+        let mut settings: Settings = Self::load_from_file(&settings_path).unwrap_or_default();
+        settings.migrate_legacy();
+        if settings.bindings_version < BINDINGS_VERSION {
+            settings.input_bindings.migrate(settings.bindings_version);
+            settings.bindings_version = BINDINGS_VERSION;
+        }
 
         Ok(Self {
             settings,
             settings_path,
+            active_profile: None,
         })
     }
 
+    /// Settings that are never loaded from or written to disk
+    #[cfg(test)]
+    pub(crate) fn in_memory(settings: Settings) -> Self {
+        Self {
+            settings,
+            settings_path: PathBuf::new(),
+            active_profile: None,
+        }
+    }
+
     fn get_settings_path() -> anyhow::Result<PathBuf> {
         let mut path = dirs::config_dir()
             .ok_or_else(|| anyhow::anyhow!("Could not determine config directory"))?;
@@ -200,11 +367,97 @@ impl SettingsService {
         &mut self.settings
     }
 
+    pub fn set_active_profile(&mut self, name: Option<String>) {
+        self.active_profile = name;
+    }
+
+    pub fn active_profile(&self) -> Option<&Profile> {
+        let name = self.active_profile.as_ref()?;
+        self.settings.profiles.iter().find(|p| &p.name == name)
+    }
+
+    /// Sensitivity of the active profile, or the global one
+    pub fn mouse_sensitivity(&self) -> f64 {
+        self.active_profile()
+            .and_then(|p| p.mouse_sensitivity)
+            .unwrap_or(self.settings.mouse_sensitivity)
+    }
+
+    /// Actions bound to `event` in `mode`, preferring the active profile's
+    /// bindings over the global table
+    pub fn resolve_binding(&self, mode: ControlMode, event: InputEvent) -> &[Action] {
+        let profile_actions = self
+            .active_profile()
+            .map(|p| p.bindings.resolve(mode, event))
+            .unwrap_or(&[]);
+        if profile_actions.is_empty() {
+            self.settings.input_bindings.resolve(mode, event)
+        } else {
+            profile_actions
+        }
+    }
+
     pub fn update_calibration(&mut self, calibration: TouchpadCalibration) -> anyhow::Result<()> {
         self.settings.touchpad_calibration = calibration;
         self.save()
     }
 
+    pub fn device_calibration(&self, address: u64) -> Option<&DeviceCalibration> {
+        self.settings
+            .device_calibrations
+            .iter()
+            .find(|c| c.address == address)
+    }
+
+    /// Calibration entry for a controller, created on first use
+    pub fn device_calibration_mut(&mut self, address: u64) -> &mut DeviceCalibration {
+        let calibrations = &mut self.settings.device_calibrations;
+        let index = match calibrations.iter().position(|c| c.address == address) {
+            Some(index) => index,
+            None => {
+                calibrations.push(DeviceCalibration::new(address));
+                calibrations.len() - 1
+            }
+        };
+        &mut calibrations[index]
+    }
+
+    /// Store a macro, replacing any existing macro with the same name
+    pub fn save_macro(&mut self, new_macro: Macro) -> anyhow::Result<()> {
+        let macros = &mut self.settings.macros;
+        match macros.iter_mut().find(|m| m.name == new_macro.name) {
+            Some(existing) => *existing = new_macro,
+            None => macros.push(new_macro),
+        }
+        self.save()
+    }
+
+    /// Follow a macro rename in the global and profile bindings and in the
+    /// steps of other macros
+    pub fn rename_macro(&mut self, old: &str, new: &str) {
+        let settings = &mut self.settings;
+        settings.input_bindings.rename_macro(old, new);
+        for profile in &mut settings.profiles {
+            profile.bindings.rename_macro(old, new);
+        }
+        for steps in settings.macros.iter_mut().map(|m| &mut m.steps) {
+            steps.iter_mut().for_each(|a| a.rename_macro(old, new));
+        }
+    }
+
+    /// Remove a macro and every step that plays it
+    pub fn delete_macro(&mut self, name: &str) {
+        let settings = &mut self.settings;
+        settings.macros.retain(|m| m.name != name);
+        settings.input_bindings.forget_macro(name);
+        for profile in &mut settings.profiles {
+            profile.bindings.forget_macro(name);
+        }
+        for steps in settings.macros.iter_mut().map(|m| &mut m.steps) {
+            steps.retain_mut(|a| a.forget_macro(name));
+        }
+    }
+
     pub fn add_known_address(&mut self, address: u64) -> anyhow::Result<()> {
         if !self.settings.known_bluetooth_addresses.contains(&address) {
             self.settings.known_bluetooth_addresses.push(address);
@@ -213,3 +466,61 @@ impl SettingsService {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Settings as an older version wrote them: `replaced` missing and the
+    /// legacy fields present
+    fn legacy(replaced: &str, fields: serde_json::Value) -> Settings {
+        let mut json = serde_json::to_value(Settings::default()).unwrap();
+        let object = json.as_object_mut().unwrap();
+        object.remove(replaced);
+        object.extend(fields.as_object().unwrap().clone());
+        let mut settings: Settings = serde_json::from_value(json).unwrap();
+        settings.migrate_legacy();
+        settings
+    }
+
+    #[test]
+    fn test_legacy_acceleration_becomes_transfer_curve() {
+        let settings = legacy(
+            "touchpad_transfer",
+            serde_json::json!({ "enable_acceleration": true, "acceleration_power": 2.0 }),
+        );
+        assert_eq!(
+            settings.touchpad_transfer.curve,
+            TransferCurve::Power { exponent: 2.0 }
+        );
+
+        let settings = legacy(
+            "touchpad_transfer",
+            serde_json::json!({ "enable_acceleration": false, "acceleration_power": 2.0 }),
+        );
+        assert_eq!(settings.touchpad_transfer.curve, TransferCurve::Linear);
+
+        // Not written back
+        let json = serde_json::to_value(&settings).unwrap();
+        assert!(json.get("enable_acceleration").is_none());
+    }
+
+    #[test]
+    fn test_legacy_smoothing_becomes_touchpad_filter() {
+        let settings = legacy(
+            "touchpad_filter",
+            serde_json::json!({ "enable_smoothing": true, "smoothing_factor": 8 }),
+        );
+        assert_eq!(settings.touchpad_filter.kind, FilterKind::MovingAverage);
+        assert_eq!(settings.touchpad_filter.window, 8);
+
+        let settings = legacy(
+            "touchpad_filter",
+            serde_json::json!({ "enable_smoothing": false, "smoothing_factor": 8 }),
+        );
+        assert_eq!(settings.touchpad_filter.kind, FilterKind::Off);
+
+        let json = serde_json::to_value(&settings).unwrap();
+        assert!(json.get("enable_smoothing").is_none());
+    }
+}
@@ -0,0 +1,432 @@
+//! Drawn Shape Gestures
+//!
+//! Template matching of whole touchpad strokes with the $1 unistroke
+//! recognizer (Wobbrock et al., 2007). A stroke is resampled to a fixed
+//! number of points, rotated so the line from its first point to its
+//! centroid is horizontal, scaled to a square and centered; the score is
+//! the average point distance to the closest template at the best rotation
+//! within +/-45 degrees. Every step is deterministic.
+
+use crate::domain::models::ControllerData;
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+
+/// Touchpad position in normalized pad units (range 2.0, y down)
+pub type Point = (f64, f64);
+
+const RESAMPLE_POINTS: usize = 64;
+const SQUARE_SIZE: f64 = 250.0;
+const ANGLE_RANGE: f64 = std::f64::consts::FRAC_PI_4;
+const ANGLE_PRECISION: f64 = 2.0 * std::f64::consts::PI / 180.0;
+/// Strokes whose short side is below this fraction of the long side are
+/// lines, which $1 cannot tell apart
+const MIN_ASPECT: f64 = 0.2;
+/// Longest stroke kept, older points are dropped
+const MAX_STROKE_POINTS: usize = 2048;
+
+/// A named shape with one or more normalized sample strokes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Shape {
+    /// Stable id used by `InputEvent::Shape` bindings
+    pub id: u32,
+    pub name: String,
+    pub templates: Vec<Vec<Point>>,
+}
+
+/// Best template match for a stroke
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recognition {
+    pub id: u32,
+    pub name: String,
+    /// 0 (no resemblance) to 1 (identical)
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShapeSettings {
+    pub enabled: bool,
+    /// Minimum score for a stroke to count as a shape
+    pub min_score: f64,
+    /// Minimum extent of a stroke (normalized pad units)
+    pub min_size: f64,
+    pub shapes: Vec<Shape>,
+}
+
+impl Default for ShapeSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_score: 0.8,
+            min_size: 0.5,
+            shapes: builtin_shapes(),
+        }
+    }
+}
+
+impl ShapeSettings {
+    /// Add an empty shape and return its id
+    pub fn add_shape(&mut self, name: &str) -> u32 {
+        let id = self.shapes.iter().map(|s| s.id + 1).max().unwrap_or(0);
+        self.shapes.push(Shape {
+            id,
+            name: name.to_string(),
+            templates: Vec::new(),
+        });
+        id
+    }
+
+    /// Store `stroke` as another sample of shape `id`
+    pub fn train(&mut self, id: u32, stroke: &[Point]) -> anyhow::Result<()> {
+        if !self.is_drawable(stroke) {
+            bail!("Stroke is too small or too straight for a shape");
+        }
+        let template = normalize(stroke).context("Stroke has no length")?;
+        let shape = self
+            .shapes
+            .iter_mut()
+            .find(|s| s.id == id)
+            .context("Unknown shape")?;
+        shape.templates.push(template);
+        Ok(())
+    }
+
+    /// Closest shape to `stroke`, regardless of the score threshold
+    pub fn best_match(&self, stroke: &[Point]) -> Option<Recognition> {
+        if !self.is_drawable(stroke) {
+            return None;
+        }
+        let candidate = normalize(stroke)?;
+
+        let (shape, distance) = self
+            .shapes
+            .iter()
+            .flat_map(|shape| shape.templates.iter().map(move |t| (shape, t)))
+            .map(|(shape, template)| (shape, distance_at_best_angle(&candidate, template)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))?;
+
+        let half_diagonal = 0.5 * (2.0 * SQUARE_SIZE * SQUARE_SIZE).sqrt();
+        Some(Recognition {
+            id: shape.id,
+            name: shape.name.clone(),
+            score: 1.0 - distance / half_diagonal,
+        })
+    }
+
+    /// Whether a match scores high enough to count as its shape
+    pub fn accepts(&self, recognition: &Recognition) -> bool {
+        recognition.score >= self.min_score
+    }
+
+    fn is_drawable(&self, stroke: &[Point]) -> bool {
+        let (width, height) = bounding_box(stroke);
+        let long = width.max(height);
+        long >= self.min_size && width.min(height) >= long * MIN_ASPECT
+    }
+}
+
+/// Collects the complete path of each touch
+#[derive(Debug, Default)]
+pub struct StrokeCapture {
+    points: Vec<Point>,
+    touching: bool,
+}
+
+impl StrokeCapture {
+    /// Feed a touchpad sample, returns the stroke when the finger lifts
+    pub fn process(&mut self, data: &ControllerData) -> Option<Vec<Point>> {
+        if data.touchpad_touched {
+            if !self.touching {
+                self.touching = true;
+                self.points.clear();
+            }
+            if self.points.len() == MAX_STROKE_POINTS {
+                self.points.remove(0);
+            }
+            self.points
+                .push((data.processed_touchpad_x, data.processed_touchpad_y));
+            None
+        } else if self.touching {
+            self.touching = false;
+            Some(std::mem::take(&mut self.points))
+        } else {
+            None
+        }
+    }
+}
+
+/// Circle, check mark, Z and triangle, drawn the usual way
+fn builtin_shapes() -> Vec<Shape> {
+    let circle: Vec<Point> = (0..=36)
+        .map(|i| {
+            // Clockwise from the top
+            let angle = (i as f64 * 10.0 - 90.0).to_radians();
+            (0.6 * angle.cos(), 0.6 * angle.sin())
+        })
+        .collect();
+    let strokes = [
+        ("Circle", circle),
+        (
+            "Check Mark",
+            polyline(&[(-0.5, 0.0), (-0.15, 0.4), (0.6, -0.6)]),
+        ),
+        (
+            "Z",
+            polyline(&[(-0.5, -0.5), (0.5, -0.5), (-0.5, 0.5), (0.5, 0.5)]),
+        ),
+        (
+            "Triangle",
+            polyline(&[(0.0, -0.5), (0.5, 0.4), (-0.5, 0.4), (0.0, -0.5)]),
+        ),
+    ];
+
+    strokes
+        .into_iter()
+        .enumerate()
+        .filter_map(|(id, (name, stroke))| {
+            Some(Shape {
+                id: id as u32,
+                name: name.to_string(),
+                templates: vec![normalize(&stroke)?],
+            })
+        })
+        .collect()
+}
+
+/// Corner points joined by straight segments
+fn polyline(corners: &[Point]) -> Vec<Point> {
+    let mut points = vec![corners[0]];
+    for pair in corners.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        for i in 1..=10 {
+            let t = i as f64 / 10.0;
+            points.push((a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t));
+        }
+    }
+    points
+}
+
+fn distance(a: Point, b: Point) -> f64 {
+    (b.0 - a.0).hypot(b.1 - a.1)
+}
+
+fn path_length(points: &[Point]) -> f64 {
+    points.windows(2).map(|w| distance(w[0], w[1])).sum()
+}
+
+fn centroid(points: &[Point]) -> Point {
+    let n = points.len() as f64;
+    let (x, y) = points
+        .iter()
+        .fold((0.0, 0.0), |acc, p| (acc.0 + p.0, acc.1 + p.1));
+    (x / n, y / n)
+}
+
+fn bounding_box(points: &[Point]) -> (f64, f64) {
+    let (mut min_x, mut min_y) = (f64::MAX, f64::MAX);
+    let (mut max_x, mut max_y) = (f64::MIN, f64::MIN);
+    for &(x, y) in points {
+        min_x = min_x.min(x);
+        max_x = max_x.max(x);
+        min_y = min_y.min(y);
+        max_y = max_y.max(y);
+    }
+    if points.is_empty() {
+        (0.0, 0.0)
+    } else {
+        (max_x - min_x, max_y - min_y)
+    }
+}
+
+/// Resample, rotate, scale and translate a stroke into template space
+fn normalize(stroke: &[Point]) -> Option<Vec<Point>> {
+    if stroke.len() < 2 || path_length(stroke) <= f64::EPSILON {
+        return None;
+    }
+    let points = resample(stroke, RESAMPLE_POINTS);
+
+    let c = centroid(&points);
+    let indicative_angle = (c.1 - points[0].1).atan2(c.0 - points[0].0);
+    let points = rotate_by(&points, -indicative_angle);
+
+    let (width, height) = bounding_box(&points);
+    let (sx, sy) = (
+        SQUARE_SIZE / width.max(f64::EPSILON),
+        SQUARE_SIZE / height.max(f64::EPSILON),
+    );
+    let c = centroid(&points);
+    Some(
+        points
+            .iter()
+            .map(|p| ((p.0 - c.0) * sx, (p.1 - c.1) * sy))
+            .collect(),
+    )
+}
+
+/// `n` points evenly spaced along the path
+fn resample(points: &[Point], n: usize) -> Vec<Point> {
+    let interval = path_length(points) / (n - 1) as f64;
+    let mut resampled = vec![points[0]];
+    let mut travelled = 0.0;
+    let mut previous = points[0];
+    let mut i = 1;
+
+    while i < points.len() && resampled.len() < n {
+        let current = points[i];
+        let segment = distance(previous, current);
+        if segment > 0.0 && travelled + segment >= interval {
+            let t = (interval - travelled) / segment;
+            let q = (
+                previous.0 + t * (current.0 - previous.0),
+                previous.1 + t * (current.1 - previous.1),
+            );
+            resampled.push(q);
+            previous = q;
+            travelled = 0.0;
+        } else {
+            travelled += segment;
+            previous = current;
+            i += 1;
+        }
+    }
+    // Rounding can leave the last point out
+    while resampled.len() < n {
+        resampled.push(points[points.len() - 1]);
+    }
+    resampled
+}
+
+fn rotate_by(points: &[Point], angle: f64) -> Vec<Point> {
+    let c = centroid(points);
+    let (sin, cos) = angle.sin_cos();
+    points
+        .iter()
+        .map(|p| {
+            let (dx, dy) = (p.0 - c.0, p.1 - c.1);
+            (dx * cos - dy * sin + c.0, dx * sin + dy * cos + c.1)
+        })
+        .collect()
+}
+
+fn path_distance(a: &[Point], b: &[Point]) -> f64 {
+    a.iter().zip(b).map(|(p, q)| distance(*p, *q)).sum::<f64>() / a.len() as f64
+}
+
+/// Golden section search for the rotation that fits the template best
+fn distance_at_best_angle(points: &[Point], template: &[Point]) -> f64 {
+    let phi = 0.5 * (5.0_f64.sqrt() - 1.0);
+    let distance_at = |angle: f64| path_distance(&rotate_by(points, angle), template);
+
+    let (mut a, mut b) = (-ANGLE_RANGE, ANGLE_RANGE);
+    let mut x1 = phi * a + (1.0 - phi) * b;
+    let mut f1 = distance_at(x1);
+    let mut x2 = (1.0 - phi) * a + phi * b;
+    let mut f2 = distance_at(x2);
+
+    while (b - a).abs() > ANGLE_PRECISION {
+        if f1 < f2 {
+            b = x2;
+            x2 = x1;
+            f2 = f1;
+            x1 = phi * a + (1.0 - phi) * b;
+            f1 = distance_at(x1);
+        } else {
+            a = x1;
+            x1 = x2;
+            f1 = f2;
+            x2 = (1.0 - phi) * a + phi * b;
+            f2 = distance_at(x2);
+        }
+    }
+    f1.min(f2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `corners` redrawn smaller, off-center, slightly rotated and wobbly
+    fn hand_drawn(corners: &[Point], rotation_degrees: f64) -> Vec<Point> {
+        let (sin, cos) = rotation_degrees.to_radians().sin_cos();
+        polyline(corners)
+            .iter()
+            .enumerate()
+            .map(|(i, &(x, y))| {
+                let wobble = 0.02 * (i as f64 * 1.7).sin();
+                let (x, y) = (x * 0.7 + wobble, y * 0.7 - wobble);
+                (x * cos - y * sin + 0.1, x * sin + y * cos - 0.05)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_recognizes_builtin_shapes_from_synthetic_strokes() {
+        let settings = ShapeSettings::default();
+        let strokes = [
+            (
+                "Check Mark",
+                hand_drawn(&[(-0.5, 0.0), (-0.15, 0.4), (0.6, -0.6)], 10.0),
+            ),
+            (
+                "Z",
+                hand_drawn(&[(-0.5, -0.5), (0.5, -0.5), (-0.5, 0.5), (0.5, 0.5)], -15.0),
+            ),
+            (
+                "Triangle",
+                hand_drawn(&[(0.0, -0.5), (0.5, 0.4), (-0.5, 0.4), (0.0, -0.5)], 20.0),
+            ),
+            (
+                "Circle",
+                hand_drawn(
+                    &(0..=12)
+                        .map(|i| {
+                            let angle = (i as f64 * 30.0 - 90.0).to_radians();
+                            (angle.cos(), angle.sin())
+                        })
+                        .collect::<Vec<_>>(),
+                    5.0,
+                ),
+            ),
+        ];
+
+        for (name, stroke) in strokes {
+            let recognition = settings.best_match(&stroke).expect(name);
+            assert_eq!(recognition.name, name);
+            assert!(settings.accepts(&recognition), "{:?}", recognition);
+            // Deterministic: the same stroke always scores the same
+            assert_eq!(settings.best_match(&stroke), Some(recognition));
+        }
+    }
+
+    #[test]
+    fn test_lines_and_small_strokes_are_not_shapes() {
+        let settings = ShapeSettings::default();
+        assert_eq!(
+            settings.best_match(&polyline(&[(-0.8, 0.0), (0.8, 0.1)])),
+            None
+        );
+        assert_eq!(
+            settings.best_match(&hand_drawn(&[(0.0, -0.1), (0.1, 0.1), (-0.1, 0.1)], 0.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_trained_shape_is_recognized() {
+        let mut settings = ShapeSettings {
+            shapes: Vec::new(),
+            ..ShapeSettings::default()
+        };
+        let arrow = [(-0.6, 0.6), (0.0, -0.6), (0.6, 0.6), (0.0, 0.2)];
+        let id = settings.add_shape("Arrow");
+        assert!(settings
+            .train(id, &polyline(&[(-0.8, 0.0), (0.8, 0.0)]))
+            .is_err());
+        settings.train(id, &polyline(&arrow)).unwrap();
+
+        let recognition = settings.best_match(&hand_drawn(&arrow, 10.0)).unwrap();
+        assert_eq!(recognition.id, id);
+        assert!(recognition.score > 0.9);
+        assert_eq!(settings.add_shape("Second"), id + 1);
+    }
+}
@@ -0,0 +1,216 @@
+//! Pointer Transfer Functions
+//!
+//! Map input motion onto cursor motion. The curve gives a gain for each
+//! input speed: flat for a linear feel, rising so that slow movements stay
+//! precise while fast flicks cross the screen. The result is scaled by the
+//! source's own sensitivity and per-axis gain, and by the global
+//! sensitivity (which profiles can override).
+//!
+//! Speeds are in source units per second: touchpad widths (normalized,
+//! range 2.0) for the touchpad, radians for the air mouse.
+//!
+//! Cursor motion is fractional; `SubPixel` carries what does not make a
+//! whole pixel over to the next update so slow movements still add up.
+
+use serde::{Deserialize, Serialize};
+
+/// Speeds the curve editor shows; faster input uses the gain at the edge
+pub const MAX_CURVE_SPEED: f64 = 5.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TransferCurve {
+    /// Constant gain of 1
+    Linear,
+    /// Gain `speed^(exponent - 1)`: 1 at one unit per second
+    Power { exponent: f64 },
+    /// Gain rising smoothly from `low` to `high` around the `midpoint`
+    /// speed, like Windows' "Enhance pointer precision"
+    Sigmoid {
+        low: f64,
+        high: f64,
+        midpoint: f64,
+        steepness: f64,
+    },
+    /// User-drawn gain per speed, interpolated linearly between points
+    /// sorted by speed
+    Piecewise { points: Vec<[f64; 2]> },
+}
+
+impl TransferCurve {
+    /// Each kind with its default shape, for choosing in the editor
+    pub fn presets() -> [TransferCurve; 4] {
+        [
+            TransferCurve::Linear,
+            TransferCurve::Power { exponent: 1.5 },
+            TransferCurve::Sigmoid {
+                low: 0.5,
+                high: 2.0,
+                midpoint: 1.0,
+                steepness: 4.0,
+            },
+            TransferCurve::Piecewise {
+                points: vec![[0.0, 0.5], [1.0, 1.0], [3.0, 2.0], [5.0, 2.5]],
+            },
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TransferCurve::Linear => "Linear",
+            TransferCurve::Power { .. } => "Power",
+            TransferCurve::Sigmoid { .. } => "Sigmoid",
+            TransferCurve::Piecewise { .. } => "Custom",
+        }
+    }
+
+    pub fn gain(&self, speed: f64) -> f64 {
+        let speed = speed.abs();
+        let gain = match self {
+            TransferCurve::Linear => 1.0,
+            TransferCurve::Power { exponent } => speed.powf(exponent - 1.0),
+            TransferCurve::Sigmoid {
+                low,
+                high,
+                midpoint,
+                steepness,
+            } => low + (high - low) / (1.0 + (-(speed - midpoint) * steepness).exp()),
+            TransferCurve::Piecewise { points } => interpolate(points, speed),
+        };
+        if gain.is_finite() {
+            gain.max(0.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+fn interpolate(points: &[[f64; 2]], speed: f64) -> f64 {
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return 1.0;
+    };
+    if speed <= first[0] {
+        return first[1];
+    }
+    if speed >= last[0] {
+        return last[1];
+    }
+    points
+        .windows(2)
+        .find(|pair| speed <= pair[1][0])
+        .map(|pair| {
+            let ([x0, y0], [x1, y1]) = (pair[0], pair[1]);
+            if x1 - x0 <= f64::EPSILON {
+                y1
+            } else {
+                y0 + (y1 - y0) * (speed - x0) / (x1 - x0)
+            }
+        })
+        .unwrap_or(last[1])
+}
+
+/// Curve and gains for one input source
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PointerTransfer {
+    pub curve: TransferCurve,
+    /// Source sensitivity, on top of the global one
+    pub sensitivity: f64,
+    pub gain_x: f64,
+    pub gain_y: f64,
+}
+
+impl Default for PointerTransfer {
+    fn default() -> Self {
+        Self {
+            curve: TransferCurve::Linear,
+            sensitivity: 1.0,
+            gain_x: 1.0,
+            gain_y: 1.0,
+        }
+    }
+}
+
+impl PointerTransfer {
+    /// Touchpad defaults: the power curve it always had
+    pub fn touchpad() -> Self {
+        Self {
+            curve: TransferCurve::Power { exponent: 1.5 },
+            ..Self::default()
+        }
+    }
+
+    /// Cursor motion for an input motion moving at `speed`
+    pub fn apply(&self, delta: [f64; 2], speed: f64, global_sensitivity: f64) -> [f64; 2] {
+        let scale = self.curve.gain(speed) * self.sensitivity * global_sensitivity;
+        [
+            delta[0] * scale * self.gain_x,
+            delta[1] * scale * self.gain_y,
+        ]
+    }
+}
+
+/// Turns fractional cursor motion into whole pixels, keeping the remainder
+#[derive(Debug, Default, Clone)]
+pub struct SubPixel {
+    remainder: [f64; 2],
+}
+
+impl SubPixel {
+    /// Whole pixels to move now; the fraction is added to the next delta
+    pub fn take(&mut self, delta: [f64; 2]) -> (i32, i32) {
+        let [x, y] = [0, 1].map(|axis| {
+            let total = self.remainder[axis] + delta[axis];
+            let whole = total.trunc();
+            self.remainder[axis] = total - whole;
+            whole as i32
+        });
+        (x, y)
+    }
+
+    pub fn reset(&mut self) {
+        self.remainder = [0.0; 2];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_curves_shape_the_gain() {
+        let [linear, power, sigmoid, piecewise] = TransferCurve::presets();
+        assert_eq!(linear.gain(3.0), 1.0);
+        assert!((power.gain(4.0) - 2.0).abs() < 1e-9);
+        assert_eq!(power.gain(0.0), 0.0);
+
+        // Sigmoid: near `low` when slow, `high` when fast, halfway at the midpoint
+        assert!((sigmoid.gain(0.0) - 0.5).abs() < 0.05);
+        assert!((sigmoid.gain(1.0) - 1.25).abs() < 1e-9);
+        assert!((sigmoid.gain(4.0) - 2.0).abs() < 0.01);
+
+        // Piecewise interpolates and holds its ends
+        assert!((piecewise.gain(2.0) - 1.5).abs() < 1e-9);
+        assert_eq!(piecewise.gain(10.0), 2.5);
+        assert_eq!(TransferCurve::Piecewise { points: vec![] }.gain(1.0), 1.0);
+
+        // Axis gains and both sensitivities multiply
+        let transfer = PointerTransfer {
+            curve: TransferCurve::Linear,
+            sensitivity: 2.0,
+            gain_x: 1.0,
+            gain_y: 0.5,
+        };
+        assert_eq!(transfer.apply([10.0, 10.0], 1.0, 3.0), [60.0, 30.0]);
+    }
+
+    #[test]
+    fn test_sub_pixel_motion_adds_up() {
+        let mut sub_pixel = SubPixel::default();
+        let moved: Vec<(i32, i32)> = (0..12).map(|_| sub_pixel.take([0.25, -0.25])).collect();
+        assert_eq!(moved[0], (0, 0));
+        let total = moved
+            .iter()
+            .fold((0, 0), |acc, m| (acc.0 + m.0, acc.1 + m.1));
+        assert_eq!(total, (3, -3));
+    }
+}
@@ -0,0 +1,337 @@
+//! Bluetooth Adapter Module
+//!
+//! Enumerates local Bluetooth adapters and monitors the selected one:
+//! radio on/off changes and adapters being plugged in or removed are
+//! reported as [`AppEvent::AdapterState`] / [`AppEvent::AdaptersChanged`]
+//! instead of surfacing later as generic connection failures.
+//!
+//! Scanning always runs on the system default adapter, so its radio is
+//! watched as well and reported as [`AppEvent::ScanAdapterState`].
+
+use crate::domain::models::{AdapterState, AppEvent, BluetoothAdapterInfo};
+use anyhow::Result;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+use windows::core::HSTRING;
+use windows::Devices::Bluetooth::BluetoothAdapter;
+use windows::Devices::Enumeration::{DeviceInformation, DeviceInformationUpdate, DeviceWatcher};
+use windows::Devices::Radios::{Radio, RadioState};
+use windows::Foundation::TypedEventHandler;
+
+/// State changes of one adapter's radio, reported as `event`
+struct RadioWatch {
+    event: fn(AdapterState) -> AppEvent,
+    /// Set when the last selection found no adapter
+    missing: bool,
+    radio: Option<Radio>,
+    radio_token: Option<i64>,
+}
+
+impl RadioWatch {
+    fn new(event: fn(AdapterState) -> AppEvent) -> Self {
+        Self {
+            event,
+            missing: false,
+            radio: None,
+            radio_token: None,
+        }
+    }
+
+    fn state(&self) -> AdapterState {
+        match &self.radio {
+            Some(radio) => radio
+                .State()
+                .map(map_radio_state)
+                .unwrap_or(AdapterState::Unknown),
+            None if self.missing => AdapterState::Unavailable,
+            None => AdapterState::Unknown,
+        }
+    }
+
+    /// Watch the radio of `adapter` (None = no adapter), reporting the
+    /// resulting state
+    async fn watch(
+        &mut self,
+        adapter: Option<&BluetoothAdapter>,
+        sender: &mpsc::UnboundedSender<AppEvent>,
+    ) -> Result<AdapterState> {
+        self.stop();
+        let Some(adapter) = adapter else {
+            self.missing = true;
+            let _ = sender.send((self.event)(AdapterState::Unavailable));
+            return Ok(AdapterState::Unavailable);
+        };
+        self.missing = false;
+
+        let radio = adapter.GetRadioAsync()?.await?;
+        let sender_clone = sender.clone();
+        let event = self.event;
+        let handler = TypedEventHandler::new(move |radio: windows::core::Ref<Radio>, _| {
+            if let Some(radio) = radio.as_ref() {
+                let state = map_radio_state(radio.State()?);
+                info!("Bluetooth radio state changed: {:?}", state);
+                let _ = sender_clone.send(event(state));
+            }
+            Ok(())
+        });
+        self.radio_token = Some(radio.StateChanged(&handler)?);
+        self.radio = Some(radio);
+
+        let state = self.state();
+        let _ = sender.send((self.event)(state));
+        Ok(state)
+    }
+
+    fn stop(&mut self) {
+        if let (Some(radio), Some(token)) = (self.radio.take(), self.radio_token.take()) {
+            let _ = radio.RemoveStateChanged(token);
+        }
+    }
+}
+
+/// Monitors the radios of the selected and the default adapter, and
+/// adapter arrival/removal
+pub struct AdapterMonitor {
+    event_sender: mpsc::UnboundedSender<AppEvent>,
+    selected: Option<BluetoothAdapterInfo>,
+    /// Radio of the selected adapter, used for connecting
+    radio: RadioWatch,
+    /// Radio of the system default adapter, used for scanning
+    scan_radio: RadioWatch,
+    watcher: Option<DeviceWatcher>,
+}
+
+impl AdapterMonitor {
+    pub fn new(event_sender: mpsc::UnboundedSender<AppEvent>) -> Self {
+        Self {
+            event_sender,
+            selected: None,
+            radio: RadioWatch::new(AppEvent::AdapterState),
+            scan_radio: RadioWatch::new(AppEvent::ScanAdapterState),
+            watcher: None,
+        }
+    }
+
+    /// List all Bluetooth adapters known to the system
+    pub async fn list_adapters() -> Result<Vec<BluetoothAdapterInfo>> {
+        let selector = BluetoothAdapter::GetDeviceSelector()?;
+        let devices = DeviceInformation::FindAllAsyncAqsFilter(&selector)?.await?;
+
+        let mut adapters = Vec::new();
+        for device_info in devices {
+            let id = device_info.Id()?;
+            match BluetoothAdapter::FromIdAsync(&id)?.await {
+                Ok(adapter) => adapters.push(BluetoothAdapterInfo {
+                    id: id.to_string(),
+                    name: device_info.Name()?.to_string(),
+                    address: adapter.BluetoothAddress()?,
+                    supports_le: adapter.IsLowEnergySupported()?,
+                }),
+                Err(e) => warn!("Skipping adapter {}: {}", id, e),
+            }
+        }
+
+        Ok(adapters)
+    }
+
+    /// Currently selected adapter, if one was found
+    pub fn selected(&self) -> Option<&BluetoothAdapterInfo> {
+        self.selected.as_ref()
+    }
+
+    /// Current state of the selected adapter's radio
+    pub fn state(&self) -> AdapterState {
+        self.radio.state()
+    }
+
+    /// Current state of the default adapter's radio, which scanning uses
+    pub fn scan_state(&self) -> AdapterState {
+        self.scan_radio.state()
+    }
+
+    /// Select an adapter by device id (`None` = system default) and start
+    /// monitoring its radio and the default adapter's. Reports both states
+    /// and returns the default adapter's.
+    pub async fn select(&mut self, adapter_id: Option<&str>) -> Result<AdapterState> {
+        self.ensure_watcher()?;
+
+        let default = BluetoothAdapter::GetDefaultAsync()?.await.ok();
+        let scan_state = self
+            .scan_radio
+            .watch(default.as_ref(), &self.event_sender)
+            .await?;
+
+        let adapter = match adapter_id {
+            Some(id) => BluetoothAdapter::FromIdAsync(&HSTRING::from(id))?
+                .await
+                .ok(),
+            None => default,
+        };
+
+        let Some(adapter) = adapter else {
+            warn!(
+                "Bluetooth adapter not available (requested: {:?})",
+                adapter_id
+            );
+            self.selected = None;
+            self.radio.watch(None, &self.event_sender).await?;
+            return Ok(scan_state);
+        };
+
+        let id = adapter.DeviceId()?.to_string();
+        let name = DeviceInformation::CreateFromIdAsync(&adapter.DeviceId()?)?
+            .await
+            .and_then(|info| info.Name())
+            .map(|n| n.to_string())
+            .unwrap_or_else(|_| "Bluetooth Adapter".to_string());
+        self.selected = Some(BluetoothAdapterInfo {
+            id,
+            name,
+            address: adapter.BluetoothAddress()?,
+            supports_le: adapter.IsLowEnergySupported()?,
+        });
+
+        let state = self.radio.watch(Some(&adapter), &self.event_sender).await?;
+        info!(
+            "Monitoring Bluetooth adapter {:?}: {:?} (scanning: {:?})",
+            self.selected.as_ref().map(|a| &a.name),
+            state,
+            scan_state
+        );
+        Ok(scan_state)
+    }
+
+    /// Start watching for adapters being added or removed
+    fn ensure_watcher(&mut self) -> Result<()> {
+        if self.watcher.is_some() {
+            return Ok(());
+        }
+
+        let watcher =
+            DeviceInformation::CreateWatcherAqsFilter(&BluetoothAdapter::GetDeviceSelector()?)?;
+
+        // The initial enumeration reports every existing adapter as "added"
+        let enumerated = Arc::new(AtomicBool::new(false));
+
+        let sender = self.event_sender.clone();
+        let flag = enumerated.clone();
+        watcher.Added(&TypedEventHandler::new(
+            move |_: windows::core::Ref<DeviceWatcher>,
+                  info: windows::core::Ref<DeviceInformation>| {
+                if flag.load(Ordering::SeqCst) {
+                    if let Some(info) = info.as_ref() {
+                        info!("Bluetooth adapter added: {}", info.Name()?);
+                    }
+                    let _ = sender.send(AppEvent::AdaptersChanged);
+                }
+                Ok(())
+            },
+        ))?;
+
+        let sender = self.event_sender.clone();
+        watcher.Removed(&TypedEventHandler::new(
+            move |_: windows::core::Ref<DeviceWatcher>,
+                  update: windows::core::Ref<DeviceInformationUpdate>| {
+                if let Some(update) = update.as_ref() {
+                    info!("Bluetooth adapter removed: {}", update.Id()?);
+                }
+                let _ = sender.send(AppEvent::AdaptersChanged);
+                Ok(())
+            },
+        ))?;
+
+        // Updated must be subscribed for Added/Removed to be raised
+        watcher.Updated(&TypedEventHandler::new(
+            |_: windows::core::Ref<DeviceWatcher>,
+             _: windows::core::Ref<DeviceInformationUpdate>| Ok(()),
+        ))?;
+
+        let flag = enumerated;
+        watcher.EnumerationCompleted(&TypedEventHandler::new(
+            move |_: windows::core::Ref<DeviceWatcher>, _| {
+                flag.store(true, Ordering::SeqCst);
+                Ok(())
+            },
+        ))?;
+
+        watcher.Start()?;
+        self.watcher = Some(watcher);
+        Ok(())
+    }
+}
+
+impl Drop for AdapterMonitor {
+    fn drop(&mut self) {
+        self.radio.stop();
+        self.scan_radio.stop();
+        if let Some(watcher) = self.watcher.take() {
+            let _ = watcher.Stop();
+        }
+    }
+}
+
+fn map_radio_state(state: RadioState) -> AdapterState {
+    match state {
+        RadioState::On => AdapterState::Ready,
+        RadioState::Off | RadioState::Disabled => AdapterState::RadioOff,
+        _ => AdapterState::Unknown,
+    }
+}
+
+/// Device id of an LE device as seen through a specific adapter
+///
+/// Windows identifies LE devices as
+/// `BluetoothLE#BluetoothLE<adapter address>-<device address>`.
+pub fn le_device_id(adapter_address: u64, device_address: u64) -> String {
+    format!(
+        "BluetoothLE#BluetoothLE{}-{}",
+        format_address(adapter_address),
+        format_address(device_address)
+    )
+}
+
+/// Format a 48-bit Bluetooth address as `aa:bb:cc:dd:ee:ff`
+fn format_address(address: u64) -> String {
+    (0..6)
+        .rev()
+        .map(|i| format!("{:02x}", (address >> (i * 8)) & 0xFF))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_radio_state_mapping() {
+        assert_eq!(map_radio_state(RadioState::On), AdapterState::Ready);
+        assert_eq!(map_radio_state(RadioState::Off), AdapterState::RadioOff);
+        assert_eq!(
+            map_radio_state(RadioState::Disabled),
+            AdapterState::RadioOff
+        );
+        assert_eq!(map_radio_state(RadioState::Unknown), AdapterState::Unknown);
+    }
+
+    #[test]
+    fn test_unselected_adapter_state() {
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let mut monitor = AdapterMonitor::new(tx);
+        assert_eq!(monitor.state(), AdapterState::Unknown);
+        assert_eq!(monitor.scan_state(), AdapterState::Unknown);
+        monitor.radio.missing = true;
+        assert_eq!(monitor.state(), AdapterState::Unavailable);
+        assert_eq!(monitor.scan_state(), AdapterState::Unknown);
+    }
+
+    #[test]
+    fn test_le_device_id() {
+        assert_eq!(
+            le_device_id(0x0011_2233_44AA, 0x2C_BA_BA_01_02_03),
+            "BluetoothLE#BluetoothLE00:11:22:33:44:aa-2c:ba:ba:01:02:03"
+        );
+    }
+}
@@ -0,0 +1,203 @@
+//! BLE Advertisement Decoding
+//!
+//! Pure decoding of advertisement data sections (AD structures) into
+//! [`AdvertisementData`]. The scanner extracts the raw `(type, payload)`
+//! pairs from the OS and hands them over here, so this module has no
+//! platform dependencies.
+
+use crate::domain::models::AdvertisementData;
+use tracing::trace;
+
+/// AD type codes from the Bluetooth Assigned Numbers document
+pub mod ad_type {
+    pub const FLAGS: u8 = 0x01;
+    pub const SHORTENED_LOCAL_NAME: u8 = 0x08;
+    pub const COMPLETE_LOCAL_NAME: u8 = 0x09;
+    pub const TX_POWER_LEVEL: u8 = 0x0A;
+    pub const SERVICE_DATA_16: u8 = 0x16;
+    pub const SERVICE_DATA_32: u8 = 0x20;
+    pub const SERVICE_DATA_128: u8 = 0x21;
+    pub const MANUFACTURER_SPECIFIC: u8 = 0xFF;
+}
+
+/// Bluetooth Base UUID suffix used to expand 16/32-bit UUIDs
+const BASE_UUID_SUFFIX: &str = "0000-1000-8000-00805f9b34fb";
+
+/// Decode a list of `(data type, payload)` sections
+///
+/// Unknown section types are ignored, malformed sections (too short for
+/// their type) are skipped.
+pub fn parse_data_sections(sections: &[(u8, Vec<u8>)]) -> AdvertisementData {
+    let mut data = AdvertisementData::default();
+
+    for (data_type, payload) in sections {
+        match *data_type {
+            ad_type::FLAGS => {
+                if let Some(&flags) = payload.first() {
+                    data.flags = Some(flags);
+                }
+            }
+            ad_type::SHORTENED_LOCAL_NAME => {
+                // A complete name always wins over a shortened one
+                if data.local_name.is_none() {
+                    data.local_name = decode_name(payload);
+                }
+            }
+            ad_type::COMPLETE_LOCAL_NAME => {
+                if let Some(name) = decode_name(payload) {
+                    data.local_name = Some(name);
+                }
+            }
+            ad_type::TX_POWER_LEVEL => {
+                if let Some(&power) = payload.first() {
+                    data.tx_power = Some(power as i8);
+                }
+            }
+            ad_type::MANUFACTURER_SPECIFIC => {
+                if payload.len() >= 2 {
+                    let company_id = u16::from_le_bytes([payload[0], payload[1]]);
+                    data.manufacturer_data
+                        .push((company_id, payload[2..].to_vec()));
+                }
+            }
+            ad_type::SERVICE_DATA_16 => {
+                if payload.len() >= 2 {
+                    let uuid = u16::from_le_bytes([payload[0], payload[1]]);
+                    data.service_data
+                        .push((format_uuid_32(uuid as u32), payload[2..].to_vec()));
+                }
+            }
+            ad_type::SERVICE_DATA_32 => {
+                if payload.len() >= 4 {
+                    let uuid = u32::from_le_bytes(payload[..4].try_into().unwrap());
+                    data.service_data
+                        .push((format_uuid_32(uuid), payload[4..].to_vec()));
+                }
+            }
+            ad_type::SERVICE_DATA_128 => {
+                if payload.len() >= 16 {
+                    let mut uuid = [0u8; 16];
+                    uuid.copy_from_slice(&payload[..16]);
+                    data.service_data
+                        .push((format_uuid_128(&uuid), payload[16..].to_vec()));
+                }
+            }
+            other => {
+                trace!("Ignoring advertisement section type {:#04X}", other);
+            }
+        }
+    }
+
+    data
+}
+
+fn decode_name(payload: &[u8]) -> Option<String> {
+    let name = String::from_utf8_lossy(payload)
+        .trim_end_matches('\0')
+        .to_string();
+    if name.is_empty() {
+        None
+    } else {
+        Some(name)
+    }
+}
+
+/// Expand a 16/32-bit UUID using the Bluetooth Base UUID
+fn format_uuid_32(uuid: u32) -> String {
+    format!("{:08x}-{}", uuid, BASE_UUID_SUFFIX)
+}
+
+/// Format a little-endian 128-bit UUID in the canonical string form
+fn format_uuid_128(bytes: &[u8; 16]) -> String {
+    let hex: String = bytes.iter().rev().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_flags_and_tx_power() {
+        let data = parse_data_sections(&[
+            (ad_type::FLAGS, vec![0x06]),
+            (ad_type::TX_POWER_LEVEL, vec![0xF4]),
+        ]);
+        assert_eq!(data.flags, Some(0x06));
+        assert_eq!(data.tx_power, Some(-12));
+        assert!(data.is_discoverable());
+    }
+
+    #[test]
+    fn test_parse_manufacturer_data() {
+        // Samsung company id 0x0075
+        let data =
+            parse_data_sections(&[(ad_type::MANUFACTURER_SPECIFIC, vec![0x75, 0x00, 0x01, 0x02])]);
+        assert_eq!(data.manufacturer_data, vec![(0x0075, vec![0x01, 0x02])]);
+    }
+
+    #[test]
+    fn test_parse_service_data() {
+        let data = parse_data_sections(&[(ad_type::SERVICE_DATA_16, vec![0x0F, 0x18, 0x55])]);
+        assert_eq!(
+            data.service_data,
+            vec![(
+                "0000180f-0000-1000-8000-00805f9b34fb".to_string(),
+                vec![0x55]
+            )]
+        );
+
+        // Controller service UUID, little-endian on the wire
+        let mut payload: Vec<u8> = vec![
+            0x65, 0x74, 0x6f, 0x6d, 0x65, 0x65, 0x72, 0x68, 0x54, 0x20, 0x73, 0x75, 0x6c, 0x75,
+            0x63, 0x4f,
+        ];
+        payload.push(0xAA);
+        let data = parse_data_sections(&[(ad_type::SERVICE_DATA_128, payload)]);
+        assert_eq!(
+            data.service_data,
+            vec![(
+                "4f63756c-7573-2054-6872-65656d6f7465".to_string(),
+                vec![0xAA]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_complete_name_wins_over_shortened() {
+        let data = parse_data_sections(&[
+            (
+                ad_type::COMPLETE_LOCAL_NAME,
+                b"Gear VR Controller(17DB)".to_vec(),
+            ),
+            (ad_type::SHORTENED_LOCAL_NAME, b"Gear VR".to_vec()),
+        ]);
+        assert_eq!(data.local_name.as_deref(), Some("Gear VR Controller(17DB)"));
+    }
+
+    #[test]
+    fn test_malformed_sections_are_skipped() {
+        let data = parse_data_sections(&[
+            (ad_type::FLAGS, vec![]),
+            (ad_type::MANUFACTURER_SPECIFIC, vec![0x75]),
+            (ad_type::SERVICE_DATA_128, vec![0x00; 4]),
+        ]);
+        assert_eq!(data, AdvertisementData::default());
+    }
+
+    #[test]
+    fn test_merge_scan_response() {
+        let mut adv = parse_data_sections(&[(ad_type::FLAGS, vec![0x06])]);
+        let response = parse_data_sections(&[(ad_type::COMPLETE_LOCAL_NAME, b"Gear VR".to_vec())]);
+        adv.merge(&response);
+        assert_eq!(adv.flags, Some(0x06));
+        assert_eq!(adv.local_name.as_deref(), Some("Gear VR"));
+    }
+}
@@ -2,19 +2,27 @@
 //!
 //! Handles device connection, pairing, and GATT service access.
 
+use crate::domain::diagnostics::{ConnectionAttempt, ConnectionStep};
 use crate::domain::models::{AppEvent, MessageSeverity, StatusMessage};
+use crate::infrastructure::bluetooth::adapter;
+use crate::infrastructure::bluetooth::link::{self, BleLink};
 use crate::infrastructure::bluetooth::protocol::{self, COMMAND_DELAY_MS, INIT_SEQUENCE};
 use anyhow::Result;
+use std::future::Future;
+use std::time::Instant;
 use tokio::sync::mpsc;
 use tracing::{error, info, warn};
+use windows::core::HSTRING;
 use windows::Devices::Bluetooth::GenericAttributeProfile::{
     GattCharacteristic, GattClientCharacteristicConfigurationDescriptorValue,
-    GattCommunicationStatus,
+    GattCommunicationStatus, GattDeviceService, GattSession,
 };
 use windows::Devices::Bluetooth::{
     BluetoothCacheMode, BluetoothConnectionStatus, BluetoothLEDevice,
 };
-use windows::Devices::Enumeration::{DeviceInformation, DeviceUnpairingResultStatus};
+use windows::Devices::Enumeration::{
+    DeviceAccessStatus, DeviceInformation, DeviceUnpairingResultStatus,
+};
 use windows::Storage::Streams::DataWriter;
 
 /// Configuration for connection behavior
@@ -30,6 +38,8 @@ pub struct ConnectionConfig {
     pub data_char_uuid: String,
     /// Command characteristic UUID
     pub command_char_uuid: String,
+    /// Address of the adapter to connect through (None = system default)
+    pub adapter_address: Option<u64>,
 }
 
 impl Default for ConnectionConfig {
@@ -40,15 +50,38 @@ impl Default for ConnectionConfig {
             service_uuid: protocol::SERVICE_UUID.to_string(),
             data_char_uuid: protocol::DATA_CHAR_UUID.to_string(),
             command_char_uuid: protocol::COMMAND_CHAR_UUID.to_string(),
+            adapter_address: None,
         }
     }
 }
 
-/// Result of a successful connection
-pub struct ConnectionResult {
-    pub device: BluetoothLEDevice,
-    pub data_characteristic: GattCharacteristic,
-    pub command_characteristic: GattCharacteristic,
+/// Run one connection step, recording its duration and outcome in `attempt`
+async fn timed<T>(
+    attempt: &mut ConnectionAttempt,
+    step: ConnectionStep,
+    future: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    let start = Instant::now();
+    let result = future.await;
+    let elapsed = start.elapsed();
+
+    match &result {
+        Ok(_) => info!("{} took {} ms", step.name(), elapsed.as_millis()),
+        Err(e) => warn!(
+            "{} failed after {} ms: {}",
+            step.name(),
+            elapsed.as_millis(),
+            e
+        ),
+    }
+    attempt.record(
+        step,
+        elapsed,
+        result.is_ok(),
+        result.as_ref().err().map(|e| e.to_string()),
+    );
+
+    result
 }
 
 /// BLE Connection handler
@@ -67,30 +100,88 @@ impl BleConnection {
     }
 
     /// Connect to a device by Bluetooth address
-    pub async fn connect(&self, address: u64) -> Result<ConnectionResult> {
+    ///
+    /// Every step is timed and the finished [`ConnectionAttempt`] is reported
+    /// through [`AppEvent::ConnectionAttempt`], whether it succeeded or not.
+    pub async fn connect(&self, address: u64) -> Result<BleLink> {
+        let mut attempt = ConnectionAttempt::begin(address);
+        let result = self.run_connection_steps(address, &mut attempt).await;
+
+        attempt.finish(result.as_ref().err().map(|e| e.to_string()));
+        info!(
+            "Connection attempt finished in {} ms (success: {})",
+            attempt.total_ms, attempt.success
+        );
+        let _ = self.event_sender.send(AppEvent::ConnectionAttempt(attempt));
+
+        result
+    }
+
+    async fn run_connection_steps(
+        &self,
+        address: u64,
+        attempt: &mut ConnectionAttempt,
+    ) -> Result<BleLink> {
         info!("Connecting to Bluetooth device: {:#X}", address);
         self.send_log("Connecting to device...", MessageSeverity::Info);
 
         // Step 1: Connect to BLE device
-        let device = self.connect_device(address).await?;
-        info!("Device connected: {:?}", device.Name()?);
+        let device = timed(
+            attempt,
+            ConnectionStep::DeviceOpen,
+            self.connect_device(address),
+        )
+        .await?;
+        info!("Device connected: {:?}", device.Name().unwrap_or_default());
 
         // Step 2: Create GattSession to maintain connection
-        // This helps prevent Windows from requiring additional pairing
-        if let Ok(session) = self.create_gatt_session(&device).await {
-            info!("GattSession created, MaintainConnection set to true");
-            // Keep session alive by not dropping it
-            std::mem::forget(session);
-        } else {
-            warn!("Failed to create GattSession, continuing anyway...");
+        // This helps prevent Windows from requiring additional pairing.
+        // The session is owned by the returned link and released on disconnect.
+        let session = match timed(
+            attempt,
+            ConnectionStep::GattSession,
+            self.create_gatt_session(&device),
+        )
+        .await
+        {
+            Ok(session) => {
+                info!("GattSession created, MaintainConnection set to true");
+                Some(session)
+            }
+            Err(_) => {
+                warn!("Failed to create GattSession, continuing anyway...");
+                None
+            }
+        };
+
+        // Everything after this point holds the device and session open;
+        // release them if the connection cannot be completed
+        match self.open_data_channel(address, attempt, &device).await {
+            Ok(data_char) => Ok(BleLink::new(device, data_char, session)),
+            Err(e) => {
+                link::release(session, &device);
+                Err(e)
+            }
         }
+    }
 
+    /// Pairing checks, GATT discovery and controller initialization on an
+    /// opened device, returning the data characteristic
+    async fn open_data_channel(
+        &self,
+        address: u64,
+        attempt: &mut ConnectionAttempt,
+        device: &BluetoothLEDevice,
+    ) -> Result<GattCharacteristic> {
         // Step 2.5: Verify system pairing status (Windows PnP Database check)
         // We now get the actual DeviceInformation object if found, to allow "ghost busting"
-        let system_device_info = self
-            .check_system_paired_status(address)
-            .await
-            .unwrap_or(None);
+        let system_device_info = timed(
+            attempt,
+            ConnectionStep::SystemPairingLookup,
+            self.check_system_paired_status(address),
+        )
+        .await
+        .unwrap_or(None);
         let system_paired = system_device_info.is_some();
 
         if system_paired {
@@ -100,7 +191,12 @@ impl BleConnection {
         }
 
         // Step 3: Handle pairing
-        let was_paired = self.handle_pairing(&device).await?;
+        let was_paired = timed(
+            attempt,
+            ConnectionStep::PairingCheck,
+            self.handle_pairing(device),
+        )
+        .await?;
 
         // Diagnosis & Auto-Fix: Ghost Device Detection
         // If system thinks it's paired, but our current handle thinks it's NOT,
@@ -112,46 +208,56 @@ impl BleConnection {
 
             // Ghost Busting!
             if let Some(ghost_info) = system_device_info {
-                info!("Ghost Buster: Attempting to unpair system record for device");
-                match ghost_info.Pairing()?.UnpairAsync()?.await {
-                    Ok(result) => {
-                        let status = result.Status()?;
-                        info!("Ghost Buster Result: {:?}", status);
-                        if status == DeviceUnpairingResultStatus::Unpaired
-                            || status == DeviceUnpairingResultStatus::AlreadyUnpaired
-                        {
-                            self.send_log(
-                                "残留配对已清除！请立刻重试连接。",
-                                MessageSeverity::Success,
-                            );
-                            // We could auto-retry here, but asking user to click once is safer for now
-                            anyhow::bail!("已清除残留系统配对。请点击‘连接’重试。");
-                        } else {
-                            self.send_log(
-                                "自动清理失败，请在Windows设置中手动删除设备。",
-                                MessageSeverity::Error,
-                            );
-                        }
-                    }
-                    Err(e) => {
-                        error!("Ghost Buster Failed: {:?}", e);
-                        self.send_log(
-                            "自动清理出错，请手动检查Windows设置。",
-                            MessageSeverity::Error,
-                        );
-                    }
+                let cleared = timed(
+                    attempt,
+                    ConnectionStep::GhostDeviceCleanup,
+                    self.remove_ghost_pairing(&ghost_info),
+                )
+                .await;
+                if cleared.is_ok() {
+                    // We could auto-retry here, but asking user to click once is safer for now
+                    anyhow::bail!("已清除残留系统配对。请点击‘连接’重试。");
                 }
             }
         }
 
         // Step 4: Get GATT services and characteristics
-        let (data_char, cmd_char) = self.get_characteristics(&device).await?;
+        let service = timed(
+            attempt,
+            ConnectionStep::ServiceDiscovery,
+            self.discover_service(device),
+        )
+        .await?;
+
+        // Access status is informational only, characteristic discovery decides
+        let access_start = Instant::now();
+        let access = self.request_service_access(&service).await;
+        attempt.record(
+            ConnectionStep::AccessRequest,
+            access_start.elapsed(),
+            access.is_ok(),
+            Some(match &access {
+                Ok(status) => format!("{:?}", status),
+                Err(e) => e.to_string(),
+            }),
+        );
+        access?;
+
+        let (data_char, cmd_char) = timed(
+            attempt,
+            ConnectionStep::CharacteristicDiscovery,
+            self.get_characteristics(&service),
+        )
+        .await?;
 
         // Step 5: Try enabling notifications BEFORE sending init commands
         // Some devices need this order, and it may trigger the pairing dialog earlier
-        let notifications_enabled = match self
-            .enable_notifications(&data_char, was_paired, &device)
-            .await
+        let notifications_enabled = match timed(
+            attempt,
+            ConnectionStep::NotificationSubscription,
+            self.enable_notifications(&data_char, was_paired, device),
+        )
+        .await
         {
             Ok(()) => true,
             Err(e) => {
@@ -164,14 +270,22 @@ impl BleConnection {
         };
 
         // Step 6: Send initialization commands
-        self.send_init_commands(&cmd_char).await?;
+        timed(
+            attempt,
+            ConnectionStep::InitCommands,
+            self.send_init_commands(&cmd_char),
+        )
+        .await?;
 
         // Step 7: If notifications weren't enabled earlier, try again
         if !notifications_enabled {
             info!("Retrying notification subscription after init commands...");
-            if let Err(e) = self
-                .enable_notifications(&data_char, was_paired, &device)
-                .await
+            if let Err(e) = timed(
+                attempt,
+                ConnectionStep::NotificationSubscription,
+                self.enable_notifications(&data_char, was_paired, device),
+            )
+            .await
             {
                 // If still failing, log warning but continue - device may auto-send data
                 warn!(
@@ -185,20 +299,11 @@ impl BleConnection {
             }
         }
 
-        Ok(ConnectionResult {
-            device,
-            data_characteristic: data_char,
-            command_characteristic: cmd_char,
-        })
+        Ok(data_char)
     }
 
     /// Create a GattSession to maintain the BLE connection
-    async fn create_gatt_session(
-        &self,
-        device: &BluetoothLEDevice,
-    ) -> Result<windows::Devices::Bluetooth::GenericAttributeProfile::GattSession> {
-        use windows::Devices::Bluetooth::GenericAttributeProfile::GattSession;
-
+    async fn create_gatt_session(&self, device: &BluetoothLEDevice) -> Result<GattSession> {
         let device_id = device.BluetoothDeviceId()?;
         let session = GattSession::FromDeviceIdAsync(&device_id)?.await?;
         session.SetMaintainConnection(true)?;
@@ -207,6 +312,18 @@ impl BleConnection {
 
     /// Connect to BLE device
     async fn connect_device(&self, address: u64) -> Result<BluetoothLEDevice> {
+        // Open the device through the selected adapter if one was chosen
+        if let Some(adapter_address) = self.config.adapter_address {
+            let device_id = adapter::le_device_id(adapter_address, address);
+            match BluetoothLEDevice::FromIdAsync(&HSTRING::from(device_id.as_str()))?.await {
+                Ok(device) => return Ok(device),
+                Err(e) => warn!(
+                    "Could not open {} on selected adapter ({}), falling back to default",
+                    device_id, e
+                ),
+            }
+        }
+
         let device_async = BluetoothLEDevice::FromBluetoothAddressAsync(address)?;
         let device = device_async.await?;
         Ok(device)
@@ -268,6 +385,40 @@ impl BleConnection {
         Ok(None)
     }
 
+    /// Remove a stale system pairing record ("ghost device")
+    ///
+    /// Returns `Ok(())` only if the record is gone, so the caller can ask the
+    /// user to retry with a clean state.
+    async fn remove_ghost_pairing(&self, ghost_info: &DeviceInformation) -> Result<()> {
+        info!("Ghost Buster: Attempting to unpair system record for device");
+        match ghost_info.Pairing()?.UnpairAsync()?.await {
+            Ok(result) => {
+                let status = result.Status()?;
+                info!("Ghost Buster Result: {:?}", status);
+                if status == DeviceUnpairingResultStatus::Unpaired
+                    || status == DeviceUnpairingResultStatus::AlreadyUnpaired
+                {
+                    self.send_log("残留配对已清除！请立刻重试连接。", MessageSeverity::Success);
+                    Ok(())
+                } else {
+                    self.send_log(
+                        "自动清理失败，请在Windows设置中手动删除设备。",
+                        MessageSeverity::Error,
+                    );
+                    anyhow::bail!("Unpair returned {:?}", status)
+                }
+            }
+            Err(e) => {
+                error!("Ghost Buster Failed: {:?}", e);
+                self.send_log(
+                    "自动清理出错，请手动检查Windows设置。",
+                    MessageSeverity::Error,
+                );
+                Err(e.into())
+            }
+        }
+    }
+
     /// Attempt to unpair the device
     pub async fn unpair_device(&self, device: &BluetoothLEDevice) -> Result<()> {
         let device_info = device.DeviceInformation()?;
@@ -309,14 +460,9 @@ impl BleConnection {
         Ok(())
     }
 
-    /// Get GATT characteristics
-    async fn get_characteristics(
-        &self,
-        device: &BluetoothLEDevice,
-    ) -> Result<(GattCharacteristic, GattCharacteristic)> {
+    /// Find the controller GATT service
+    async fn discover_service(&self, device: &BluetoothLEDevice) -> Result<GattDeviceService> {
         let service_uuid = protocol::parse_uuid(&self.config.service_uuid)?;
-        let data_uuid = protocol::parse_uuid(&self.config.data_char_uuid)?;
-        let cmd_uuid = protocol::parse_uuid(&self.config.command_char_uuid)?;
 
         // Get services - Force Uncached mode to skip Windows GATT cache
         let services_result = device
@@ -338,11 +484,27 @@ impl BleConnection {
 
         let service = services.GetAt(0)?;
         info!("Found controller service (Cache Refreshed)");
+        Ok(service)
+    }
 
-        // Request access
+    /// Request access to the controller service
+    async fn request_service_access(
+        &self,
+        service: &GattDeviceService,
+    ) -> Result<DeviceAccessStatus> {
         info!("Requesting service access...");
         let access_status = service.RequestAccessAsync()?.await?;
         info!("Service access status: {:?}", access_status);
+        Ok(access_status)
+    }
+
+    /// Get GATT characteristics
+    async fn get_characteristics(
+        &self,
+        service: &GattDeviceService,
+    ) -> Result<(GattCharacteristic, GattCharacteristic)> {
+        let data_uuid = protocol::parse_uuid(&self.config.data_char_uuid)?;
+        let cmd_uuid = protocol::parse_uuid(&self.config.command_char_uuid)?;
 
         // Get characteristics - Force Uncached mode
         let chars_result = service
@@ -0,0 +1,320 @@
+//! BLE Link Lifecycle
+//!
+//! An established connection owns OS resources that must be released
+//! explicitly: the `GattSession` keeping the link alive and the event
+//! handler registrations on the device and data characteristic.
+//! [`LinkManager`] makes sure they are revoked before a link is dropped or
+//! replaced, so reconnect cycles don't accumulate stale handlers.
+
+use crate::domain::models::{AppEvent, ConnectionStatus};
+use crate::infrastructure::bluetooth::{connection::BleConnection, protocol};
+use anyhow::Result;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+use windows::Devices::Bluetooth::GenericAttributeProfile::{
+    GattCharacteristic, GattSession, GattValueChangedEventArgs,
+};
+use windows::Devices::Bluetooth::{BluetoothConnectionStatus, BluetoothLEDevice};
+use windows::Foundation::TypedEventHandler;
+
+/// An established controller link
+pub trait ControllerLink {
+    /// Register the data and connection status handlers, forwarding events to `events`
+    fn register_handlers(&mut self, events: mpsc::UnboundedSender<AppEvent>) -> Result<()>;
+
+    /// Revoke every handler registered by [`ControllerLink::register_handlers`]
+    fn revoke_handlers(&mut self);
+
+    /// Stop maintaining the connection and release the session and device
+    fn close(&mut self);
+
+    /// Whether the underlying device is currently connected
+    fn is_connected(&self) -> bool;
+}
+
+/// WinRT implementation of [`ControllerLink`]
+pub struct BleLink {
+    pub device: BluetoothLEDevice,
+    pub data_characteristic: GattCharacteristic,
+    session: Option<GattSession>,
+    data_token: Option<i64>,
+    status_token: Option<i64>,
+}
+
+impl BleLink {
+    pub fn new(
+        device: BluetoothLEDevice,
+        data_characteristic: GattCharacteristic,
+        session: Option<GattSession>,
+    ) -> Self {
+        Self {
+            device,
+            data_characteristic,
+            session,
+            data_token: None,
+            status_token: None,
+        }
+    }
+}
+
+impl ControllerLink for BleLink {
+    fn register_handlers(&mut self, events: mpsc::UnboundedSender<AppEvent>) -> Result<()> {
+        // Never stack handlers on top of a previous registration
+        self.revoke_handlers();
+
+        // Data notification handler
+        let sender = events.clone();
+        let data_handler = TypedEventHandler::new(
+            move |_: windows::core::Ref<GattCharacteristic>,
+                  args: windows::core::Ref<GattValueChangedEventArgs>| {
+                if let Some(args) = args.as_ref() {
+                    if let Ok(value) = args.CharacteristicValue() {
+                        if let Ok(data) = protocol::parse_data_packet(&value) {
+                            let _ = sender.send(AppEvent::ControllerData(data));
+                        }
+                    }
+                }
+                Ok(())
+            },
+        );
+        self.data_token = Some(self.data_characteristic.ValueChanged(&data_handler)?);
+
+        // Connection status handler
+        let sender = events;
+        let status_handler =
+            TypedEventHandler::new(move |dev: windows::core::Ref<BluetoothLEDevice>, _| {
+                if let Some(dev) = dev.as_ref() {
+                    if let Ok(status) = dev.ConnectionStatus() {
+                        let app_status = match status {
+                            BluetoothConnectionStatus::Connected => ConnectionStatus::Connected,
+                            BluetoothConnectionStatus::Disconnected => {
+                                ConnectionStatus::Disconnected
+                            }
+                            _ => ConnectionStatus::Error,
+                        };
+                        let _ = sender.send(AppEvent::ConnectionStatus(app_status));
+                    }
+                }
+                Ok(())
+            });
+        self.status_token = Some(self.device.ConnectionStatusChanged(&status_handler)?);
+
+        Ok(())
+    }
+
+    fn revoke_handlers(&mut self) {
+        if let Some(token) = self.data_token.take() {
+            if let Err(e) = self.data_characteristic.RemoveValueChanged(token) {
+                warn!("Failed to revoke data handler: {}", e);
+            }
+        }
+        if let Some(token) = self.status_token.take() {
+            if let Err(e) = self.device.RemoveConnectionStatusChanged(token) {
+                warn!("Failed to revoke connection status handler: {}", e);
+            }
+        }
+    }
+
+    fn close(&mut self) {
+        release(self.session.take(), &self.device);
+    }
+
+    fn is_connected(&self) -> bool {
+        BleConnection::is_connected(&self.device)
+    }
+}
+
+/// Stop maintaining the connection and close the session and device
+pub fn release(session: Option<GattSession>, device: &BluetoothLEDevice) {
+    if let Some(session) = session {
+        info!("Releasing GattSession (MaintainConnection = false)");
+        let _ = session.SetMaintainConnection(false);
+        let _ = session.Close();
+    }
+    let _ = device.Close();
+}
+
+/// Owner of the active link
+#[derive(Default)]
+pub struct LinkManager {
+    link: Option<Box<dyn ControllerLink>>,
+}
+
+impl LinkManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take ownership of a new link and register its handlers.
+    /// Any previous link is released first.
+    pub fn attach(
+        &mut self,
+        mut link: Box<dyn ControllerLink>,
+        events: mpsc::UnboundedSender<AppEvent>,
+    ) -> Result<()> {
+        self.release();
+
+        if let Err(e) = link.register_handlers(events) {
+            link.revoke_handlers();
+            link.close();
+            return Err(e);
+        }
+
+        self.link = Some(link);
+        Ok(())
+    }
+
+    /// Revoke handlers and close the current link.
+    /// Returns false if there was no link to release.
+    pub fn release(&mut self) -> bool {
+        match self.link.take() {
+            Some(mut link) => {
+                link.revoke_handlers();
+                link.close();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether a link is held and its device is connected
+    pub fn is_connected(&self) -> bool {
+        self.link.as_ref().is_some_and(|l| l.is_connected())
+    }
+}
+
+impl Drop for LinkManager {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Resources held by all mock links, shared with the test
+    #[derive(Default)]
+    struct MockBackend {
+        active_handlers: usize,
+        open_sessions: usize,
+        registrations: usize,
+    }
+
+    struct MockLink {
+        backend: Arc<Mutex<MockBackend>>,
+        handlers: usize,
+        session_open: bool,
+        fail_registration: bool,
+    }
+
+    impl MockLink {
+        fn open(backend: &Arc<Mutex<MockBackend>>) -> Box<dyn ControllerLink> {
+            Self::with_failure(backend, false)
+        }
+
+        fn with_failure(
+            backend: &Arc<Mutex<MockBackend>>,
+            fail_registration: bool,
+        ) -> Box<dyn ControllerLink> {
+            backend.lock().unwrap().open_sessions += 1;
+            Box::new(Self {
+                backend: backend.clone(),
+                handlers: 0,
+                session_open: true,
+                fail_registration,
+            })
+        }
+    }
+
+    impl ControllerLink for MockLink {
+        fn register_handlers(&mut self, _events: mpsc::UnboundedSender<AppEvent>) -> Result<()> {
+            self.revoke_handlers();
+            let mut backend = self.backend.lock().unwrap();
+            backend.registrations += 1;
+            // Data handler succeeds, status handler may fail
+            self.handlers += 1;
+            backend.active_handlers += 1;
+            if self.fail_registration {
+                anyhow::bail!("status handler registration failed");
+            }
+            self.handlers += 1;
+            backend.active_handlers += 1;
+            Ok(())
+        }
+
+        fn revoke_handlers(&mut self) {
+            self.backend.lock().unwrap().active_handlers -= self.handlers;
+            self.handlers = 0;
+        }
+
+        fn close(&mut self) {
+            if self.session_open {
+                self.session_open = false;
+                self.backend.lock().unwrap().open_sessions -= 1;
+            }
+        }
+
+        fn is_connected(&self) -> bool {
+            self.session_open
+        }
+    }
+
+    #[test]
+    fn test_connect_disconnect_cycles_leave_no_handlers() {
+        let backend = Arc::new(Mutex::new(MockBackend::default()));
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let mut manager = LinkManager::new();
+
+        for _ in 0..10 {
+            manager
+                .attach(MockLink::open(&backend), tx.clone())
+                .unwrap();
+            assert!(manager.is_connected());
+            assert_eq!(backend.lock().unwrap().active_handlers, 2);
+
+            assert!(manager.release());
+            assert!(!manager.is_connected());
+            assert_eq!(backend.lock().unwrap().active_handlers, 0);
+            assert_eq!(backend.lock().unwrap().open_sessions, 0);
+        }
+
+        assert_eq!(backend.lock().unwrap().registrations, 10);
+        assert!(!manager.release());
+    }
+
+    #[test]
+    fn test_reconnect_without_disconnect_releases_previous_link() {
+        let backend = Arc::new(Mutex::new(MockBackend::default()));
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let mut manager = LinkManager::new();
+
+        for _ in 0..5 {
+            manager
+                .attach(MockLink::open(&backend), tx.clone())
+                .unwrap();
+        }
+
+        assert_eq!(backend.lock().unwrap().active_handlers, 2);
+        assert_eq!(backend.lock().unwrap().open_sessions, 1);
+
+        drop(manager);
+        assert_eq!(backend.lock().unwrap().active_handlers, 0);
+        assert_eq!(backend.lock().unwrap().open_sessions, 0);
+    }
+
+    #[test]
+    fn test_failed_registration_is_rolled_back() {
+        let backend = Arc::new(Mutex::new(MockBackend::default()));
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let mut manager = LinkManager::new();
+
+        assert!(manager
+            .attach(MockLink::with_failure(&backend, true), tx)
+            .is_err());
+        assert!(!manager.is_connected());
+        assert_eq!(backend.lock().unwrap().active_handlers, 0);
+        assert_eq!(backend.lock().unwrap().open_sessions, 0);
+    }
+}
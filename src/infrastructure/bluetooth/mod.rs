@@ -24,12 +24,18 @@
 //!
 //! ## Modules
 //!
+//! - [`adapter`] - Adapter enumeration and radio state monitoring
 //! - [`protocol`] - Controller protocol definitions, commands, and data parsing
+//! - [`advertisement`] - Advertisement data decoding (flags, TX power, manufacturer/service data)
 //! - [`scanner`] - BLE device discovery
 //! - [`connection`] - Device connection, pairing, and GATT service handling
+//! - [`link`] - Ownership of an established link (GattSession, event handler tokens)
 //! - [`service`] - Main service coordinator
 
+pub mod adapter;
+pub mod advertisement;
 pub mod connection;
+pub mod link;
 pub mod protocol;
 pub mod scanner;
 pub mod service;
@@ -89,7 +89,8 @@ pub mod imu_scale {
     /// Magnetometer: value * 0.06
     pub const MAG: f32 = 0.06;
 
-    /// Timestamp factor
+    /// Timestamp: value / 1000.0 * TIMESTAMP_FACTOR
+    /// (the raw value counts microseconds; to seconds)
     pub const TIMESTAMP_FACTOR: f32 = 0.001;
 }
 
@@ -98,7 +99,7 @@ pub mod imu_scale {
 /// # Data Packet Structure (60 bytes)
 ///
 /// ```text
-/// [0-3]   : Timestamp (u32 little-endian, milliseconds)
+/// [0-3]   : Timestamp (u32 little-endian, microseconds)
 /// [4-5]   : Temperature or unknown (i16 little-endian)
 /// [6-7]   : Reserved
 ///
@@ -156,8 +157,9 @@ pub fn parse_raw_bytes(bytes: &[u8]) -> Result<ControllerData> {
         return Err(anyhow::anyhow!("Invalid packet size: {}", bytes.len()));
     }
 
-    // Timestamp
-    let timestamp = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as i64;
+    // Timestamp, converted to seconds like the Samsung APK does
+    let raw_timestamp = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    let timestamp = raw_timestamp as f64 / 1000.0 * imu_scale::TIMESTAMP_FACTOR as f64;
 
     // Temperature (byte 57 according to JS implementation)
     let temperature = Some(bytes[57] as i16);
@@ -279,6 +281,27 @@ mod tests {
         assert_eq!(guid.data1, 0x4f63756c);
     }
 
+    #[test]
+    fn test_timestamp_feeds_sample_timer() {
+        use crate::domain::filters::SampleTimer;
+
+        let mut packet = [0u8; 60];
+        let mut timer = SampleTimer::default();
+        let mut dt = 0.0;
+        // The raw clock counts microseconds: packets 15000 apart are 15 ms
+        for (raw, seconds) in [(2_000_000u32, 2.0), (2_015_000, 2.015)] {
+            packet[..4].copy_from_slice(&raw.to_le_bytes());
+            let data = parse_raw_bytes(&packet).unwrap();
+            assert!(
+                (data.timestamp - seconds).abs() < 1e-6,
+                "{}",
+                data.timestamp
+            );
+            dt = timer.interval(data.timestamp);
+        }
+        assert!((dt - 0.015).abs() < 1e-5, "{}", dt);
+    }
+
     #[test]
     fn test_command_bytes() {
         assert_eq!(ControllerCommand::Off.as_bytes(), &[0x00, 0x00]);
@@ -1,17 +1,24 @@
 //! BLE Scanner Module
 //!
 //! Handles Bluetooth LE device discovery for Gear VR Controllers.
+//!
+//! WinRT advertisement watchers always listen on the system default
+//! adapter and cannot be bound to another one, so scanning ignores
+//! `bluetooth_adapter_id`. The selected adapter only applies when
+//! connecting (see `ConnectionConfig::adapter_address`); scanning is gated
+//! on the default adapter's radio (`AdapterMonitor::scan_state`).
 
 use crate::domain::models::{AppEvent, MessageSeverity, ScannedDevice, StatusMessage};
-use crate::infrastructure::bluetooth::protocol;
+use crate::infrastructure::bluetooth::{advertisement, protocol};
 use anyhow::Result;
 use tokio::sync::mpsc;
 use tracing::info;
 use windows::Devices::Bluetooth::Advertisement::{
-    BluetoothLEAdvertisementReceivedEventArgs, BluetoothLEAdvertisementWatcher,
-    BluetoothLEScanningMode,
+    BluetoothLEAdvertisement, BluetoothLEAdvertisementReceivedEventArgs,
+    BluetoothLEAdvertisementWatcher, BluetoothLEScanningMode,
 };
 use windows::Foundation::TypedEventHandler;
+use windows::Storage::Streams::DataReader;
 
 /// BLE Scanner for discovering Gear VR Controllers
 pub struct BleScanner {
@@ -73,6 +80,8 @@ impl BleScanner {
                         let name = adv.LocalName()?.to_string();
                         let address = args.BluetoothAddress()?;
                         let rssi = args.RawSignalStrengthInDBm()?;
+                        let advertisement =
+                            advertisement::parse_data_sections(&read_data_sections(&adv)?);
 
                         let device = ScannedDevice {
                             name: if name.is_empty() {
@@ -82,6 +91,7 @@ impl BleScanner {
                             },
                             address,
                             signal_strength: rssi,
+                            advertisement,
                         };
 
                         let _ = sender.send(AppEvent::DeviceFound(device));
@@ -117,6 +127,23 @@ impl BleScanner {
     }
 }
 
+/// Extract the raw `(data type, payload)` sections of an advertisement
+fn read_data_sections(adv: &BluetoothLEAdvertisement) -> windows::core::Result<Vec<(u8, Vec<u8>)>> {
+    let sections = adv.DataSections()?;
+    let mut result = Vec::with_capacity(sections.Size()? as usize);
+
+    for i in 0..sections.Size()? {
+        let section = sections.GetAt(i)?;
+        let buffer = section.Data()?;
+        let reader = DataReader::FromBuffer(&buffer)?;
+        let mut bytes = vec![0u8; reader.UnconsumedBufferLength()? as usize];
+        reader.ReadBytes(&mut bytes)?;
+        result.push((section.DataType()?, bytes));
+    }
+
+    Ok(result)
+}
+
 impl Drop for BleScanner {
     fn drop(&mut self) {
         let _ = self.stop();
@@ -6,25 +6,21 @@
 use crate::domain::models::{AppEvent, ConnectionStatus, MessageSeverity, StatusMessage};
 use crate::domain::settings::SettingsService;
 use crate::infrastructure::bluetooth::{
-    connection::{BleConnection, ConnectionConfig, ConnectionResult},
-    protocol,
+    adapter::AdapterMonitor,
+    connection::{BleConnection, ConnectionConfig},
+    link::LinkManager,
     scanner::BleScanner,
 };
 use anyhow::Result;
 use std::sync::{Arc, Mutex};
 use tokio::sync::mpsc;
-use tracing::info;
-use windows::Devices::Bluetooth::GenericAttributeProfile::{
-    GattCharacteristic, GattValueChangedEventArgs,
-};
-use windows::Devices::Bluetooth::{BluetoothConnectionStatus, BluetoothLEDevice};
-use windows::Foundation::TypedEventHandler;
+use tracing::{info, warn};
 
 /// Main Bluetooth service coordinating all BLE operations
 pub struct BluetoothService {
-    device: Option<BluetoothLEDevice>,
-    data_characteristic: Option<GattCharacteristic>,
+    link: LinkManager,
     scanner: BleScanner,
+    adapter: AdapterMonitor,
     event_sender: mpsc::UnboundedSender<AppEvent>,
     settings: Arc<Mutex<SettingsService>>,
 }
@@ -36,16 +32,48 @@ impl BluetoothService {
         settings: Arc<Mutex<SettingsService>>,
     ) -> Self {
         Self {
-            device: None,
-            data_characteristic: None,
+            link: LinkManager::new(),
             scanner: BleScanner::new(event_sender.clone()),
+            adapter: AdapterMonitor::new(event_sender.clone()),
             event_sender,
             settings,
         }
     }
 
-    /// Start scanning for devices
+    /// Enumerate adapters and select the one configured in settings.
+    /// Scanning is stopped if the default adapter's radio is not usable.
+    pub async fn refresh_adapters(&mut self) -> Result<()> {
+        match AdapterMonitor::list_adapters().await {
+            Ok(adapters) => {
+                info!("Found {} Bluetooth adapter(s)", adapters.len());
+                let _ = self.event_sender.send(AppEvent::AdapterList(adapters));
+            }
+            Err(e) => warn!("Failed to enumerate Bluetooth adapters: {}", e),
+        }
+
+        let adapter_id = {
+            let settings = self
+                .settings
+                .lock()
+                .map_err(|_| anyhow::anyhow!("Lock error"))?;
+            settings.get().bluetooth_adapter_id.clone()
+        };
+
+        let scan_state = self.adapter.select(adapter_id.as_deref()).await?;
+        if !scan_state.is_usable() {
+            self.scanner.stop()?;
+        }
+
+        Ok(())
+    }
+
+    /// Start scanning for devices on the default adapter
     pub fn start_scan(&mut self) -> Result<()> {
+        let state = self.adapter.scan_state();
+        if !state.is_usable() {
+            anyhow::bail!("Bluetooth adapter not ready ({:?})", state);
+        }
+
         let (service_uuid, show_all) = {
             let settings = self
                 .settings
@@ -65,6 +93,11 @@ impl BluetoothService {
 
     /// Connect to a device by address
     pub async fn connect(&mut self, address: u64) -> Result<()> {
+        let state = self.adapter.state();
+        if !state.is_usable() {
+            anyhow::bail!("Bluetooth adapter not ready ({:?})", state);
+        }
+
         // Get configuration from settings
         let config = {
             let settings = self
@@ -78,19 +111,28 @@ impl BluetoothService {
                 service_uuid: s.ble_service_uuid.clone(),
                 data_char_uuid: s.ble_data_char_uuid.clone(),
                 command_char_uuid: s.ble_command_char_uuid.clone(),
+                // Only pin the adapter if the user explicitly picked one
+                adapter_address: s
+                    .bluetooth_adapter_id
+                    .as_ref()
+                    .and(self.adapter.selected())
+                    .map(|a| a.address),
             }
         };
 
+        // Release any stale link so its session stops holding the device
+        if self.link.release() {
+            info!("Released previous link before reconnecting");
+        }
+
         // Create connection handler and connect
         let connection = BleConnection::new(self.event_sender.clone(), config);
         let result = connection.connect(address).await?;
 
-        // Set up event handlers
-        self.setup_event_handlers(&result)?;
-
-        // Store references
-        self.device = Some(result.device);
-        self.data_characteristic = Some(result.data_characteristic);
+        // Take ownership of the link and set up event handlers.
+        // A previous (stale) link is released first.
+        self.link
+            .attach(Box::new(result), self.event_sender.clone())?;
 
         // Save to history on successful connection
         {
@@ -107,60 +149,21 @@ impl BluetoothService {
         Ok(())
     }
 
-    /// Set up event handlers for data and connection status
-    fn setup_event_handlers(&self, result: &ConnectionResult) -> Result<()> {
-        // Data notification handler
-        let sender = self.event_sender.clone();
-        let data_handler = TypedEventHandler::new(
-            move |_: windows::core::Ref<GattCharacteristic>,
-                  args: windows::core::Ref<GattValueChangedEventArgs>| {
-                if let Some(args) = args.as_ref() {
-                    if let Ok(value) = args.CharacteristicValue() {
-                        if let Ok(data) = protocol::parse_data_packet(&value) {
-                            let _ = sender.send(AppEvent::ControllerData(data));
-                        }
-                    }
-                }
-                Ok(())
-            },
-        );
-        result.data_characteristic.ValueChanged(&data_handler)?;
-
-        // Connection status handler
-        let sender = self.event_sender.clone();
-        let status_handler =
-            TypedEventHandler::new(move |dev: windows::core::Ref<BluetoothLEDevice>, _| {
-                if let Some(dev) = dev.as_ref() {
-                    if let Ok(status) = dev.ConnectionStatus() {
-                        let app_status = match status {
-                            BluetoothConnectionStatus::Connected => ConnectionStatus::Connected,
-                            BluetoothConnectionStatus::Disconnected => {
-                                ConnectionStatus::Disconnected
-                            }
-                            _ => ConnectionStatus::Error,
-                        };
-                        let _ = sender.send(AppEvent::ConnectionStatus(app_status));
-                    }
-                }
-                Ok(())
-            });
-        result.device.ConnectionStatusChanged(&status_handler)?;
-
-        Ok(())
-    }
-
     /// Disconnect from the current device
+    ///
+    /// Revokes the event handlers, stops maintaining the GattSession and
+    /// closes the device, even if the link already dropped on its own.
     pub fn disconnect(&mut self) {
-        if !self.is_connected() {
+        let was_connected = self.is_connected();
+        if !self.link.release() {
             return;
         }
 
-        if let Some(device) = self.device.take() {
-            let _ = device.Close();
+        if was_connected {
+            info!("Disconnected from device");
+        } else {
+            info!("Released link of an already disconnected device");
         }
-        self.data_characteristic = None;
-
-        info!("Disconnected from device");
         let _ = self.event_sender.send(AppEvent::LogMessage(StatusMessage {
             message: "Disconnected from device".to_string(),
             severity: MessageSeverity::Info,
@@ -172,10 +175,6 @@ impl BluetoothService {
 
     /// Check if connected
     pub fn is_connected(&self) -> bool {
-        self.device
-            .as_ref()
-            .and_then(|d| d.ConnectionStatus().ok())
-            .map(|s| s == BluetoothConnectionStatus::Connected)
-            .unwrap_or(false)
+        self.link.is_connected()
     }
 }
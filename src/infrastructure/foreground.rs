@@ -0,0 +1,78 @@
+//! Foreground Window Detection
+//!
+//! Windows implementation of [`ForegroundWindowProvider`]. The application
+//! only runs on Windows, so there is no X11 or Wayland counterpart.
+
+use crate::domain::profiles::{ForegroundWindow, ForegroundWindowProvider};
+use std::path::Path;
+use windows::core::PWSTR;
+use windows::Win32::Foundation::CloseHandle;
+use windows::Win32::System::Threading::{
+    OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetForegroundWindow, GetWindowTextW, GetWindowThreadProcessId,
+};
+
+/// Queries the window that currently has keyboard focus
+pub struct SystemForegroundWindow {
+    own_process_id: u32,
+}
+
+impl SystemForegroundWindow {
+    pub fn new() -> Self {
+        Self {
+            own_process_id: std::process::id(),
+        }
+    }
+}
+
+impl ForegroundWindowProvider for SystemForegroundWindow {
+    fn foreground(&mut self) -> Option<ForegroundWindow> {
+        unsafe {
+            let hwnd = GetForegroundWindow();
+            if hwnd.is_invalid() {
+                return None;
+            }
+
+            let mut process_id = 0u32;
+            GetWindowThreadProcessId(hwnd, Some(&mut process_id));
+            if process_id == 0 || process_id == self.own_process_id {
+                return None;
+            }
+
+            let mut title = [0u16; 512];
+            let len = GetWindowTextW(hwnd, &mut title).max(0) as usize;
+            let title = String::from_utf16_lossy(&title[..len]);
+
+            Some(ForegroundWindow {
+                executable: executable_name(process_id).unwrap_or_default(),
+                title,
+            })
+        }
+    }
+}
+
+/// File name of the executable running as `process_id`
+fn executable_name(process_id: u32) -> Option<String> {
+    unsafe {
+        // Limited access also works for elevated processes
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, process_id).ok()?;
+
+        let mut buffer = [0u16; 1024];
+        let mut len = buffer.len() as u32;
+        let result = QueryFullProcessImageNameW(
+            process,
+            PROCESS_NAME_WIN32,
+            PWSTR(buffer.as_mut_ptr()),
+            &mut len,
+        );
+        let _ = CloseHandle(process);
+        result.ok()?;
+
+        let path = String::from_utf16_lossy(&buffer[..len as usize]);
+        Path::new(&path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+    }
+}
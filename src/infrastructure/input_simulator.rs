@@ -1,11 +1,22 @@
+use crate::domain::actions::{InputBackend, Key, MouseButton};
+use crate::domain::imu::ScreenBounds;
 use tracing::{debug, trace};
 use windows::Win32::Foundation::POINT;
 use windows::Win32::UI::Input::KeyboardAndMouse::{
     SendInput, INPUT, INPUT_0, INPUT_KEYBOARD, INPUT_MOUSE, KEYBDINPUT, KEYEVENTF_KEYUP,
-    MOUSEEVENTF_HWHEEL, MOUSEEVENTF_LEFTDOWN, MOUSEEVENTF_LEFTUP, MOUSEEVENTF_MOVE,
-    MOUSEEVENTF_RIGHTDOWN, MOUSEEVENTF_RIGHTUP, MOUSEEVENTF_WHEEL, MOUSEINPUT, VIRTUAL_KEY,
+    KEYEVENTF_UNICODE, MOUSEEVENTF_HWHEEL, MOUSEEVENTF_LEFTDOWN, MOUSEEVENTF_LEFTUP,
+    MOUSEEVENTF_MIDDLEDOWN, MOUSEEVENTF_MIDDLEUP, MOUSEEVENTF_MOVE, MOUSEEVENTF_RIGHTDOWN,
+    MOUSEEVENTF_RIGHTUP, MOUSEEVENTF_WHEEL, MOUSEEVENTF_XDOWN, MOUSEEVENTF_XUP, MOUSEINPUT,
+    MOUSE_EVENT_FLAGS, VIRTUAL_KEY, VK_BACK, VK_CAPITAL, VK_DELETE, VK_DOWN, VK_END, VK_ESCAPE,
+    VK_F1, VK_HOME, VK_INSERT, VK_LCONTROL, VK_LEFT, VK_LMENU, VK_LSHIFT, VK_LWIN,
+    VK_MEDIA_NEXT_TRACK, VK_MEDIA_PLAY_PAUSE, VK_MEDIA_PREV_TRACK, VK_MEDIA_STOP, VK_NEXT,
+    VK_PRIOR, VK_RETURN, VK_RIGHT, VK_SNAPSHOT, VK_SPACE, VK_TAB, VK_UP, VK_VOLUME_DOWN,
+    VK_VOLUME_MUTE, VK_VOLUME_UP,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetCursorPos, GetSystemMetrics, SetCursorPos, SM_CXSCREEN, SM_CXVIRTUALSCREEN, SM_CYSCREEN,
+    SM_CYVIRTUALSCREEN, SM_XVIRTUALSCREEN, SM_YVIRTUALSCREEN, XBUTTON1, XBUTTON2,
 };
-use windows::Win32::UI::WindowsAndMessaging::{GetCursorPos, SetCursorPos};
 
 const WHEEL_DELTA: i32 = 120;
 
@@ -58,6 +69,28 @@ impl InputSimulator {
         }
     }
 
+    /// Area the cursor can reach: the primary monitor, or the virtual
+    /// desktop spanning all monitors
+    pub fn screen_bounds(&self, all_monitors: bool) -> ScreenBounds {
+        unsafe {
+            if all_monitors {
+                ScreenBounds {
+                    left: GetSystemMetrics(SM_XVIRTUALSCREEN),
+                    top: GetSystemMetrics(SM_YVIRTUALSCREEN),
+                    width: GetSystemMetrics(SM_CXVIRTUALSCREEN),
+                    height: GetSystemMetrics(SM_CYVIRTUALSCREEN),
+                }
+            } else {
+                ScreenBounds {
+                    left: 0,
+                    top: 0,
+                    width: GetSystemMetrics(SM_CXSCREEN),
+                    height: GetSystemMetrics(SM_CYSCREEN),
+                }
+            }
+        }
+    }
+
     /// Simulate left mouse button down
     pub fn mouse_left_down(&self) -> anyhow::Result<()> {
         debug!("Mouse Left Down");
@@ -160,32 +193,31 @@ impl InputSimulator {
         Ok(())
     }
 
-    /// Simulate mouse wheel scroll
-    /// Simulate mouse wheel scroll
+    /// Simulate mouse wheel scroll, in notches
     pub fn mouse_wheel(&self, delta: i32) -> anyhow::Result<()> {
         debug!("Mouse Wheel Scroll: {}", delta);
-        unsafe {
-            let input = INPUT {
-                r#type: INPUT_MOUSE,
-                Anonymous: INPUT_0 {
-                    mi: MOUSEINPUT {
-                        dx: 0,
-                        dy: 0,
-                        mouseData: (delta * WHEEL_DELTA) as u32,
-                        dwFlags: MOUSEEVENTF_WHEEL,
-                        time: 0,
-                        dwExtraInfo: 0,
-                    },
-                },
-            };
-            SendInput(&[input], std::mem::size_of::<INPUT>() as i32);
-        }
-        Ok(())
+        self.smooth_wheel(delta * WHEEL_DELTA)
     }
 
-    /// Simulate horizontal mouse wheel scroll
+    /// Simulate horizontal mouse wheel scroll, in notches
     pub fn mouse_h_wheel(&self, delta: i32) -> anyhow::Result<()> {
         debug!("Mouse Horizontal Wheel Scroll: {}", delta);
+        self.smooth_h_wheel(delta * WHEEL_DELTA)
+    }
+
+    /// High-resolution wheel scroll; `WHEEL_DELTA` (120) is one notch
+    pub fn smooth_wheel(&self, amount: i32) -> anyhow::Result<()> {
+        trace!("Smooth Wheel Scroll: {}", amount);
+        self.send_wheel(MOUSEEVENTF_WHEEL, amount)
+    }
+
+    /// High-resolution horizontal wheel scroll
+    pub fn smooth_h_wheel(&self, amount: i32) -> anyhow::Result<()> {
+        trace!("Smooth Horizontal Wheel Scroll: {}", amount);
+        self.send_wheel(MOUSEEVENTF_HWHEEL, amount)
+    }
+
+    fn send_wheel(&self, flags: MOUSE_EVENT_FLAGS, amount: i32) -> anyhow::Result<()> {
         unsafe {
             let input = INPUT {
                 r#type: INPUT_MOUSE,
@@ -193,8 +225,8 @@ impl InputSimulator {
                     mi: MOUSEINPUT {
                         dx: 0,
                         dy: 0,
-                        mouseData: (delta * WHEEL_DELTA) as u32,
-                        dwFlags: MOUSEEVENTF_HWHEEL,
+                        mouseData: amount as u32,
+                        dwFlags: flags,
                         time: 0,
                         dwExtraInfo: 0,
                     },
@@ -205,9 +237,9 @@ impl InputSimulator {
         Ok(())
     }
 
-    /// Simulate key press
-    pub fn key_down(&self, key: VIRTUAL_KEY) -> anyhow::Result<()> {
-        debug!("Key Down: {:?}", key);
+    /// Send a single virtual key transition
+    fn send_key(&self, key: VIRTUAL_KEY, up: bool) -> anyhow::Result<()> {
+        debug!("Key {}: {:?}", if up { "Up" } else { "Down" }, key);
         unsafe {
             let input = INPUT {
                 r#type: INPUT_KEYBOARD,
@@ -215,7 +247,11 @@ impl InputSimulator {
                     ki: KEYBDINPUT {
                         wVk: key,
                         wScan: 0,
-                        dwFlags: Default::default(),
+                        dwFlags: if up {
+                            KEYEVENTF_KEYUP
+                        } else {
+                            Default::default()
+                        },
                         time: 0,
                         dwExtraInfo: 0,
                     },
@@ -226,17 +262,18 @@ impl InputSimulator {
         Ok(())
     }
 
-    /// Simulate key release
-    pub fn key_up(&self, key: VIRTUAL_KEY) -> anyhow::Result<()> {
-        debug!("Key Up: {:?}", key);
+    /// Send a mouse button transition that has no dedicated helper
+    fn send_mouse_button(&self, flags: MOUSE_EVENT_FLAGS, data: u32) -> anyhow::Result<()> {
+        debug!("Mouse Button {:?} ({})", flags, data);
         unsafe {
             let input = INPUT {
-                r#type: INPUT_KEYBOARD,
+                r#type: INPUT_MOUSE,
                 Anonymous: INPUT_0 {
-                    ki: KEYBDINPUT {
-                        wVk: key,
-                        wScan: 0,
-                        dwFlags: KEYEVENTF_KEYUP,
+                    mi: MOUSEINPUT {
+                        dx: 0,
+                        dy: 0,
+                        mouseData: data,
+                        dwFlags: flags,
                         time: 0,
                         dwExtraInfo: 0,
                     },
@@ -246,11 +283,122 @@ impl InputSimulator {
         }
         Ok(())
     }
+}
+
+impl InputBackend for InputSimulator {
+    fn key_down(&mut self, key: Key) -> anyhow::Result<()> {
+        self.send_key(virtual_key(key)?, false)
+    }
 
-    /// Simulate key press and release
-    pub fn key_press(&self, key: VIRTUAL_KEY) -> anyhow::Result<()> {
-        self.key_down(key)?;
-        self.key_up(key)?;
+    fn key_up(&mut self, key: Key) -> anyhow::Result<()> {
+        self.send_key(virtual_key(key)?, true)
+    }
+
+    fn mouse_down(&mut self, button: MouseButton) -> anyhow::Result<()> {
+        match button {
+            MouseButton::Left => self.mouse_left_down(),
+            MouseButton::Right => self.mouse_right_down(),
+            MouseButton::Middle => self.send_mouse_button(MOUSEEVENTF_MIDDLEDOWN, 0),
+            MouseButton::X1 => self.send_mouse_button(MOUSEEVENTF_XDOWN, XBUTTON1 as u32),
+            MouseButton::X2 => self.send_mouse_button(MOUSEEVENTF_XDOWN, XBUTTON2 as u32),
+        }
+    }
+
+    fn mouse_up(&mut self, button: MouseButton) -> anyhow::Result<()> {
+        match button {
+            MouseButton::Left => self.mouse_left_up(),
+            MouseButton::Right => self.mouse_right_up(),
+            MouseButton::Middle => self.send_mouse_button(MOUSEEVENTF_MIDDLEUP, 0),
+            MouseButton::X1 => self.send_mouse_button(MOUSEEVENTF_XUP, XBUTTON1 as u32),
+            MouseButton::X2 => self.send_mouse_button(MOUSEEVENTF_XUP, XBUTTON2 as u32),
+        }
+    }
+
+    fn scroll(&mut self, notches: i32) -> anyhow::Result<()> {
+        self.mouse_wheel(notches)
+    }
+
+    fn h_scroll(&mut self, notches: i32) -> anyhow::Result<()> {
+        self.mouse_h_wheel(notches)
+    }
+
+    /// Type text as Unicode key events, independent of the keyboard layout
+    fn type_text(&mut self, text: &str) -> anyhow::Result<()> {
+        debug!("Typing {} characters", text.chars().count());
+        let inputs: Vec<INPUT> = text
+            .encode_utf16()
+            .flat_map(|unit| {
+                [KEYEVENTF_UNICODE, KEYEVENTF_UNICODE | KEYEVENTF_KEYUP].map(|flags| INPUT {
+                    r#type: INPUT_KEYBOARD,
+                    Anonymous: INPUT_0 {
+                        ki: KEYBDINPUT {
+                            wVk: VIRTUAL_KEY(0),
+                            wScan: unit,
+                            dwFlags: flags,
+                            time: 0,
+                            dwExtraInfo: 0,
+                        },
+                    },
+                })
+            })
+            .collect();
+
+        let sent = unsafe { SendInput(&inputs, std::mem::size_of::<INPUT>() as i32) };
+        if sent as usize != inputs.len() {
+            anyhow::bail!("Only {} of {} key events were sent", sent, inputs.len());
+        }
         Ok(())
     }
+
+    fn run_command(&mut self, command: &str) -> anyhow::Result<()> {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+        debug!("Running command: {}", command);
+        std::process::Command::new("cmd")
+            .arg("/C")
+            .raw_arg(command)
+            .creation_flags(CREATE_NO_WINDOW)
+            .spawn()?;
+        Ok(())
+    }
+}
+
+fn virtual_key(key: Key) -> anyhow::Result<VIRTUAL_KEY> {
+    let vk = match key {
+        // Letters and digits share their uppercase ASCII code
+        Key::Char(c) if c.is_ascii_alphanumeric() => VIRTUAL_KEY(c.to_ascii_uppercase() as u16),
+        Key::Char(c) => anyhow::bail!("No virtual key for '{}'", c),
+        Key::F(n) if (1..=24).contains(&n) => VIRTUAL_KEY(VK_F1.0 + (n as u16 - 1)),
+        Key::F(n) => anyhow::bail!("No virtual key for F{}", n),
+        Key::Enter => VK_RETURN,
+        Key::Tab => VK_TAB,
+        Key::Escape => VK_ESCAPE,
+        Key::Space => VK_SPACE,
+        Key::Backspace => VK_BACK,
+        Key::Delete => VK_DELETE,
+        Key::Insert => VK_INSERT,
+        Key::Home => VK_HOME,
+        Key::End => VK_END,
+        Key::PageUp => VK_PRIOR,
+        Key::PageDown => VK_NEXT,
+        Key::Left => VK_LEFT,
+        Key::Right => VK_RIGHT,
+        Key::Up => VK_UP,
+        Key::Down => VK_DOWN,
+        Key::Ctrl => VK_LCONTROL,
+        Key::Shift => VK_LSHIFT,
+        Key::Alt => VK_LMENU,
+        Key::Win => VK_LWIN,
+        Key::CapsLock => VK_CAPITAL,
+        Key::PrintScreen => VK_SNAPSHOT,
+        Key::VolumeUp => VK_VOLUME_UP,
+        Key::VolumeDown => VK_VOLUME_DOWN,
+        Key::VolumeMute => VK_VOLUME_MUTE,
+        Key::MediaPlayPause => VK_MEDIA_PLAY_PAUSE,
+        Key::MediaNext => VK_MEDIA_NEXT_TRACK,
+        Key::MediaPrevious => VK_MEDIA_PREV_TRACK,
+        Key::MediaStop => VK_MEDIA_STOP,
+    };
+    Ok(vk)
 }
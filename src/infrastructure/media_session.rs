@@ -0,0 +1,135 @@
+//! OS Media Session
+//!
+//! Windows implementation of [`MediaSession`] on top of the System Media
+//! Transport Controls (SMTC). WinRT calls block, so they run on a worker
+//! thread that executes queued commands and refreshes the current track
+//! about once per second.
+//!
+//! The Linux counterpart is the MPRIS session in `infrastructure::mpris`.
+
+use crate::domain::media::{MediaCommand, MediaSession, NowPlaying};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tracing::{debug, warn};
+use windows::Media::Control::{
+    GlobalSystemMediaTransportControlsSession as Session,
+    GlobalSystemMediaTransportControlsSessionManager as SessionManager,
+    GlobalSystemMediaTransportControlsSessionPlaybackStatus as PlaybackStatus,
+};
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+/// WinRT `TimeSpan` ticks per second
+const TICKS_PER_SECOND: i64 = 10_000_000;
+
+pub struct SystemMediaSession {
+    commands: mpsc::Sender<MediaCommand>,
+    now_playing: Arc<Mutex<Option<NowPlaying>>>,
+}
+
+impl SystemMediaSession {
+    /// Start the worker. If the session manager is unavailable a warning is
+    /// logged and commands are silently dropped.
+    pub fn new() -> Self {
+        let (commands, receiver) = mpsc::channel();
+        let now_playing = Arc::new(Mutex::new(None));
+
+        let shared = now_playing.clone();
+        thread::spawn(move || {
+            let manager = match SessionManager::RequestAsync().and_then(|op| op.join()) {
+                Ok(manager) => manager,
+                Err(e) => {
+                    warn!("Media session unavailable: {}", e);
+                    return;
+                }
+            };
+
+            loop {
+                match receiver.recv_timeout(REFRESH_INTERVAL) {
+                    Ok(command) => {
+                        if let Err(e) = execute(&manager, command) {
+                            warn!("Media command '{}' failed: {}", command, e);
+                        }
+                    }
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+
+                let current = read_now_playing(&manager).unwrap_or_else(|e| {
+                    debug!("Failed to read media properties: {}", e);
+                    None
+                });
+                if let Ok(mut now_playing) = shared.lock() {
+                    *now_playing = current;
+                }
+            }
+        });
+
+        Self {
+            commands,
+            now_playing,
+        }
+    }
+}
+
+impl MediaSession for SystemMediaSession {
+    fn send(&mut self, command: MediaCommand) {
+        let _ = self.commands.send(command);
+    }
+
+    fn now_playing(&mut self) -> Option<NowPlaying> {
+        self.now_playing.lock().ok().and_then(|n| n.clone())
+    }
+}
+
+fn current_session(manager: &SessionManager) -> Option<Session> {
+    // Fails when no application has registered a session
+    manager.GetCurrentSession().ok()
+}
+
+fn execute(manager: &SessionManager, command: MediaCommand) -> windows::core::Result<()> {
+    let Some(session) = current_session(manager) else {
+        debug!("No media session for '{}'", command);
+        return Ok(());
+    };
+
+    let accepted = match command {
+        MediaCommand::PlayPause => session.TryTogglePlayPauseAsync()?.join()?,
+        MediaCommand::Next => session.TrySkipNextAsync()?.join()?,
+        MediaCommand::Previous => session.TrySkipPreviousAsync()?.join()?,
+        MediaCommand::Stop => session.TryStopAsync()?.join()?,
+        MediaCommand::Seek(seconds) => {
+            let timeline = session.GetTimelineProperties()?;
+            let min = timeline.MinSeekTime()?.Duration;
+            let max = timeline.MaxSeekTime()?.Duration;
+            let target = timeline.Position()?.Duration + seconds as i64 * TICKS_PER_SECOND;
+            let target = if max > min {
+                target.clamp(min, max)
+            } else {
+                target.max(0)
+            };
+            session.TryChangePlaybackPositionAsync(target)?.join()?
+        }
+    };
+
+    if !accepted {
+        debug!("Media player rejected '{}'", command);
+    }
+    Ok(())
+}
+
+fn read_now_playing(manager: &SessionManager) -> windows::core::Result<Option<NowPlaying>> {
+    let Some(session) = current_session(manager) else {
+        return Ok(None);
+    };
+
+    let properties = session.TryGetMediaPropertiesAsync()?.join()?;
+    let playing = session.GetPlaybackInfo()?.PlaybackStatus()? == PlaybackStatus::Playing;
+
+    Ok(Some(NowPlaying {
+        title: properties.Title()?.to_string_lossy(),
+        artist: properties.Artist()?.to_string_lossy(),
+        playing,
+    }))
+}
@@ -1,3 +1,9 @@
 pub mod bluetooth;
+pub mod foreground;
 pub mod input_simulator;
 pub mod logging;
+#[cfg(windows)]
+pub mod media_session;
+#[cfg(target_os = "linux")]
+pub mod mpris;
+pub mod power;
@@ -0,0 +1,317 @@
+//! MPRIS Media Session
+//!
+//! Linux implementation of [`MediaSession`] on top of MPRIS, the D-Bus
+//! interface media players export on the session bus. Like the Windows
+//! session, D-Bus calls run on a worker thread that executes queued
+//! commands and refreshes the current track about once per second.
+//!
+//! Players register as `org.mpris.MediaPlayer2.<name>`; commands go to the
+//! one that is playing, or else the first one found.
+
+use crate::domain::media::{MediaCommand, MediaSession, NowPlaying};
+use std::collections::HashMap;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tracing::{debug, warn};
+use zbus::blocking::fdo::DBusProxy;
+use zbus::blocking::Connection;
+use zbus::proxy::CacheProperties;
+use zbus::zvariant::OwnedValue;
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+const PLAYER_PREFIX: &str = "org.mpris.MediaPlayer2.";
+/// MPRIS positions and offsets are in microseconds
+const MICROS_PER_SECOND: i64 = 1_000_000;
+
+#[zbus::proxy(
+    interface = "org.mpris.MediaPlayer2.Player",
+    default_path = "/org/mpris/MediaPlayer2",
+    gen_async = false,
+    blocking_name = "PlayerProxy"
+)]
+trait Player {
+    fn play_pause(&self) -> zbus::Result<()>;
+    fn next(&self) -> zbus::Result<()>;
+    fn previous(&self) -> zbus::Result<()>;
+    fn stop(&self) -> zbus::Result<()>;
+    /// Move the position by `offset` microseconds
+    fn seek(&self, offset: i64) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn playback_status(&self) -> zbus::Result<String>;
+    #[zbus(property)]
+    fn metadata(&self) -> zbus::Result<HashMap<String, OwnedValue>>;
+}
+
+pub struct MprisMediaSession {
+    commands: mpsc::Sender<MediaCommand>,
+    now_playing: Arc<Mutex<Option<NowPlaying>>>,
+}
+
+impl MprisMediaSession {
+    /// Start the worker on the session bus. If the bus is unavailable a
+    /// warning is logged and commands are silently dropped.
+    pub fn new() -> Self {
+        Self::with_connection(Connection::session)
+    }
+
+    /// Start the worker on the bus `connect` opens
+    pub(crate) fn with_connection<F>(connect: F) -> Self
+    where
+        F: FnOnce() -> zbus::Result<Connection> + Send + 'static,
+    {
+        let (commands, receiver) = mpsc::channel();
+        let now_playing = Arc::new(Mutex::new(None));
+
+        let shared = now_playing.clone();
+        thread::spawn(move || {
+            let connection = match connect() {
+                Ok(connection) => connection,
+                Err(e) => {
+                    warn!("Media session unavailable: {}", e);
+                    return;
+                }
+            };
+
+            loop {
+                match receiver.recv_timeout(REFRESH_INTERVAL) {
+                    Ok(command) => {
+                        if let Err(e) = execute(&connection, command) {
+                            warn!("Media command '{}' failed: {}", command, e);
+                        }
+                    }
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+
+                let current = read_now_playing(&connection).unwrap_or_else(|e| {
+                    debug!("Failed to read media properties: {}", e);
+                    None
+                });
+                if let Ok(mut now_playing) = shared.lock() {
+                    *now_playing = current;
+                }
+            }
+        });
+
+        Self {
+            commands,
+            now_playing,
+        }
+    }
+}
+
+impl MediaSession for MprisMediaSession {
+    fn send(&mut self, command: MediaCommand) {
+        let _ = self.commands.send(command);
+    }
+
+    fn now_playing(&mut self) -> Option<NowPlaying> {
+        self.now_playing.lock().ok().and_then(|n| n.clone())
+    }
+}
+
+fn current_player(connection: &Connection) -> zbus::Result<Option<PlayerProxy<'static>>> {
+    let mut names: Vec<String> = DBusProxy::new(connection)?
+        .list_names()?
+        .into_iter()
+        .map(|name| name.to_string())
+        .filter(|name| name.starts_with(PLAYER_PREFIX))
+        .collect();
+    names.sort();
+
+    let mut first = None;
+    for name in names {
+        let player = PlayerProxy::builder(connection)
+            .destination(name)?
+            .cache_properties(CacheProperties::No)
+            .build()?;
+        if player.playback_status().is_ok_and(|s| s == "Playing") {
+            return Ok(Some(player));
+        }
+        first.get_or_insert(player);
+    }
+    Ok(first)
+}
+
+fn execute(connection: &Connection, command: MediaCommand) -> zbus::Result<()> {
+    let Some(player) = current_player(connection)? else {
+        debug!("No media player for '{}'", command);
+        return Ok(());
+    };
+
+    match command {
+        MediaCommand::PlayPause => player.play_pause(),
+        MediaCommand::Next => player.next(),
+        MediaCommand::Previous => player.previous(),
+        MediaCommand::Stop => player.stop(),
+        MediaCommand::Seek(seconds) => player.seek(seconds as i64 * MICROS_PER_SECOND),
+    }
+}
+
+fn read_now_playing(connection: &Connection) -> zbus::Result<Option<NowPlaying>> {
+    let Some(player) = current_player(connection)? else {
+        return Ok(None);
+    };
+
+    let mut metadata = player.metadata()?;
+    let playing = player.playback_status()? == "Playing";
+
+    Ok(Some(NowPlaying {
+        title: metadata
+            .remove("xesam:title")
+            .and_then(|v| String::try_from(v).ok())
+            .unwrap_or_default(),
+        artist: metadata
+            .remove("xesam:artist")
+            .and_then(|v| Vec::<String>::try_from(v).ok())
+            .map(|artists| artists.join(", "))
+            .unwrap_or_default(),
+        playing,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::process::{Child, Command, Stdio};
+    use std::time::Instant;
+    use zbus::zvariant::Value;
+
+    /// Private session bus, stopped when dropped
+    struct Bus {
+        daemon: Child,
+        address: String,
+    }
+
+    impl Bus {
+        /// None when `dbus-daemon` is not installed
+        fn start() -> Option<Self> {
+            let mut daemon = Command::new("dbus-daemon")
+                .args(["--session", "--nofork", "--print-address"])
+                .stdout(Stdio::piped())
+                .spawn()
+                .ok()?;
+            let mut address = String::new();
+            BufReader::new(daemon.stdout.take()?)
+                .read_line(&mut address)
+                .ok()?;
+            Some(Self {
+                daemon,
+                address: address.trim().to_string(),
+            })
+        }
+
+        fn connect(&self) -> zbus::Result<Connection> {
+            zbus::blocking::connection::Builder::address(self.address.as_str())?.build()
+        }
+    }
+
+    impl Drop for Bus {
+        fn drop(&mut self) {
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+        }
+    }
+
+    /// Player that records the calls it receives
+    struct MockPlayer {
+        calls: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[zbus::interface(name = "org.mpris.MediaPlayer2.Player")]
+    impl MockPlayer {
+        fn play_pause(&self) {
+            self.calls.lock().unwrap().push("PlayPause".into());
+        }
+
+        fn next(&self) {
+            self.calls.lock().unwrap().push("Next".into());
+        }
+
+        fn previous(&self) {
+            self.calls.lock().unwrap().push("Previous".into());
+        }
+
+        fn stop(&self) {
+            self.calls.lock().unwrap().push("Stop".into());
+        }
+
+        fn seek(&self, offset: i64) {
+            self.calls.lock().unwrap().push(format!("Seek({})", offset));
+        }
+
+        #[zbus(property)]
+        fn playback_status(&self) -> String {
+            "Playing".into()
+        }
+
+        #[zbus(property)]
+        fn metadata(&self) -> HashMap<String, OwnedValue> {
+            let artists = Value::from(vec!["Artist A", "Artist B"]);
+            HashMap::from([
+                (
+                    "xesam:title".into(),
+                    Value::from("Track").try_into().unwrap(),
+                ),
+                ("xesam:artist".into(), artists.try_into().unwrap()),
+            ])
+        }
+    }
+
+    /// Poll `check` until it passes or a few seconds have gone by
+    fn eventually(mut check: impl FnMut() -> bool) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            if check() {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        false
+    }
+
+    #[test]
+    fn test_commands_and_track_go_through_the_player() {
+        let Some(bus) = Bus::start() else {
+            eprintln!("dbus-daemon not found, skipping");
+            return;
+        };
+
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let _player = zbus::blocking::connection::Builder::address(bus.address.as_str())
+            .unwrap()
+            .name("org.mpris.MediaPlayer2.mock")
+            .unwrap()
+            .serve_at(
+                "/org/mpris/MediaPlayer2",
+                MockPlayer {
+                    calls: calls.clone(),
+                },
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let client = bus.connect().unwrap();
+        let mut session = MprisMediaSession::with_connection(move || Ok(client));
+        session.send(MediaCommand::PlayPause);
+        session.send(MediaCommand::Seek(-10));
+
+        assert!(eventually(|| calls.lock().unwrap().len() == 2));
+        assert_eq!(*calls.lock().unwrap(), ["PlayPause", "Seek(-10000000)"]);
+
+        assert!(eventually(|| session.now_playing().is_some()));
+        assert_eq!(
+            session.now_playing(),
+            Some(NowPlaying {
+                title: "Track".into(),
+                artist: "Artist A, Artist B".into(),
+                playing: true,
+            })
+        );
+    }
+}
@@ -0,0 +1,113 @@
+//! System Power Notifications
+//!
+//! Windows implementation of [`PowerEventSource`] based on
+//! `PowerRegisterSuspendResumeNotification`. The callback runs on a system
+//! thread and only forwards the event into a channel that the UI polls,
+//! then wakes the UI so the event is handled even while no frame is due.
+
+use crate::domain::power::{PowerEvent, PowerEventSource};
+use std::ffi::c_void;
+use std::sync::mpsc;
+use tracing::{info, warn};
+use windows::Win32::Foundation::{ERROR_SUCCESS, HANDLE};
+use windows::Win32::System::Power::{
+    PowerRegisterSuspendResumeNotification, PowerUnregisterSuspendResumeNotification,
+    DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS, HPOWERNOTIFY,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    DEVICE_NOTIFY_CALLBACK, PBT_APMRESUMEAUTOMATIC, PBT_APMSUSPEND,
+};
+
+/// Suspend/resume notifications from the OS
+pub struct SystemPowerEvents {
+    receiver: mpsc::Receiver<PowerEvent>,
+    registration: Option<HPOWERNOTIFY>,
+    // Referenced by the registered callback, must outlive the registration
+    _params: Box<DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS>,
+    _notifier: Box<Notifier>,
+}
+
+/// What the callback needs to hand an event to the UI
+struct Notifier {
+    sender: mpsc::Sender<PowerEvent>,
+    ctx: egui::Context,
+}
+
+impl SystemPowerEvents {
+    /// Register for notifications. If registration fails, a warning is
+    /// logged and the source simply never reports anything.
+    pub fn new(ctx: egui::Context) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let notifier = Box::new(Notifier { sender, ctx });
+        let mut params = Box::new(DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS {
+            Callback: Some(power_callback),
+            Context: &*notifier as *const Notifier as *mut c_void,
+        });
+
+        let mut handle: *mut c_void = std::ptr::null_mut();
+        let result = unsafe {
+            PowerRegisterSuspendResumeNotification(
+                DEVICE_NOTIFY_CALLBACK,
+                HANDLE(&mut *params as *mut DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS as *mut c_void),
+                &mut handle,
+            )
+        };
+
+        let registration = if result == ERROR_SUCCESS {
+            info!("Registered for suspend/resume notifications");
+            Some(HPOWERNOTIFY(handle as isize))
+        } else {
+            warn!(
+                "Failed to register for suspend/resume notifications: {:?}",
+                result
+            );
+            None
+        };
+
+        Self {
+            receiver,
+            registration,
+            _params: params,
+            _notifier: notifier,
+        }
+    }
+}
+
+impl PowerEventSource for SystemPowerEvents {
+    fn poll(&mut self) -> Option<PowerEvent> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+impl Drop for SystemPowerEvents {
+    fn drop(&mut self) {
+        if let Some(registration) = self.registration.take() {
+            unsafe {
+                let _ = PowerUnregisterSuspendResumeNotification(registration);
+            }
+        }
+    }
+}
+
+unsafe extern "system" fn power_callback(
+    context: *const c_void,
+    event_type: u32,
+    _setting: *const c_void,
+) -> u32 {
+    let event = match event_type {
+        PBT_APMSUSPEND => PowerEvent::Suspend,
+        // Sent on every resume; PBT_APMRESUMESUSPEND would report it twice
+        PBT_APMRESUMEAUTOMATIC => PowerEvent::Resume,
+        _ => return ERROR_SUCCESS.0,
+    };
+
+    info!("System power event: {:?}", event);
+    // SAFETY: context points to the boxed notifier owned by
+    // SystemPowerEvents, which unregisters this callback before dropping it.
+    if let Some(notifier) = unsafe { (context as *const Notifier).as_ref() } {
+        let _ = notifier.sender.send(event);
+        // Suspend gives only a short grace period; don't wait for the next frame
+        notifier.ctx.request_repaint();
+    }
+    ERROR_SUCCESS.0
+}
@@ -1,14 +1,37 @@
+use crate::domain::actions::{ActionExecutor, HostRequest};
+use crate::domain::axes::AxisWizard;
+use crate::domain::buttons::ButtonEngine;
+use crate::domain::calibration::{AccelCalibrator, ImuPreprocessor, MagCalibrator};
+use crate::domain::circular::CircularMotion;
 use crate::domain::controller::TouchpadProcessor;
-use crate::domain::gestures::{GestureDirection, GestureRecognizer};
+use crate::domain::diagnostics::ConnectionHistory;
+use crate::domain::filters::{FilterPreview, FilterSource};
+use crate::domain::gestures::GestureRecognizer;
 use crate::domain::imu::ImuProcessor;
+use crate::domain::input_mapper::{ButtonTrigger, ControllerButton, InputEvent, InputMapper};
+use crate::domain::macros::{Macro, MacroRecorder};
+use crate::domain::media::{MediaCommand, MediaSession, NowPlaying};
 use crate::domain::models::{
-    AppEvent, BluetoothCommand, CalibrationState, ConnectionStatus, ControllerData,
-    MessageSeverity, ScannedDevice, StatusMessage, Tab,
+    AdapterState, AppEvent, BluetoothAdapterInfo, BluetoothCommand, CalibrationState,
+    ConnectionStatus, ControlMode, ControllerData, MessageSeverity, StatusMessage, Tab,
 };
+use crate::domain::motion::MotionRecognizer;
+use crate::domain::orientation::{Orientation, OrientationEstimator};
+use crate::domain::power::{LinkSnapshot, PowerAction, PowerMonitor};
+use crate::domain::profiles::ProfileSwitcher;
+use crate::domain::scan::ScanResults;
+use crate::domain::scroll::ScrollEngine;
 use crate::domain::settings::SettingsService;
+use crate::domain::shapes::{Point, Recognition, StrokeCapture};
 use crate::infrastructure::bluetooth::BluetoothService;
+use crate::infrastructure::foreground::SystemForegroundWindow;
 use crate::infrastructure::input_simulator::InputSimulator;
-use crate::presentation::radial_menu::{ControlMode, RadialMenu};
+#[cfg(windows)]
+use crate::infrastructure::media_session::SystemMediaSession;
+#[cfg(target_os = "linux")]
+use crate::infrastructure::mpris::MprisMediaSession as SystemMediaSession;
+use crate::infrastructure::power::SystemPowerEvents;
+use crate::presentation::radial_menu::RadialMenu;
 use eframe::egui::{self, Pos2};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
@@ -16,6 +39,11 @@ use tokio::sync::mpsc;
 use tracing::error;
 use windows::Win32::UI::Input::KeyboardAndMouse::VK_ESCAPE;
 
+/// Advertisements required before auto-connect trusts a device's signal strength
+const AUTO_CONNECT_MIN_SAMPLES: u32 = 3;
+/// How long the now-playing overlay stays up after a change
+const MEDIA_OVERLAY_DURATION: Duration = Duration::from_secs(4);
+
 pub struct GearVRApp {
     // Services
     pub(crate) settings: Arc<Mutex<SettingsService>>,
@@ -23,10 +51,24 @@ pub struct GearVRApp {
     pub(crate) touchpad_processor: Option<TouchpadProcessor>,
     pub(crate) gesture_recognizer: Option<GestureRecognizer>,
     pub(crate) imu_processor: Option<ImuProcessor>,
+    pub(crate) circular_motion: CircularMotion,
+    pub(crate) scroll_engine: ScrollEngine,
+    pub(crate) motion_recognizer: MotionRecognizer,
+    pub(crate) orientation_estimator: OrientationEstimator,
+    /// Applies the connected controller's IMU calibration to every packet
+    pub(crate) imu_preprocessor: ImuPreprocessor,
+    /// Fused controller orientation, updated with every packet in all modes
+    pub(crate) orientation: Option<Orientation>,
+    pub(crate) input_mapper: InputMapper,
+    pub(crate) action_executor: ActionExecutor<InputSimulator>,
 
     // Bluetooth
     pub(crate) bluetooth_tx: mpsc::UnboundedSender<BluetoothCommand>,
     pub(crate) controller_data_rx: mpsc::UnboundedReceiver<AppEvent>,
+    pub(crate) adapters: Vec<BluetoothAdapterInfo>,
+    pub(crate) adapter_state: AdapterState,
+    /// Radio of the system default adapter, which scanning uses
+    pub(crate) scan_adapter_state: AdapterState,
 
     // State
     pub(crate) connection_status: ConnectionStatus,
@@ -35,32 +77,57 @@ pub struct GearVRApp {
 
     // UI State
     pub(crate) selected_tab: Tab,
+    /// Live raw vs. smoothed signal shown while tuning the filters
+    pub(crate) filter_preview: FilterPreview,
     pub(crate) bluetooth_address_input: String,
 
     // Calibration
     pub(crate) is_calibrating: bool,
     pub(crate) calibration_data: CalibrationState,
+    /// Collects magnetometer samples while calibrating
+    pub(crate) mag_calibrator: Option<MagCalibrator>,
+    /// Captures resting positions while calibrating the accelerometer
+    pub(crate) accel_calibrator: Option<AccelCalibrator>,
+    /// Orientation wizard in progress
+    pub(crate) axis_wizard: Option<AxisWizard>,
+
+    // Buttons
+    pub(crate) button_engine: ButtonEngine,
+
+    // Macros
+    pub(crate) macro_recorder: MacroRecorder,
+    pub(crate) macro_name_input: String,
+
+    // Shape gestures
+    pub(crate) stroke_capture: StrokeCapture,
+    /// Shape whose next stroke is stored as a training sample
+    pub(crate) shape_training: Option<u32>,
+    pub(crate) last_shape_match: Option<Recognition>,
 
-    // Button states (for edge detection)
-    pub(crate) last_trigger_state: bool,
-    pub(crate) last_touchpad_button_state: bool,
-    pub(crate) last_back_button_state: bool,
+    // Profiles
+    pub(crate) profile_switcher: ProfileSwitcher,
+    /// Mode to restore when the profile that changed it is left
+    pub(crate) mode_before_profile: Option<ControlMode>,
+
+    // Media
+    pub(crate) media_session: Option<Box<dyn MediaSession>>,
+    pub(crate) now_playing: Option<NowPlaying>,
+    pub(crate) media_overlay_until: Option<Instant>,
 
     // Scanning
     pub(crate) is_scanning: bool,
-    pub(crate) scanned_devices: Vec<ScannedDevice>,
+    pub(crate) scanned_devices: ScanResults,
+    /// Scan stopped because the radio went away, resumed when it returns
+    pub(crate) scan_paused: bool,
 
     // Reconnection
     pub(crate) auto_reconnect: bool,
     pub(crate) last_connected_address: Option<u64>,
     pub(crate) reconnect_timer: Option<Instant>,
+    pub(crate) power_monitor: PowerMonitor,
 
-    // Debounce
-    pub(crate) trigger_debounce: Option<Instant>,
-    pub(crate) touchpad_btn_debounce: Option<Instant>,
-    pub(crate) back_btn_debounce: Option<Instant>,
-    pub(crate) volume_up_debounce: Option<Instant>,
-    pub(crate) volume_down_debounce: Option<Instant>,
+    // Diagnostics
+    pub(crate) connection_history: ConnectionHistory,
 
     // Admin Client for elevated tasks
     pub(crate) admin_client: crate::admin_client::AdminClient,
@@ -74,7 +141,6 @@ pub struct GearVRApp {
     // Radial Menu
     pub(crate) radial_menu: RadialMenu,
     pub(crate) current_control_mode: ControlMode,
-    pub(crate) trigger_hold_start: Option<Instant>,
 }
 
 impl GearVRApp {
@@ -106,6 +172,10 @@ impl GearVRApp {
                 let tx_clone = data_tx.clone();
                 let mut bt_service = BluetoothService::new(data_tx, bt_settings);
 
+                if let Err(e) = bt_service.refresh_adapters().await {
+                    error!("Failed to select Bluetooth adapter: {}", e);
+                }
+
                 while let Some(cmd) = bt_cmd_rx.recv().await {
                     match cmd {
                         BluetoothCommand::Connect(address) => {
@@ -133,15 +203,43 @@ impl GearVRApp {
                                 error!("Failed to stop scan: {}", e);
                             }
                         }
+                        BluetoothCommand::RefreshAdapters => {
+                            if let Err(e) = bt_service.refresh_adapters().await {
+                                error!("Failed to select Bluetooth adapter: {}", e);
+                            }
+                        }
                     }
                 }
             });
         });
 
         let touchpad_processor = Some(TouchpadProcessor::new(settings.clone()));
-        let gesture_recognizer = Some(GestureRecognizer::new(settings.clone()));
         let imu_processor = Some(ImuProcessor::new(settings.clone()));
-        let last_connected_address = settings.lock().unwrap().get().last_connected_address;
+        let input_mapper = InputMapper::new(settings.clone());
+        let (
+            last_connected_address,
+            connection_history_size,
+            button_engine,
+            circular_motion,
+            scroll_engine,
+            gesture_recognizer,
+            motion_recognizer,
+            orientation_estimator,
+            imu_preprocessor,
+        ) = {
+            let s = settings.lock().unwrap();
+            (
+                s.get().last_connected_address,
+                s.get().connection_history_size,
+                ButtonEngine::new(s.get().button_timings.clone()),
+                CircularMotion::new(s.get().scroll_ring.clone()),
+                ScrollEngine::new(s.get().scrolling.clone()),
+                Some(GestureRecognizer::new(s.get().gestures.clone())),
+                MotionRecognizer::new(s.get().motion_gestures.clone()),
+                OrientationEstimator::new(s.get().fusion.clone()),
+                ImuPreprocessor::new(s.get().gyro_bias.clone()),
+            )
+        };
 
         Self {
             settings,
@@ -149,48 +247,130 @@ impl GearVRApp {
             touchpad_processor,
             gesture_recognizer,
             imu_processor,
+            circular_motion,
+            scroll_engine,
+            motion_recognizer,
+            orientation_estimator,
+            orientation: None,
+            imu_preprocessor,
+            input_mapper,
+            action_executor: ActionExecutor::new(InputSimulator::new()),
             bluetooth_tx: bt_cmd_tx,
             controller_data_rx: data_rx,
+            adapters: Vec::new(),
+            adapter_state: AdapterState::Unknown,
+            scan_adapter_state: AdapterState::Unknown,
             connection_status: ConnectionStatus::Disconnected,
             status_message: None,
             latest_controller_data: None,
             selected_tab: Tab::Home,
+            filter_preview: FilterPreview::new(FilterSource::Touchpad),
             bluetooth_address_input: String::new(),
             is_calibrating: false,
             calibration_data: CalibrationState::default(),
-            last_trigger_state: false,
-            last_touchpad_button_state: false,
-            last_back_button_state: false,
+            mag_calibrator: None,
+            accel_calibrator: None,
+            axis_wizard: None,
+            button_engine,
+            macro_recorder: MacroRecorder::new(),
+            macro_name_input: String::new(),
+            stroke_capture: StrokeCapture::default(),
+            shape_training: None,
+            last_shape_match: None,
+            profile_switcher: ProfileSwitcher::new(Box::new(SystemForegroundWindow::new())),
+            mode_before_profile: None,
+            media_session: None,
+            now_playing: None,
+            media_overlay_until: None,
             is_scanning: false,
-            scanned_devices: Vec::new(),
+            scanned_devices: ScanResults::new(),
+            scan_paused: false,
             auto_reconnect: false,
             last_connected_address,
             reconnect_timer: None,
-            trigger_debounce: None,
-            touchpad_btn_debounce: None,
-            back_btn_debounce: None,
-            volume_up_debounce: None,
-            volume_down_debounce: None,
+            power_monitor: PowerMonitor::new(Box::new(SystemPowerEvents::new(cc.egui_ctx.clone()))),
+            connection_history: ConnectionHistory::new(connection_history_size),
             admin_client: crate::admin_client::AdminClient::new(),
             is_dark_mode: false,
             _logging_guard: logging_guard,
             radial_menu: RadialMenu::new(),
             current_control_mode: ControlMode::default(),
-            trigger_hold_start: None,
         }
     }
 
     fn process_controller_data(&mut self, mut data: ControllerData) {
-        let (enable_tp, enable_btns, enable_gestures) = {
+        // Packets still in flight while going to sleep must not inject input
+        if self.power_monitor.is_suspended() {
+            return;
+        }
+
+        let (
+            enable_tp,
+            enable_btns,
+            enable_gestures,
+            button_timings,
+            scroll_ring,
+            scrolling,
+            gestures,
+            motion,
+            mut fusion,
+            preview_filter,
+            pointer,
+            device_calibration,
+            gyro_bias,
+            axes,
+        ) = {
+            let address = self.last_connected_address;
             let s = self.settings.lock().unwrap();
             let settings = s.get();
             (
                 settings.enable_touchpad,
                 settings.enable_buttons,
                 settings.enable_gestures,
+                settings.button_timings.clone(),
+                settings.scroll_ring.clone(),
+                settings.scrolling.clone(),
+                settings.gestures.clone(),
+                settings.motion_gestures.clone(),
+                settings.fusion.clone(),
+                match self.filter_preview.source {
+                    FilterSource::Touchpad => settings.touchpad_filter.clone(),
+                    FilterSource::Imu => settings.imu_filter.clone(),
+                },
+                settings.pointer.clone(),
+                address.and_then(|a| s.device_calibration(a)).cloned(),
+                settings.gyro_bias.clone(),
+                settings.axes.clone(),
             )
         };
 
+        // Calibrations collect raw samples; every other consumer sees
+        // corrected ones
+        if let Some(calibrator) = &mut self.mag_calibrator {
+            calibrator.add(&data);
+        }
+        if let Some(calibrator) = &mut self.accel_calibrator {
+            if let Some(face) = calibrator.add(&data) {
+                self.status_message = Some(StatusMessage {
+                    message: format!("Captured the {} position", face),
+                    severity: MessageSeverity::Info,
+                });
+            }
+        }
+        self.imu_preprocessor
+            .set_device(self.last_connected_address, device_calibration.as_ref());
+        self.imu_preprocessor.set_settings(&gyro_bias);
+        if self.imu_preprocessor.process(&mut data) {
+            self.save_gyro_bias();
+        }
+        // An uncalibrated magnetometer would drag the heading around
+        if !self.imu_preprocessor.has_magnetometer_calibration() {
+            fusion.use_magnetometer = false;
+        }
+
+        self.orientation_estimator.set_settings(&fusion);
+        self.orientation = Some(self.orientation_estimator.update(&data));
+
         // Skip normal touchpad/gesture processing when radial menu is active
         let menu_active = self.radial_menu.is_visible;
         // let input_disabled = self.current_control_mode == ControlMode::Disabled; // Disabled mode removed
@@ -200,6 +380,43 @@ impl GearVRApp {
             processor.process(&mut data);
         }
 
+        if self.selected_tab == Tab::Settings {
+            self.filter_preview.push(&data, &preview_filter);
+        }
+        if let Some(wizard) = &mut self.axis_wizard {
+            wizard.add(&data);
+        }
+
+        // Scroll ring: circular motion around the pad center
+        self.circular_motion.set_settings(&scroll_ring);
+        let rotations = self.circular_motion.process(&data);
+        let ring_engaged = enable_tp && self.circular_motion.is_engaged();
+        if enable_tp && !menu_active {
+            for rotation in rotations {
+                self.dispatch(InputEvent::Circle(rotation));
+            }
+        }
+
+        // Motion gestures work in every mode; the recognizer always runs to
+        // keep its gravity estimate current
+        self.motion_recognizer.set_settings(&motion);
+        let motions = self.motion_recognizer.process(&data);
+        if motion.enabled && !menu_active {
+            for gesture in motions {
+                self.dispatch(InputEvent::Motion(gesture));
+            }
+        }
+
+        // Touchpad scrolling in air mouse mode; momentum stops as soon as
+        // the pad is needed for something else
+        let scroll_active = enable_tp
+            && !ring_engaged
+            && !menu_active
+            && self.current_control_mode == ControlMode::Mouse;
+        self.scroll_engine.set_settings(&scrolling);
+        let (horizontal_scroll, vertical_scroll) =
+            self.scroll_engine.update(&data, &axes, scroll_active);
+
         // Handle input based on current control mode
         if !menu_active {
             match self.current_control_mode {
@@ -213,37 +430,17 @@ impl GearVRApp {
                     }
 
                     // 2. Touchpad Scroll (Vertical & Horizontal)
-                    if enable_tp && data.touchpad_touched {
-                        if let Some(processor) = &mut self.touchpad_processor {
-                            // Use raw movement for scroll to avoid acceleration weirdness
-                            let dx = data.touchpad_x as f64
-                                - processor
-                                    .last_processed_pos
-                                    .unwrap_or((data.touchpad_x as f64, data.touchpad_y as f64))
-                                    .0;
-                            let dy = data.touchpad_y as f64
-                                - processor
-                                    .last_processed_pos
-                                    .unwrap_or((data.touchpad_x as f64, data.touchpad_y as f64))
-                                    .1;
-
-                            // Scroll Threshold
-                            let threshold = 0.05;
-                            if dy.abs() > threshold {
-                                let scroll = if dy > 0.0 { -1 } else { 1 };
-                                let _ = self.input_simulator.mouse_wheel(scroll);
-                            }
-                            if dx.abs() > threshold {
-                                let scroll = if dx > 0.0 { 1 } else { -1 };
-                                let _ = self.input_simulator.mouse_h_wheel(scroll);
-                            }
-                        }
+                    if horizontal_scroll != 0 {
+                        let _ = self.input_simulator.smooth_h_wheel(horizontal_scroll);
+                    }
+                    if vertical_scroll != 0 {
+                        let _ = self.input_simulator.smooth_wheel(vertical_scroll);
                     }
                 }
                 ControlMode::Touchpad => {
                     // --- LAPTOP TRACKPAD MODE ---
                     // 1. Touchpad Cursor
-                    if enable_tp && data.touchpad_touched {
+                    if enable_tp && data.touchpad_touched && !ring_engaged {
                         if let Some(processor) = &mut self.touchpad_processor {
                             if let Some((dx, dy)) = processor.calculate_mouse_delta(&data) {
                                 let _ = self.input_simulator.move_mouse(dx, dy);
@@ -251,269 +448,574 @@ impl GearVRApp {
                         }
                     }
                 }
-                ControlMode::Presentation | ControlMode::Settings => {
+                ControlMode::Pointer => {
+                    // --- LASER POINTER MODE ---
+                    // Cursor sits where the controller points
+                    if let (Some(imu), Some(orientation)) =
+                        (&mut self.imu_processor, &self.orientation)
+                    {
+                        let bounds = self.input_simulator.screen_bounds(pointer.all_monitors);
+                        if let Some((x, y)) =
+                            imu.calculate_pointer_position(orientation, &pointer, bounds)
+                        {
+                            let _ = self.input_simulator.set_cursor_pos(x, y);
+                        }
+                    }
+                }
+                ControlMode::Presentation | ControlMode::Media | ControlMode::Settings => {
                     // No cursor movement in these modes
                 }
             }
         }
 
         if enable_gestures && !menu_active {
-            if let Some(recognizer) = &mut self.gesture_recognizer {
-                if let Some(direction) = recognizer.process(&data) {
-                    let msg = format!("Gesture Detected: {:?}", direction);
-                    tracing::info!("{}", msg);
-                    self.status_message = Some(StatusMessage {
-                        message: msg.clone(),
-                        severity: MessageSeverity::Info,
-                    });
+            let circled = self.circular_motion.has_rotated();
+            let stroke = self.stroke_capture.process(&data).filter(|_| !circled);
+            let gesture = self
+                .gesture_recognizer
+                .as_mut()
+                .and_then(|recognizer| {
+                    recognizer.set_settings(&gestures);
+                    recognizer.process(&data)
+                })
+                .filter(|_| !circled);
 
-                    match direction {
-                        GestureDirection::Up => {
-                            let _ = self.input_simulator.mouse_wheel(1);
-                        }
-                        GestureDirection::Down => {
-                            let _ = self.input_simulator.mouse_wheel(-1);
-                        }
-                        GestureDirection::Left | GestureDirection::Right => {
-                            let _ = self
-                                .input_simulator
-                                .key_press(windows::Win32::UI::Input::KeyboardAndMouse::VK_LMENU);
-                        }
-                        _ => {}
-                    }
+            let event = if self.shape_training.is_some() {
+                if let Some(stroke) = stroke {
+                    self.train_shape(&stroke);
                 }
+                None
+            } else {
+                stroke
+                    .and_then(|stroke| self.recognize_shape(&stroke))
+                    .or(gesture)
+            };
+
+            if let Some(event) = event {
+                let msg = format!("Gesture Detected: {}", event);
+                tracing::info!("{}", msg);
+                self.status_message = Some(StatusMessage {
+                    message: msg.clone(),
+                    severity: MessageSeverity::Info,
+                });
+
+                self.dispatch(event);
             }
         }
 
-        let now = Instant::now();
-        let debounce_duration = Duration::from_millis(50);
-        let menu_hold_threshold = Duration::from_millis(300);
-
         if enable_btns {
-            // --- BUTTON MAPPING BASED ON MODE ---
-
-            // Trigger Button
-            if data.trigger_button != self.last_trigger_state {
-                if self
-                    .trigger_debounce
-                    .map_or(true, |last| now.duration_since(last) > debounce_duration)
-                {
-                    self.last_trigger_state = data.trigger_button;
-                    self.trigger_debounce = Some(now);
-
-                    if data.trigger_button {
-                        // Trigger Pressed
-                        match self.current_control_mode {
-                            ControlMode::Mouse | ControlMode::Touchpad => {
-                                let _ = self.input_simulator.mouse_left_down();
-                            }
-                            ControlMode::Presentation => {
-                                // Next Slide (Right Arrow)
-                                let _ = self.input_simulator.key_press(
-                                    windows::Win32::UI::Input::KeyboardAndMouse::VK_RIGHT,
-                                );
-                            }
-                            _ => {}
+            self.button_engine.set_timings(&button_timings);
+            for event in self.button_engine.update(&data) {
+                match event {
+                    // Back long press opens the radial menu, release picks the mode
+                    InputEvent::Button(ControllerButton::Back, ButtonTrigger::LongPress) => {
+                        if let Ok((x, y)) = self.input_simulator.get_cursor_pos() {
+                            self.radial_menu.show(Pos2::new(x as f32, y as f32));
                         }
-                    } else {
-                        // Trigger Released
-                        match self.current_control_mode {
-                            ControlMode::Mouse | ControlMode::Touchpad => {
-                                let _ = self.input_simulator.mouse_left_up();
-                            }
-                            ControlMode::Presentation => {
-                                // Key press already handled on down, no release needed for simple key.
-                            }
-                            _ => {}
+                    }
+                    InputEvent::Button(ControllerButton::Back, ButtonTrigger::Release)
+                        if self.radial_menu.is_visible =>
+                    {
+                        if let Some(selected_mode) = self.radial_menu.hide() {
+                            self.set_control_mode(selected_mode);
                         }
                     }
+                    event => self.dispatch(event),
                 }
             }
 
-            // Touchpad Button (Center Click)
-            if data.touchpad_button != self.last_touchpad_button_state {
-                if self
-                    .touchpad_btn_debounce
-                    .map_or(true, |last| now.duration_since(last) > debounce_duration)
-                {
-                    self.last_touchpad_button_state = data.touchpad_button;
-                    self.touchpad_btn_debounce = Some(now);
-                    if data.touchpad_button {
-                        // Touchpad Button Pressed
-                        match self.current_control_mode {
-                            ControlMode::Mouse | ControlMode::Touchpad => {
-                                let _ = self.input_simulator.mouse_right_down();
-                            }
-                            ControlMode::Presentation => {
-                                // Previous Slide (Left Arrow)
-                                let _ = self.input_simulator.key_press(
-                                    windows::Win32::UI::Input::KeyboardAndMouse::VK_LEFT,
-                                );
-                            }
-                            _ => {}
-                        }
-                    } else {
-                        // Touchpad Button Released
-                        match self.current_control_mode {
-                            ControlMode::Mouse | ControlMode::Touchpad => {
-                                let _ = self.input_simulator.mouse_right_up();
-                            }
-                            ControlMode::Presentation => {
-                                // Key press already handled on down.
-                            }
-                            _ => {}
-                        }
-                    }
-                }
+            // Update menu selection based on touchpad
+            if self.radial_menu.is_visible && data.touchpad_touched {
+                self.radial_menu
+                    .update_selection(data.processed_touchpad_x, data.processed_touchpad_y);
             }
+        }
 
-            // Back Button (Radial Menu Activator on Long Press, otherwise Escape)
-            if data.back_button {
-                if self.trigger_hold_start.is_none() {
-                    // Reusing trigger_hold_start for back button hold
-                    self.trigger_hold_start = Some(now);
-                } else if let Some(start_time) = self.trigger_hold_start {
-                    if now.duration_since(start_time) >= menu_hold_threshold
-                        && !self.radial_menu.is_visible
-                    {
-                        // Show radial menu at current cursor position
-                        if let Ok((x, y)) = self.input_simulator.get_cursor_pos() {
-                            self.radial_menu.show(Pos2::new(x as f32, y as f32));
-                        }
-                    }
+        if self.is_calibrating && data.touchpad_touched {
+            self.calibration_data
+                .samples
+                .push((data.touchpad_x, data.touchpad_y));
+            self.calibration_data.min_x = self.calibration_data.min_x.min(data.touchpad_x);
+            self.calibration_data.max_x = self.calibration_data.max_x.max(data.touchpad_x);
+            self.calibration_data.min_y = self.calibration_data.min_y.min(data.touchpad_y);
+            self.calibration_data.max_y = self.calibration_data.max_y.max(data.touchpad_y);
+        }
 
-                    // Update menu selection based on touchpad
-                    if self.radial_menu.is_visible && data.touchpad_touched {
-                        self.radial_menu
-                            .update_selection(data.processed_touchpad_x, data.processed_touchpad_y);
-                    }
+        self.latest_controller_data = Some(data);
+    }
+
+    /// Expire stale scan results and, in auto-connect mode, connect to the
+    /// strongest known controller once it has been heard a few times.
+    fn update_scan_results(&mut self) {
+        let (timeout_secs, auto_connect, known) = {
+            let s = self.settings.lock().unwrap();
+            let settings = s.get();
+            (
+                settings.scan_device_timeout_secs,
+                settings.scan_auto_connect,
+                settings.known_bluetooth_addresses.clone(),
+            )
+        };
+
+        self.scanned_devices
+            .prune(Instant::now(), Duration::from_secs(timeout_secs));
+
+        if !self.is_scanning
+            || !auto_connect
+            || self.connection_status != ConnectionStatus::Disconnected
+        {
+            return;
+        }
+
+        if let Some(target) = self
+            .scanned_devices
+            .strongest_known(&known, AUTO_CONNECT_MIN_SAMPLES)
+        {
+            let address = target.device.address;
+            let name = target.device.name.clone();
+            tracing::info!(
+                "Auto-connecting to strongest known controller {:#X}",
+                address
+            );
+
+            self.is_scanning = false;
+            let _ = self.bluetooth_tx.send(BluetoothCommand::StopScan);
+
+            self.bluetooth_address_input = format!("{:X}", address);
+            self.connection_status = ConnectionStatus::Connecting;
+            self.auto_reconnect = true;
+            self.last_connected_address = Some(address);
+            let _ = self.bluetooth_tx.send(BluetoothCommand::Connect(address));
+
+            self.status_message = Some(StatusMessage {
+                message: format!("Auto-connecting to {}...", name),
+                severity: MessageSeverity::Info,
+            });
+        }
+    }
+
+    /// Run the actions bound to `event` in the current mode
+    fn dispatch(&mut self, event: InputEvent) {
+        let now = Instant::now();
+        for action in self.input_mapper.map(self.current_control_mode, event) {
+            self.macro_recorder.record(&action, now);
+            self.action_executor.run(&action, now);
+        }
+        self.apply_host_requests();
+    }
+
+    /// Shape event for a finished stroke. Shapes without a binding leave
+    /// the stroke to the swipe gestures.
+    fn recognize_shape(&mut self, stroke: &[Point]) -> Option<InputEvent> {
+        let (best, accepted) = {
+            let settings = self.settings.lock().ok()?;
+            let shapes = &settings.get().shape_gestures;
+            if !shapes.enabled {
+                return None;
+            }
+            let best = shapes.best_match(stroke)?;
+            let accepted = shapes.accepts(&best);
+            (best, accepted)
+        };
+
+        let event = InputEvent::Shape(best.id);
+        self.last_shape_match = Some(best);
+        let bound = !self
+            .input_mapper
+            .map(self.current_control_mode, event)
+            .is_empty();
+        (accepted && bound).then_some(event)
+    }
+
+    /// Store a stroke as a sample of the shape being trained
+    fn train_shape(&mut self, stroke: &[Point]) {
+        let Some(id) = self.shape_training else {
+            return;
+        };
+        let Ok(mut settings) = self.settings.lock() else {
+            return;
+        };
+        let result = settings
+            .get_mut()
+            .shape_gestures
+            .train(id, stroke)
+            .and_then(|()| settings.save());
+        drop(settings);
+
+        self.status_message = Some(match result {
+            Ok(()) => {
+                self.shape_training = None;
+                StatusMessage {
+                    message: "Shape sample saved".to_string(),
+                    severity: MessageSeverity::Success,
                 }
-            } else {
-                // Back Button Released
-                if let Some(start_time) = self.trigger_hold_start {
-                    let hold_duration = now.duration_since(start_time);
+            }
+            Err(e) => StatusMessage {
+                message: format!("Sample not saved, draw again: {}", e),
+                severity: MessageSeverity::Warning,
+            },
+        });
+    }
 
-                    if self.radial_menu.is_visible {
-                        // Was showing radial menu - handle selection
-                        if let Some(selected_mode) = self.radial_menu.hide() {
-                            if selected_mode == ControlMode::Settings {
-                                self.selected_tab = Tab::Settings;
-                            } else {
-                                self.current_control_mode = selected_mode;
-                            }
+    /// Handle mode switches and macro control raised by actions
+    fn apply_host_requests(&mut self) {
+        for request in self.action_executor.take_requests() {
+            match request {
+                HostRequest::SwitchMode(mode) => self.set_control_mode(mode),
+                HostRequest::Media(command) => self.send_media_command(command),
+                HostRequest::PlayMacro(name) => self.play_macro(&name),
+                HostRequest::ToggleRecording => self.toggle_macro_recording(),
+                HostRequest::Recenter => self.recenter_pointer(),
+            }
+        }
+    }
 
-                            self.status_message = Some(StatusMessage {
-                                message: format!(
-                                    "Mode: {} - {}",
-                                    selected_mode.name(),
-                                    selected_mode.description()
-                                ),
-                                severity: MessageSeverity::Success,
-                            });
-                        }
-                    } else if hold_duration < menu_hold_threshold {
-                        // Quick tap - normal back/escape behavior
-                        if self
-                            .back_btn_debounce
-                            .map_or(true, |last| now.duration_since(last) > debounce_duration)
-                        {
-                            self.back_btn_debounce = Some(now);
-                            match self.current_control_mode {
-                                ControlMode::Mouse | ControlMode::Touchpad => {
-                                    // Right Click
-                                    let _ = self.input_simulator.mouse_right_click();
-                                }
-                                ControlMode::Presentation => {
-                                    // Prev Slide
-                                    let _ = self.input_simulator.key_press(
-                                        windows::Win32::UI::Input::KeyboardAndMouse::VK_LEFT,
-                                    );
-                                }
-                                _ => {}
-                            }
-                        }
+    fn send_media_command(&mut self, command: MediaCommand) {
+        let session = self
+            .media_session
+            .as_mut()
+            .map(|s| s.as_mut() as &mut dyn MediaSession);
+        match command.route(session) {
+            Some(fallback) => self.action_executor.run(&fallback, Instant::now()),
+            None => self.media_overlay_until = Some(Instant::now() + MEDIA_OVERLAY_DURATION),
+        }
+    }
+
+    /// Keep the media session connected while Media mode uses it and
+    /// track the current title for the overlay
+    fn update_media_session(&mut self) {
+        let enabled = self.current_control_mode == ControlMode::Media
+            && self
+                .settings
+                .lock()
+                .map(|s| s.get().media_session_enabled)
+                .unwrap_or(false);
+
+        if !enabled {
+            self.media_session = None;
+            self.now_playing = None;
+            return;
+        }
+
+        let session = self
+            .media_session
+            .get_or_insert_with(|| Box::new(SystemMediaSession::new()));
+        let now_playing = session.now_playing();
+        let title_changed =
+            now_playing.as_ref().map(|n| &n.title) != self.now_playing.as_ref().map(|n| &n.title);
+        if title_changed && now_playing.is_some() {
+            self.media_overlay_until = Some(Instant::now() + MEDIA_OVERLAY_DURATION);
+        }
+        self.now_playing = now_playing;
+    }
+
+    /// Show the current track for a few seconds after it changes
+    fn render_media_overlay(&self, ctx: &egui::Context) {
+        let (Some(track), Some(until)) = (&self.now_playing, self.media_overlay_until) else {
+            return;
+        };
+        if Instant::now() >= until {
+            return;
+        }
+
+        egui::Area::new(egui::Id::new("now_playing_overlay"))
+            .anchor(egui::Align2::CENTER_BOTTOM, [0.0, -50.0])
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    let state = if track.playing { "▶" } else { "⏸" };
+                    ui.label(
+                        egui::RichText::new(format!("{} {}", state, track.title))
+                            .strong()
+                            .size(18.0),
+                    );
+                    if !track.artist.is_empty() {
+                        ui.label(&track.artist);
                     }
-                    self.trigger_hold_start = None;
+                });
+            });
+    }
+
+    pub(crate) fn play_macro(&mut self, name: &str) {
+        let steps = self.settings.lock().ok().and_then(|s| {
+            s.get()
+                .macros
+                .iter()
+                .find(|m| m.name == name)
+                .map(|m| m.steps.clone())
+        });
+
+        self.status_message = Some(match steps {
+            Some(steps) => {
+                self.action_executor.play(&steps, Instant::now());
+                StatusMessage {
+                    message: format!("Playing macro '{}'", name),
+                    severity: MessageSeverity::Info,
                 }
             }
+            None => StatusMessage {
+                message: format!("Unknown macro '{}'", name),
+                severity: MessageSeverity::Warning,
+            },
+        });
+    }
 
-            // Volume Up Button
-            if data.volume_up_button {
-                if self
-                    .volume_up_debounce
-                    .map_or(true, |last| now.duration_since(last) > debounce_duration)
-                {
-                    self.volume_up_debounce = Some(now);
-                    match self.current_control_mode {
-                        ControlMode::Mouse => {
-                            // Volume Up
-                            let _ = self.input_simulator.key_press(
-                                windows::Win32::UI::Input::KeyboardAndMouse::VK_VOLUME_UP,
-                            );
-                        }
-                        ControlMode::Touchpad => {
-                            // Scroll Up
-                            let _ = self.input_simulator.mouse_wheel(1);
-                        }
-                        ControlMode::Presentation => {
-                            // Volume Up
-                            let _ = self.input_simulator.key_press(
-                                windows::Win32::UI::Input::KeyboardAndMouse::VK_VOLUME_UP,
-                            );
-                        }
-                        _ => {}
-                    }
+    /// Persist the learned gyro bias for the connected controller
+    fn save_gyro_bias(&mut self) {
+        let (Some(address), Ok(mut settings)) = (self.last_connected_address, self.settings.lock())
+        else {
+            return;
+        };
+        settings.device_calibration_mut(address).gyro_bias =
+            self.imu_preprocessor.gyro_bias().bias().clone();
+        if let Err(e) = settings.save() {
+            error!("Failed to save gyro bias: {}", e);
+        }
+    }
+
+    /// Take the current pointing direction as the screen center
+    pub(crate) fn recenter_pointer(&mut self) {
+        if let Some(imu) = &mut self.imu_processor {
+            imu.reset_orientation();
+        }
+        self.status_message = Some(StatusMessage {
+            message: "Pointer recentered".to_string(),
+            severity: MessageSeverity::Info,
+        });
+    }
+
+    /// Stop macro playback and release the keys and buttons it holds
+    pub(crate) fn stop_macro(&mut self) {
+        self.action_executor.cancel();
+        self.status_message = Some(StatusMessage {
+            message: "Macro playback stopped".to_string(),
+            severity: MessageSeverity::Info,
+        });
+    }
+
+    pub(crate) fn toggle_macro_recording(&mut self) {
+        if !self.macro_recorder.is_recording() {
+            self.macro_recorder.start(Instant::now());
+            self.status_message = Some(StatusMessage {
+                message: "Recording macro...".to_string(),
+                severity: MessageSeverity::Info,
+            });
+            return;
+        }
+
+        let steps = self.macro_recorder.stop();
+        if steps.is_empty() {
+            self.status_message = Some(StatusMessage {
+                message: "Nothing was recorded".to_string(),
+                severity: MessageSeverity::Warning,
+            });
+            return;
+        }
+
+        let Ok(mut settings) = self.settings.lock() else {
+            return;
+        };
+        let name = match self.macro_name_input.trim() {
+            "" => {
+                let existing = &settings.get().macros;
+                (1..)
+                    .map(|n| format!("Macro {}", n))
+                    .find(|name| existing.iter().all(|m| &m.name != name))
+                    .unwrap_or_default()
+            }
+            name => name.to_string(),
+        };
+
+        let message = match settings.save_macro(Macro::new(name.clone(), steps)) {
+            Ok(()) => StatusMessage {
+                message: format!("Saved macro '{}'", name),
+                severity: MessageSeverity::Success,
+            },
+            Err(e) => StatusMessage {
+                message: format!("Failed to save macro '{}': {}", name, e),
+                severity: MessageSeverity::Error,
+            },
+        };
+        drop(settings);
+
+        self.macro_name_input.clear();
+        self.status_message = Some(message);
+    }
+
+    fn set_control_mode(&mut self, mode: ControlMode) {
+        if mode == ControlMode::Settings {
+            self.selected_tab = Tab::Settings;
+        } else {
+            self.current_control_mode = mode;
+        }
+
+        self.status_message = Some(StatusMessage {
+            message: format!("Mode: {} - {}", mode.name(), mode.description()),
+            severity: MessageSeverity::Success,
+        });
+    }
+
+    /// Follow the foreground window to the matching profile
+    fn update_active_profile(&mut self) {
+        let Ok(mut settings) = self.settings.lock() else {
+            return;
+        };
+        if !self
+            .profile_switcher
+            .poll(&settings.get().profiles, Instant::now())
+        {
+            return;
+        }
+
+        let active = self.profile_switcher.active().map(str::to_string);
+        settings.set_active_profile(active.clone());
+        let profile_mode = settings.active_profile().and_then(|p| p.mode);
+        drop(settings);
+
+        match profile_mode {
+            Some(mode) => {
+                self.mode_before_profile
+                    .get_or_insert(self.current_control_mode);
+                self.current_control_mode = mode;
+            }
+            None => {
+                if let Some(mode) = self.mode_before_profile.take() {
+                    self.current_control_mode = mode;
                 }
             }
+        }
 
-            // Volume Down Button
-            if data.volume_down_button {
-                if self
-                    .volume_down_debounce
-                    .map_or(true, |last| now.duration_since(last) > debounce_duration)
-                {
-                    self.volume_down_debounce = Some(now);
-                    match self.current_control_mode {
-                        ControlMode::Mouse => {
-                            // Volume Down
-                            let _ = self.input_simulator.key_press(
-                                windows::Win32::UI::Input::KeyboardAndMouse::VK_VOLUME_DOWN,
-                            );
-                        }
-                        ControlMode::Touchpad => {
-                            // Scroll Down
-                            let _ = self.input_simulator.mouse_wheel(-1);
-                        }
-                        ControlMode::Presentation => {
-                            // Volume Down
-                            let _ = self.input_simulator.key_press(
-                                windows::Win32::UI::Input::KeyboardAndMouse::VK_VOLUME_DOWN,
-                            );
-                        }
-                        _ => {}
-                    }
+        self.status_message = Some(StatusMessage {
+            message: match active {
+                Some(name) => format!("Profile: {}", name),
+                None => "Profile: Default".to_string(),
+            },
+            severity: MessageSeverity::Info,
+        });
+    }
+
+    /// Release every button/key the controller may be holding down and
+    /// reset the edge detection state
+    fn release_held_inputs(&mut self) {
+        self.action_executor.cancel();
+        self.action_executor.release_all();
+        self.button_engine.reset();
+        self.orientation_estimator.reset();
+        self.orientation = None;
+        self.radial_menu.hide();
+    }
+
+    /// Apply suspend/resume notifications
+    fn handle_power_events(&mut self) {
+        let snapshot = LinkSnapshot {
+            active: matches!(
+                self.connection_status,
+                ConnectionStatus::Connected | ConnectionStatus::Connecting
+            ),
+            auto_reconnect: self.auto_reconnect,
+            last_address: self.last_connected_address,
+        };
+
+        for action in self.power_monitor.poll(snapshot) {
+            match action {
+                PowerAction::ReleaseInputs => self.release_held_inputs(),
+                PowerAction::Disconnect => {
+                    tracing::info!("System suspending, releasing controller link");
+                    self.connection_status = ConnectionStatus::Disconnected;
+                    self.reconnect_timer = None;
+                    let _ = self.bluetooth_tx.send(BluetoothCommand::Disconnect);
+                    self.status_message = Some(StatusMessage {
+                        message: "System suspended, controller disconnected".to_string(),
+                        severity: MessageSeverity::Info,
+                    });
+                }
+                PowerAction::Reconnect(address) => {
+                    tracing::info!("System resumed, reconnecting to {:#X}", address);
+                    self.last_connected_address = Some(address);
+                    self.auto_reconnect = true;
+                    self.reconnect_timer = Some(Instant::now());
+                    self.status_message = Some(StatusMessage {
+                        message: "Resumed from sleep, reconnecting...".to_string(),
+                        severity: MessageSeverity::Info,
+                    });
                 }
             }
         }
+    }
 
-        if self.is_calibrating && data.touchpad_touched {
-            self.calibration_data
-                .samples
-                .push((data.touchpad_x, data.touchpad_y));
-            self.calibration_data.min_x = self.calibration_data.min_x.min(data.touchpad_x);
-            self.calibration_data.max_x = self.calibration_data.max_x.max(data.touchpad_x);
-            self.calibration_data.min_y = self.calibration_data.min_y.min(data.touchpad_y);
-            self.calibration_data.max_y = self.calibration_data.max_y.max(data.touchpad_y);
+    /// Pause scanning while the default adapter's radio is unusable and
+    /// resume it when the radio returns
+    fn handle_scan_adapter_state(&mut self, state: AdapterState) {
+        let was_usable = self.scan_adapter_state.is_usable();
+        self.scan_adapter_state = state;
+
+        if !state.is_usable() {
+            if self.is_scanning {
+                self.is_scanning = false;
+                self.scan_paused = true;
+                let _ = self.bluetooth_tx.send(BluetoothCommand::StopScan);
+                self.status_message = Some(StatusMessage {
+                    message: "Scanning paused: the default Bluetooth adapter is not ready"
+                        .to_string(),
+                    severity: MessageSeverity::Warning,
+                });
+            }
+        } else if !was_usable && self.scan_paused {
+            self.scan_paused = false;
+            self.is_scanning = true;
+            let _ = self.bluetooth_tx.send(BluetoothCommand::StartScan);
         }
+    }
 
-        self.latest_controller_data = Some(data);
+    /// React to the radio being switched off/on or the adapter disappearing
+    fn handle_adapter_state(&mut self, state: AdapterState) {
+        let was_usable = self.adapter_state.is_usable();
+        self.adapter_state = state;
+
+        if !state.is_usable() {
+            self.reconnect_timer = None;
+            self.status_message = Some(StatusMessage {
+                message: match state {
+                    AdapterState::RadioOff => "Bluetooth radio is off".to_string(),
+                    _ => "Bluetooth adapter unavailable".to_string(),
+                },
+                severity: MessageSeverity::Warning,
+            });
+            return;
+        }
+
+        if was_usable {
+            return;
+        }
+
+        tracing::info!("Bluetooth adapter ready again");
+        if self.auto_reconnect
+            && self.connection_status == ConnectionStatus::Disconnected
+            && self.last_connected_address.is_some()
+        {
+            self.reconnect_timer = Some(Instant::now() + Duration::from_millis(1000));
+        }
+        self.status_message = Some(StatusMessage {
+            message: "Bluetooth adapter ready".to_string(),
+            severity: MessageSeverity::Info,
+        });
     }
 }
 
 impl eframe::App for GearVRApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.handle_power_events();
+        self.update_active_profile();
+        self.update_media_session();
+
+        self.action_executor.tick(Instant::now());
+        self.apply_host_requests();
+
         if let Some(time) = self.reconnect_timer {
-            if Instant::now() >= time {
+            if !self.adapter_state.is_usable() {
+                // Wait for the radio to come back instead of failing repeatedly
+                self.reconnect_timer = None;
+            } else if Instant::now() >= time {
                 self.reconnect_timer = None;
                 if let Some(address) = self.last_connected_address {
                     self.connection_status = ConnectionStatus::Connecting;
@@ -541,7 +1043,10 @@ impl eframe::App for GearVRApp {
                             }
                         }
                     } else if let ConnectionStatus::Disconnected = status {
-                        if self.auto_reconnect {
+                        if self.auto_reconnect
+                            && self.adapter_state.is_usable()
+                            && !self.power_monitor.is_suspended()
+                        {
                             self.reconnect_timer =
                                 Some(Instant::now() + Duration::from_millis(2000));
 
@@ -571,19 +1076,24 @@ impl eframe::App for GearVRApp {
                     self.status_message = Some(msg);
                 }
                 AppEvent::DeviceFound(device) => {
-                    if let Some(existing) = self
-                        .scanned_devices
-                        .iter_mut()
-                        .find(|d| d.address == device.address)
-                    {
-                        existing.signal_strength = device.signal_strength;
-                    } else {
-                        self.scanned_devices.push(device);
-                    }
+                    self.scanned_devices.update(device, Instant::now());
+                }
+                AppEvent::ConnectionAttempt(attempt) => {
+                    self.connection_history.push(attempt);
+                }
+                AppEvent::AdapterList(adapters) => {
+                    self.adapters = adapters;
+                }
+                AppEvent::AdapterState(state) => self.handle_adapter_state(state),
+                AppEvent::ScanAdapterState(state) => self.handle_scan_adapter_state(state),
+                AppEvent::AdaptersChanged => {
+                    let _ = self.bluetooth_tx.send(BluetoothCommand::RefreshAdapters);
                 }
             }
         }
 
+        self.update_scan_results();
+
         ctx.request_repaint();
 
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
@@ -591,6 +1101,8 @@ impl eframe::App for GearVRApp {
                 ui.selectable_value(&mut self.selected_tab, Tab::Home, "Home");
                 ui.selectable_value(&mut self.selected_tab, Tab::Calibration, "Calibration");
                 ui.selectable_value(&mut self.selected_tab, Tab::Settings, "Settings");
+                ui.selectable_value(&mut self.selected_tab, Tab::Macros, "Macros");
+                ui.selectable_value(&mut self.selected_tab, Tab::Profiles, "Profiles");
                 ui.selectable_value(&mut self.selected_tab, Tab::Debug, "Debug");
 
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
@@ -607,6 +1119,27 @@ impl eframe::App for GearVRApp {
             });
         });
 
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(format!(
+                    "{} {}",
+                    self.current_control_mode.icon(),
+                    self.current_control_mode.name()
+                ));
+                ui.separator();
+                ui.label(format!(
+                    "Profile: {}",
+                    self.profile_switcher.active().unwrap_or("Default")
+                ));
+                if self.macro_recorder.is_recording() {
+                    ui.separator();
+                    ui.label(
+                        egui::RichText::new("● REC").color(egui::Color32::from_rgb(220, 50, 50)),
+                    );
+                }
+            });
+        });
+
         egui::CentralPanel::default().show(ctx, |ui| {
             egui::ScrollArea::vertical().show(ui, |ui| {
                 ui.vertical_centered(|ui| {
@@ -618,6 +1151,8 @@ impl eframe::App for GearVRApp {
                         Tab::Home => tabs::home::render(self, ui),
                         Tab::Calibration => tabs::calibration::render(self, ui),
                         Tab::Settings => tabs::settings::render(self, ui),
+                        Tab::Macros => tabs::macros::render(self, ui),
+                        Tab::Profiles => tabs::profiles::render(self, ui),
                         Tab::Debug => tabs::debug::render(self, ui),
                     }
 
@@ -626,6 +1161,8 @@ impl eframe::App for GearVRApp {
             });
         });
 
+        self.render_media_overlay(ctx);
+
         // Render radial menu overlay (on top of everything)
         self.radial_menu.render(ctx);
     }
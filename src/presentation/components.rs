@@ -1,3 +1,4 @@
+use crate::domain::actions::Action;
 use eframe::egui;
 
 pub struct Components;
@@ -62,4 +63,76 @@ impl Components {
             .wrap_mode(egui::TextWrapMode::Extend),
         );
     }
+
+    /// Single-line editor for an action in its text form
+    ///
+    /// The text is only applied when it parses; invalid input is kept while
+    /// the field has focus and outlined in red. Returns true if `action`
+    /// changed.
+    pub fn action_edit(ui: &mut egui::Ui, id: egui::Id, action: &mut Action) -> bool {
+        let mut text = ui
+            .data_mut(|d| d.get_temp::<String>(id))
+            .unwrap_or_else(|| action.to_string());
+        let parsed = text.parse::<Action>();
+
+        let mut edit = egui::TextEdit::singleline(&mut text)
+            .id(id)
+            .desired_width(ui.available_width() - 40.0);
+        if parsed.is_err() {
+            edit = edit.text_color(egui::Color32::from_rgb(220, 50, 50));
+        }
+        let mut response = ui.add(edit);
+        if let Err(e) = &parsed {
+            response = response.on_hover_text(e.to_string());
+        }
+
+        let mut changed = false;
+        if response.changed() {
+            if let Ok(new_action) = text.parse::<Action>() {
+                changed = new_action != *action;
+                *action = new_action;
+            }
+        }
+
+        if response.has_focus() {
+            ui.data_mut(|d| d.insert_temp(id, text));
+        } else {
+            ui.data_mut(|d| d.remove::<String>(id));
+        }
+        changed
+    }
+
+    /// Line plot of evenly spaced samples, all series sharing one
+    /// auto-scaled vertical axis
+    pub fn signal_plot(ui: &mut egui::Ui, series: &[(Vec<f64>, egui::Color32)], height: f32) {
+        let (rect, _) = ui.allocate_exact_size(
+            egui::vec2(ui.available_width(), height),
+            egui::Sense::hover(),
+        );
+        let painter = ui.painter_at(rect);
+        let stroke = ui.style().visuals.widgets.noninteractive.bg_stroke;
+        painter.rect_stroke(rect, 0.0, stroke);
+
+        let values = series.iter().flat_map(|(values, _)| values.iter().copied());
+        let (min, max) = values.fold((f64::MAX, f64::MIN), |(min, max), v| {
+            (min.min(v), max.max(v))
+        });
+        if min > max {
+            return;
+        }
+        let span = (max - min).max(1e-6);
+        let inner = rect.shrink(4.0);
+        for (values, color) in series {
+            let step = inner.width() / values.len().saturating_sub(1).max(1) as f32;
+            let points = values
+                .iter()
+                .enumerate()
+                .map(|(i, v)| {
+                    let y = inner.bottom() - ((v - min) / span) as f32 * inner.height();
+                    egui::pos2(inner.left() + i as f32 * step, y)
+                })
+                .collect();
+            painter.add(egui::Shape::line(points, egui::Stroke::new(1.5, *color)));
+        }
+    }
 }
@@ -6,23 +6,16 @@
 use eframe::egui::{self, Color32, Pos2, Stroke, Vec2};
 use std::f32::consts::PI;
 
-/// Available control modes for the controller
-/// Available control modes for the controller
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
-pub enum ControlMode {
-    #[default]
-    Mouse, // Air Mouse Mode (IMU cursor + TP scroll)
-    Touchpad,     // Laptop Trackpad Mode (TP cursor + Button scroll)
-    Presentation, // PPT/Media Mode (Buttons only)
-    Settings,     // Quick Settings / Calibration
-}
+pub use crate::domain::models::ControlMode;
 
 impl ControlMode {
     pub fn name(&self) -> &'static str {
         match self {
             ControlMode::Mouse => "Air Mouse",
             ControlMode::Touchpad => "Touchpad",
+            ControlMode::Pointer => "Laser Pointer",
             ControlMode::Presentation => "Presenter",
+            ControlMode::Media => "Media",
             ControlMode::Settings => "Settings",
         }
     }
@@ -31,7 +24,9 @@ impl ControlMode {
         match self {
             ControlMode::Mouse => "✈️",
             ControlMode::Touchpad => "🖱️",
+            ControlMode::Pointer => "🎯",
             ControlMode::Presentation => "📽️",
+            ControlMode::Media => "🎵",
             ControlMode::Settings => "⚙️",
         }
     }
@@ -40,7 +35,9 @@ impl ControlMode {
         match self {
             ControlMode::Mouse => "Wave to move, Touch to scroll",
             ControlMode::Touchpad => "Laptop style control",
-            ControlMode::Presentation => "PPT & Media control",
+            ControlMode::Pointer => "Cursor follows where you point",
+            ControlMode::Presentation => "Slide deck control",
+            ControlMode::Media => "Play, seek & circle for volume",
             ControlMode::Settings => "Calibration & Options",
         }
     }
@@ -76,7 +73,9 @@ impl RadialMenu {
         let modes = [
             ControlMode::Mouse,
             ControlMode::Touchpad,
+            ControlMode::Pointer,
             ControlMode::Presentation,
+            ControlMode::Media,
             ControlMode::Settings,
         ];
 
@@ -1,9 +1,14 @@
+use crate::domain::actions::{Action, Key};
+use crate::domain::axes::{AxisWizard, WizardStep};
+use crate::domain::calibration::{AccelCalibrator, Face, MagCalibrator, MIN_MAG_SAMPLES};
+use crate::domain::input_mapper::InputEvent;
 use crate::domain::models::{
-    CalibrationState, MessageSeverity, StatusMessage, TouchpadCalibration,
+    CalibrationState, ConnectionStatus, MessageSeverity, StatusMessage, TouchpadCalibration,
 };
 use crate::presentation::app::GearVRApp;
 use crate::presentation::components::Components;
 use eframe::egui;
+use tracing::error;
 
 pub fn render(app: &mut GearVRApp, ui: &mut egui::Ui) {
     Components::heading(ui, "Touchpad Calibration");
@@ -67,4 +72,433 @@ pub fn render(app: &mut GearVRApp, ui: &mut egui::Ui) {
             }
         }
     });
+
+    ui.add_space(10.0);
+    render_mag_calibration(app, ui);
+
+    ui.add_space(10.0);
+    render_accel_calibration(app, ui);
+
+    ui.add_space(10.0);
+    render_axis_wizard(app, ui);
+
+    ui.add_space(10.0);
+    render_shape_training(app, ui);
+}
+
+fn render_mag_calibration(app: &mut GearVRApp, ui: &mut egui::Ui) {
+    Components::brutalist_card(ui, "Magnetometer Calibration", |ui| {
+        let address = app
+            .last_connected_address
+            .filter(|_| app.connection_status == ConnectionStatus::Connected);
+        let Some(address) = address else {
+            ui.label("Connect a controller to calibrate its magnetometer.");
+            return;
+        };
+        ui.label(
+            "Slowly turn the controller through every orientation, tracing figure eights, \
+             away from metal and speakers. Needed for drift-free heading.",
+        );
+        ui.add_space(10.0);
+
+        let Some(calibrator) = &app.mag_calibrator else {
+            let Ok(mut settings) = app.settings.lock() else {
+                return;
+            };
+            let saved = settings
+                .device_calibration(address)
+                .and_then(|c| c.magnetometer.as_ref());
+            match saved {
+                Some(calibration) => ui.label(format!(
+                    "Calibrated: {:.1}% fit error, {:.0}% coverage, field strength {:.1}",
+                    calibration.quality.rms_error * 100.0,
+                    calibration.quality.coverage * 100.0,
+                    calibration.field_strength
+                )),
+                None => ui.label("Not calibrated"),
+            };
+            let calibrated = saved.is_some();
+
+            ui.horizontal(|ui| {
+                if ui.button("▶ Start Rotating").clicked() {
+                    app.mag_calibrator = Some(MagCalibrator::new());
+                }
+                if calibrated && ui.button("Clear").clicked() {
+                    settings.device_calibration_mut(address).magnetometer = None;
+                    let _ = settings.save();
+                }
+            });
+            return;
+        };
+
+        ui.label(format!("Samples Collected: {}", calibrator.sample_count()));
+        ui.add(egui::ProgressBar::new(calibrator.coverage()).text("Orientation Coverage"));
+        ui.add_space(15.0);
+
+        let ready = calibrator.sample_count() >= MIN_MAG_SAMPLES;
+        let (fit, cancel) = ui
+            .horizontal(|ui| {
+                (
+                    ui.add_enabled(ready, egui::Button::new("✅ Fit & Save"))
+                        .clicked(),
+                    ui.button("Cancel").clicked(),
+                )
+            })
+            .inner;
+        if cancel {
+            app.mag_calibrator = None;
+            return;
+        }
+        if !fit {
+            return;
+        }
+
+        app.status_message = Some(match calibrator.fit() {
+            Ok(calibration) => {
+                let quality = calibration.quality;
+                let summary = format!(
+                    "{:.1}% fit error, {:.0}% coverage",
+                    quality.rms_error * 100.0,
+                    quality.coverage * 100.0
+                );
+                if quality.is_good() {
+                    if let Ok(mut settings) = app.settings.lock() {
+                        settings.device_calibration_mut(address).magnetometer = Some(calibration);
+                        if let Err(e) = settings.save() {
+                            error!("Failed to save magnetometer calibration: {}", e);
+                        }
+                    }
+                    app.mag_calibrator = None;
+                    StatusMessage {
+                        message: format!("Magnetometer calibrated: {}", summary),
+                        severity: MessageSeverity::Success,
+                    }
+                } else {
+                    // Not stored: keep collecting so the next fit can do better
+                    StatusMessage {
+                        message: format!(
+                            "Fit too poor to use ({}); keep rotating and try again",
+                            summary
+                        ),
+                        severity: MessageSeverity::Warning,
+                    }
+                }
+            }
+            Err(e) => StatusMessage {
+                message: format!("Calibration failed, keep rotating: {}", e),
+                severity: MessageSeverity::Warning,
+            },
+        });
+    });
+}
+
+fn render_accel_calibration(app: &mut GearVRApp, ui: &mut egui::Ui) {
+    Components::brutalist_card(ui, "Accelerometer Calibration", |ui| {
+        let address = app
+            .last_connected_address
+            .filter(|_| app.connection_status == ConnectionStatus::Connected);
+        let Some(address) = address else {
+            ui.label("Connect a controller to calibrate its accelerometer.");
+            return;
+        };
+        ui.label(
+            "Rest the controller on a table on each of its six sides in turn. \
+             Each position is captured after about a second without moving.",
+        );
+        ui.add_space(10.0);
+
+        let Some(calibrator) = &app.accel_calibrator else {
+            let Ok(mut settings) = app.settings.lock() else {
+                return;
+            };
+            let saved = settings
+                .device_calibration(address)
+                .and_then(|c| c.accelerometer.as_ref());
+            match saved {
+                Some(calibration) => ui.label(format!(
+                    "Calibrated: gravity within {:.1}% of 1 g",
+                    calibration.gravity_error * 100.0
+                )),
+                None => ui.label("Not calibrated"),
+            };
+            let calibrated = saved.is_some();
+
+            ui.horizontal(|ui| {
+                if ui.button("▶ Start Six-Position Capture").clicked() {
+                    app.accel_calibrator = Some(AccelCalibrator::new());
+                }
+                if calibrated && ui.button("Clear").clicked() {
+                    settings.device_calibration_mut(address).accelerometer = None;
+                    let _ = settings.save();
+                }
+            });
+            return;
+        };
+
+        ui.horizontal_wrapped(|ui| {
+            for face in Face::ALL {
+                let mark = if calibrator.is_captured(face) {
+                    "✔"
+                } else {
+                    "○"
+                };
+                ui.label(format!("{} {}", mark, face));
+            }
+        });
+        ui.label(if calibrator.is_resting() {
+            "Resting, hold on..."
+        } else {
+            "Waiting for the controller to rest on a new side"
+        });
+        ui.add(
+            egui::ProgressBar::new(calibrator.captured_count() as f32 / 6.0)
+                .text("Positions Captured"),
+        );
+        ui.add_space(15.0);
+
+        let ready = calibrator.captured_count() == Face::ALL.len();
+        let (save, cancel) = ui
+            .horizontal(|ui| {
+                (
+                    ui.add_enabled(ready, egui::Button::new("✅ Save & Apply"))
+                        .clicked(),
+                    ui.button("Cancel").clicked(),
+                )
+            })
+            .inner;
+        if cancel {
+            app.accel_calibrator = None;
+            return;
+        }
+        if !save {
+            return;
+        }
+
+        let result = calibrator.fit();
+        app.accel_calibrator = None;
+        app.status_message = Some(match result {
+            Ok(calibration) => {
+                let message = format!(
+                    "Accelerometer calibrated: gravity within {:.1}% of 1 g",
+                    calibration.gravity_error * 100.0
+                );
+                if let Ok(mut settings) = app.settings.lock() {
+                    settings.device_calibration_mut(address).accelerometer = Some(calibration);
+                    let _ = settings.save();
+                }
+                StatusMessage {
+                    message,
+                    severity: MessageSeverity::Success,
+                }
+            }
+            Err(e) => StatusMessage {
+                message: format!("Accelerometer calibration failed, start over: {}", e),
+                severity: MessageSeverity::Warning,
+            },
+        });
+    });
+}
+
+fn render_axis_wizard(app: &mut GearVRApp, ui: &mut egui::Ui) {
+    Components::brutalist_card(ui, "Controller Orientation", |ui| {
+        if app.connection_status != ConnectionStatus::Connected {
+            ui.label("Connect a controller to detect how you hold it.");
+            return;
+        }
+        let Some(wizard) = &app.axis_wizard else {
+            ui.label(
+                "Detect which way is up on the touchpad and which way the air mouse turns. \
+                 Handedness and inversion are in Settings.",
+            );
+            if ui.button("▶ Start Orientation Wizard").clicked() {
+                if let Ok(settings) = app.settings.lock() {
+                    app.axis_wizard = Some(AxisWizard::new(&settings.get().axes));
+                }
+            }
+            return;
+        };
+
+        ui.label(match wizard.step() {
+            WizardStep::SwipeUp => "1. Hold the controller as you normally do and swipe up.",
+            WizardStep::TurnRight => "2. Now turn the controller to the right.",
+            WizardStep::Done => "Done.",
+        });
+        let result = wizard.result().cloned();
+        ui.add_space(15.0);
+        if ui.button("Cancel").clicked() {
+            app.axis_wizard = None;
+            return;
+        }
+        let Some(axes) = result else {
+            return;
+        };
+
+        app.axis_wizard = None;
+        let message = format!(
+            "Touchpad turned {:.0}°{}",
+            axes.touchpad_angle(),
+            if axes.invert_imu.x {
+                ", air mouse inverted horizontally"
+            } else {
+                ""
+            }
+        );
+        if let Ok(mut settings) = app.settings.lock() {
+            settings.get_mut().axes = axes;
+            let _ = settings.save();
+        }
+        app.status_message = Some(StatusMessage {
+            message,
+            severity: MessageSeverity::Success,
+        });
+    });
+}
+
+fn render_shape_training(app: &mut GearVRApp, ui: &mut egui::Ui) {
+    let Ok(mut settings) = app.settings.lock() else {
+        return;
+    };
+    let settings_mut = settings.get_mut();
+    let shapes = &mut settings_mut.shape_gestures;
+    let bindings = &mut settings_mut.input_bindings;
+    let profiles = &mut settings_mut.profiles;
+    let mut changed = false;
+
+    Components::brutalist_card(ui, "Shape Gestures", |ui| {
+        ui.label(
+            "Draw a shape on the touchpad to run its action. Record a few samples of \
+             each shape the way you draw it.",
+        );
+        ui.add_space(5.0);
+
+        changed |= ui
+            .checkbox(&mut shapes.enabled, "Recognize Drawn Shapes")
+            .changed();
+        ui.horizontal(|ui| {
+            ui.label("Minimum Score:");
+            changed |= ui
+                .add(egui::Slider::new(&mut shapes.min_score, 0.5..=0.99))
+                .changed();
+        });
+        match &app.last_shape_match {
+            Some(recognition) => {
+                let color = if shapes.accepts(recognition) {
+                    egui::Color32::from_rgb(0, 200, 100)
+                } else {
+                    egui::Color32::GRAY
+                };
+                ui.label(
+                    egui::RichText::new(format!(
+                        "Last stroke: {} (score {:.2})",
+                        recognition.name, recognition.score
+                    ))
+                    .color(color),
+                );
+            }
+            None => {
+                ui.label("Last stroke: -");
+            }
+        }
+
+        if app.shape_training.is_some() {
+            ui.horizontal(|ui| {
+                ui.label(
+                    egui::RichText::new("✏ Draw the shape on the touchpad now...")
+                        .color(egui::Color32::from_rgb(255, 200, 0)),
+                );
+                if ui.button("Cancel").clicked() {
+                    app.shape_training = None;
+                }
+            });
+        }
+
+        ui.separator();
+
+        let mut delete = None;
+        let mut unbind = None;
+        for shape in &mut shapes.shapes {
+            let event = InputEvent::Shape(shape.id);
+            ui.horizontal(|ui| {
+                changed |= ui
+                    .add(egui::TextEdit::singleline(&mut shape.name).desired_width(120.0))
+                    .changed();
+                ui.label(format!("{} samples", shape.templates.len()));
+
+                let recording = app.shape_training == Some(shape.id);
+                if ui.selectable_label(recording, "⏺ Record Sample").clicked() {
+                    app.shape_training = (!recording).then_some(shape.id);
+                }
+                if ui
+                    .add_enabled(!shape.templates.is_empty(), egui::Button::new("Clear"))
+                    .clicked()
+                {
+                    shape.templates.clear();
+                    changed = true;
+                }
+                if ui.button("🗑").on_hover_text("Delete shape").clicked() {
+                    delete = Some(shape.id);
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Action:");
+                match bindings
+                    .bindings
+                    .iter_mut()
+                    .find(|b| b.mode.is_none() && b.event == event)
+                {
+                    Some(binding) => {
+                        let mut action = match binding.actions.as_slice() {
+                            [single] => single.clone(),
+                            actions => Action::Sequence(actions.to_vec()),
+                        };
+                        let id = ui.id().with(("shape_action", shape.id));
+                        if Components::action_edit(ui, id, &mut action) {
+                            binding.actions = vec![action];
+                            changed = true;
+                        }
+                        if ui.small_button("✖").clicked() {
+                            unbind = Some(event);
+                        }
+                    }
+                    None => {
+                        if ui.button("+ Bind Action").clicked() {
+                            bindings.set(None, event, vec![Action::KeyPress(Key::Space.into())]);
+                            changed = true;
+                        }
+                    }
+                }
+            });
+            ui.add_space(5.0);
+        }
+
+        if let Some(event) = unbind {
+            bindings
+                .bindings
+                .retain(|b| b.mode.is_some() || b.event != event);
+            changed = true;
+        }
+        if let Some(id) = delete {
+            shapes.shapes.retain(|s| s.id != id);
+            let event = InputEvent::Shape(id);
+            bindings.bindings.retain(|b| b.event != event);
+            for profile in profiles.iter_mut() {
+                profile.bindings.bindings.retain(|b| b.event != event);
+            }
+            if app.shape_training == Some(id) {
+                app.shape_training = None;
+            }
+            changed = true;
+        }
+
+        if ui.button("+ New Shape").clicked() {
+            let name = format!("Shape {}", shapes.shapes.len() + 1);
+            app.shape_training = Some(shapes.add_shape(&name));
+            changed = true;
+        }
+    });
+
+    if changed {
+        let _ = settings.save();
+    }
 }
@@ -1,4 +1,4 @@
-use crate::domain::models::ConnectionStatus;
+use crate::domain::models::{ConnectionStatus, MessageSeverity, StatusMessage};
 use crate::presentation::app::GearVRApp;
 use crate::presentation::components::Components;
 use eframe::egui;
@@ -25,6 +25,10 @@ pub fn render(app: &mut GearVRApp, ui: &mut egui::Ui) {
 
     ui.add_space(10.0);
 
+    ui_connection_attempts(app, ui);
+
+    ui.add_space(10.0);
+
     if let Some(data) = &app.latest_controller_data {
         Components::brutalist_card(ui, "Raw Telemetry", |ui| {
             egui::Grid::new("debug_grid")
@@ -43,8 +47,46 @@ pub fn render(app: &mut GearVRApp, ui: &mut egui::Ui) {
                     ));
                     ui.end_row();
                     ui.label("Packets:");
-                    ui.label(format!("{}", data.timestamp));
+                    ui.label(format!("{:.3} s", data.timestamp));
+                    ui.end_row();
+                    ui.label("Temperature:");
+                    ui.label(data.temperature.map_or("-".to_string(), |t| t.to_string()));
                     ui.end_row();
+                    ui.label("Gyro Bias:");
+                    let bias = app.imu_preprocessor.gyro_bias().bias();
+                    ui.label(match bias.at(data.temperature) {
+                        Some(b) => format!(
+                            "{:.4}, {:.4}, {:.4} rad/s ({} temperatures learned)",
+                            b[0],
+                            b[1],
+                            b[2],
+                            bias.points.len()
+                        ),
+                        None => "Not learned yet; lay the controller down".to_string(),
+                    });
+                    ui.end_row();
+                    ui.label("Stillness:");
+                    ui.label(if app.imu_preprocessor.gyro_bias().is_still() {
+                        "Still"
+                    } else {
+                        "Moving"
+                    });
+                    ui.end_row();
+                    if let Some(orientation) = &app.orientation {
+                        let euler = orientation.euler;
+                        ui.label("Orientation:");
+                        ui.label(format!(
+                            "roll {:.1}°, pitch {:.1}°, yaw {:.1}°",
+                            euler.roll.to_degrees(),
+                            euler.pitch.to_degrees(),
+                            euler.yaw.to_degrees()
+                        ));
+                        ui.end_row();
+                        let q = orientation.quaternion;
+                        ui.label("Quaternion:");
+                        ui.label(format!("{:.3}, {:.3}, {:.3}, {:.3}", q.w, q.x, q.y, q.z));
+                        ui.end_row();
+                    }
                 });
         });
     }
@@ -62,3 +104,128 @@ pub fn render(app: &mut GearVRApp, ui: &mut egui::Ui) {
         });
     });
 }
+
+fn ui_connection_attempts(app: &mut GearVRApp, ui: &mut egui::Ui) {
+    Components::brutalist_card(ui, "Connection Attempts", |ui| {
+        if let Ok(mut settings) = app.settings.lock() {
+            let settings_mut = settings.get_mut();
+            ui.horizontal(|ui| {
+                ui.label("Keep Last:");
+                if ui
+                    .add(egui::Slider::new(
+                        &mut settings_mut.connection_history_size,
+                        1..=50,
+                    ))
+                    .changed()
+                {
+                    app.connection_history
+                        .set_capacity(settings_mut.connection_history_size);
+                }
+            });
+        }
+
+        if app.connection_history.is_empty() {
+            ui.label("No connection attempts yet.");
+            return;
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("📋 Copy Report").clicked() {
+                ui.ctx().copy_text(app.connection_history.to_report());
+            }
+            if ui.button("💾 Export JSON").clicked() {
+                app.status_message = Some(match export_connection_history(app) {
+                    Ok(path) => StatusMessage {
+                        message: format!("Connection report saved to {}", path.display()),
+                        severity: MessageSeverity::Success,
+                    },
+                    Err(e) => StatusMessage {
+                        message: format!("Failed to export report: {}", e),
+                        severity: MessageSeverity::Warning,
+                    },
+                });
+            }
+            if ui.button("Clear").clicked() {
+                app.connection_history.clear();
+            }
+        });
+
+        ui.add_space(5.0);
+
+        for (i, attempt) in app.connection_history.iter_newest_first().enumerate() {
+            let (verdict, color) = if attempt.success {
+                ("OK", egui::Color32::from_rgb(0, 200, 0))
+            } else {
+                ("FAILED", egui::Color32::from_rgb(255, 50, 50))
+            };
+
+            egui::CollapsingHeader::new(
+                egui::RichText::new(format!(
+                    "{:#X} - {} in {} ms",
+                    attempt.address, verdict, attempt.total_ms
+                ))
+                .color(color),
+            )
+            .id_salt(("connection_attempt", i, attempt.started_at_unix_ms))
+            .show(ui, |ui| {
+                if let Some(error) = &attempt.error {
+                    ui.label(format!("Error: {}", error));
+                }
+                if let Some(slowest) = attempt.slowest_step() {
+                    ui.label(format!(
+                        "Slowest step: {} ({} ms)",
+                        slowest.step.name(),
+                        slowest.duration_ms
+                    ));
+                }
+                if let Some(failed) = attempt.first_failure() {
+                    ui.label(format!("First failure: {}", failed.step.name()));
+                }
+
+                egui::Grid::new(("attempt_steps", i, attempt.started_at_unix_ms))
+                    .spacing([20.0, 4.0])
+                    .show(ui, |ui| {
+                        for step in &attempt.steps {
+                            ui.label(step.step.name());
+                            ui.label(format!("{} ms", step.duration_ms));
+                            if step.success {
+                                ui.label(
+                                    egui::RichText::new("ok")
+                                        .color(egui::Color32::from_rgb(0, 200, 0)),
+                                );
+                            } else {
+                                ui.label(
+                                    egui::RichText::new("FAIL")
+                                        .color(egui::Color32::from_rgb(255, 50, 50)),
+                                );
+                            }
+                            ui.label(step.detail.as_deref().unwrap_or(""));
+                            ui.end_row();
+                        }
+                    });
+            });
+        }
+    });
+}
+
+/// Write the connection history as JSON next to the log files
+fn export_connection_history(app: &GearVRApp) -> anyhow::Result<std::path::PathBuf> {
+    let log_dir = {
+        let settings = app
+            .settings
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Lock error"))?;
+        settings.get().log_settings.log_dir.clone()
+    };
+
+    let stamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let mut path = std::path::PathBuf::from(log_dir);
+    std::fs::create_dir_all(&path)?;
+    path.push(format!("connection_report_{}.json", stamp));
+    std::fs::write(&path, app.connection_history.to_json()?)?;
+    Ok(path)
+}
@@ -1,4 +1,6 @@
-use crate::domain::models::{BluetoothCommand, ConnectionStatus, MessageSeverity, StatusMessage};
+use crate::domain::models::{
+    AdapterState, BluetoothCommand, ConnectionStatus, MessageSeverity, StatusMessage,
+};
 use crate::presentation::app::GearVRApp;
 use crate::presentation::components::Components;
 use eframe::egui;
@@ -19,7 +21,17 @@ pub fn render(app: &mut GearVRApp, ui: &mut egui::Ui) {
 fn ui_connection_panel(app: &mut GearVRApp, ui: &mut egui::Ui) {
     Components::brutalist_card(ui, "Connection Control", |ui| {
         // Status Banner (Adaptive)
+        let adapter_usable = app.adapter_state.is_usable();
         let (status_text, bg_color, text_color) = match app.connection_status {
+            ConnectionStatus::Disconnected if !adapter_usable => (
+                if app.adapter_state == AdapterState::RadioOff {
+                    "BLUETOOTH OFF"
+                } else {
+                    "NO ADAPTER"
+                },
+                egui::Color32::from_rgb(255, 50, 50),
+                egui::Color32::WHITE,
+            ),
             ConnectionStatus::Connected => (
                 "CONNECTED",
                 egui::Color32::from_rgb(0, 200, 0),
@@ -58,7 +70,10 @@ fn ui_connection_panel(app: &mut GearVRApp, ui: &mut egui::Ui) {
                     let _ = app.bluetooth_tx.send(BluetoothCommand::Disconnect);
                 }
             } else {
-                if ui.button("Establish Connection").clicked() {
+                if ui
+                    .add_enabled(adapter_usable, egui::Button::new("Establish Connection"))
+                    .clicked()
+                {
                     if let Ok(address) =
                         u64::from_str_radix(&app.bluetooth_address_input.replace(":", ""), 16)
                     {
@@ -73,11 +88,18 @@ fn ui_connection_panel(app: &mut GearVRApp, ui: &mut egui::Ui) {
             if app.is_scanning {
                 if ui.button("Stop Scan").clicked() {
                     app.is_scanning = false;
+                    app.scan_paused = false;
                     let _ = app.bluetooth_tx.send(BluetoothCommand::StopScan);
                 }
                 ui.spinner();
             } else {
-                if ui.button("Scan for Gear VR").clicked() {
+                if ui
+                    .add_enabled(
+                        app.scan_adapter_state.is_usable(),
+                        egui::Button::new("Scan for Gear VR"),
+                    )
+                    .clicked()
+                {
                     app.is_scanning = true;
                     app.scanned_devices.clear();
                     let _ = app.bluetooth_tx.send(BluetoothCommand::StartScan);
@@ -85,16 +107,37 @@ fn ui_connection_panel(app: &mut GearVRApp, ui: &mut egui::Ui) {
             }
         });
 
+        if let Ok(mut settings) = app.settings.lock() {
+            ui.checkbox(
+                &mut settings.get_mut().scan_auto_connect,
+                "Auto-connect to strongest known controller",
+            );
+        }
+
         if !app.scanned_devices.is_empty() {
             ui.separator();
-            ui.label("Nearby Controllers:");
+            ui.label("Nearby Controllers (nearest first):");
             egui::ScrollArea::vertical()
                 .id_salt("scan_results")
                 .max_height(120.0)
                 .show(ui, |ui| {
-                    for device in &app.scanned_devices {
+                    for tracked in app.scanned_devices.iter() {
+                        let device = &tracked.device;
                         ui.horizontal(|ui| {
-                            ui.label(format!("{} ({} dBm)", device.name, device.signal_strength));
+                            ui.label(format!(
+                                "{} ({:.0} dBm)",
+                                device.name, tracked.smoothed_rssi
+                            ));
+                            if let Some(loss) = tracked.path_loss() {
+                                ui.label(
+                                    egui::RichText::new(format!("path loss {:.0} dB", loss))
+                                        .small()
+                                        .italics(),
+                                );
+                            }
+                            if device.advertisement.is_discoverable() {
+                                ui.label(egui::RichText::new("pairing").small().strong());
+                            }
                             if ui.button("Pick").clicked() {
                                 app.bluetooth_address_input = format!("{:X}", device.address);
                             }
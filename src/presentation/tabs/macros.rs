@@ -0,0 +1,199 @@
+use crate::domain::actions::Action;
+use crate::domain::input_mapper::InputEvent;
+use crate::presentation::app::GearVRApp;
+use crate::presentation::components::Components;
+use eframe::egui;
+
+/// Edits that need the settings lock released first
+enum MacroCommand {
+    Play(String),
+    Delete(String),
+}
+
+pub fn render(app: &mut GearVRApp, ui: &mut egui::Ui) {
+    Components::heading(ui, "Macros");
+    ui.add_space(20.0);
+
+    ui_recorder(app, ui);
+
+    ui.add_space(10.0);
+
+    if let Some(command) = ui_macro_list(app, ui) {
+        match command {
+            MacroCommand::Play(name) => app.play_macro(&name),
+            MacroCommand::Delete(name) => {
+                if let Ok(mut settings) = app.settings.lock() {
+                    settings.delete_macro(&name);
+                    let _ = settings.save();
+                }
+            }
+        }
+    }
+}
+
+fn ui_recorder(app: &mut GearVRApp, ui: &mut egui::Ui) {
+    Components::brutalist_card(ui, "Recorder", |ui| {
+        let recording = app.macro_recorder.is_recording();
+
+        ui.horizontal(|ui| {
+            ui.label("Name:");
+            ui.add(
+                egui::TextEdit::singleline(&mut app.macro_name_input)
+                    .hint_text("Macro name (optional)"),
+            );
+        });
+
+        ui.horizontal(|ui| {
+            let label = if recording {
+                "⏹ Stop Recording"
+            } else {
+                "⏺ Record"
+            };
+            if ui.button(label).clicked() {
+                app.toggle_macro_recording();
+            }
+
+            if ui
+                .add_enabled(
+                    app.action_executor.is_busy(),
+                    egui::Button::new("✖ Stop Playback"),
+                )
+                .clicked()
+            {
+                app.stop_macro();
+            }
+
+            if recording {
+                ui.label(
+                    egui::RichText::new("● RECORDING")
+                        .color(egui::Color32::from_rgb(220, 50, 50))
+                        .strong(),
+                );
+            }
+        });
+
+        ui.label(
+            egui::RichText::new(
+                "Records the keys, clicks and scrolls sent by controller bindings. \
+                 Bind 'record' to a controller event to record without the mouse.",
+            )
+            .italics()
+            .size(12.0),
+        );
+    });
+}
+
+fn ui_macro_list(app: &mut GearVRApp, ui: &mut egui::Ui) -> Option<MacroCommand> {
+    let mode = app.current_control_mode;
+    let mut command = None;
+
+    let Ok(mut settings) = app.settings.lock() else {
+        return None;
+    };
+    let settings_mut = settings.get_mut();
+    let mut changed = false;
+    let mut renamed = None;
+
+    Components::brutalist_card(ui, "Saved Macros", |ui| {
+        if settings_mut.macros.is_empty() {
+            ui.label("No macros recorded yet.");
+        }
+
+        for (index, item) in settings_mut.macros.iter_mut().enumerate() {
+            let header = format!(
+                "{}  ({} steps, {:.1} s)",
+                item.name,
+                item.steps.len(),
+                item.duration_ms() as f64 / 1000.0
+            );
+            egui::CollapsingHeader::new(header)
+                .id_salt(("macro", index))
+                .show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Name:");
+                        let old_name = item.name.clone();
+                        if ui.text_edit_singleline(&mut item.name).changed() {
+                            renamed = Some((old_name, item.name.clone()));
+                            changed = true;
+                        }
+                    });
+
+                    ui.add_space(5.0);
+
+                    let mut remove_step = None;
+                    for (step_index, step) in item.steps.iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("{:>2}.", step_index + 1));
+                            let id = ui.id().with(("macro_step", index, step_index));
+                            changed |= Components::action_edit(ui, id, step);
+                            if ui.small_button("✖").clicked() {
+                                remove_step = Some(step_index);
+                            }
+                        });
+                    }
+                    if let Some(step_index) = remove_step {
+                        item.steps.remove(step_index);
+                        changed = true;
+                    }
+
+                    ui.horizontal(|ui| {
+                        if ui.button("+ Add Step").clicked() {
+                            item.steps.push(Action::Delay(100));
+                            changed = true;
+                        }
+                        if ui.button("▶ Play").clicked() {
+                            command = Some(MacroCommand::Play(item.name.clone()));
+                        }
+                        if ui.button("🗑 Delete").clicked() {
+                            command = Some(MacroCommand::Delete(item.name.clone()));
+                        }
+                    });
+
+                    ui.add_space(5.0);
+
+                    let play = vec![Action::PlayMacro(item.name.clone())];
+                    let table = &mut settings_mut.input_bindings;
+                    ui.horizontal(|ui| {
+                        ui.label(format!("Bind in {} mode:", mode.name()));
+                        egui::ComboBox::from_id_salt(("macro_bind", index))
+                            .selected_text("Choose event")
+                            .show_ui(ui, |ui| {
+                                for event in InputEvent::bindable() {
+                                    if ui.selectable_label(false, event.to_string()).clicked() {
+                                        table.set(Some(mode), event, play.clone());
+                                        changed = true;
+                                    }
+                                }
+                            });
+                    });
+
+                    let mut unbind = None;
+                    for (binding_index, binding) in table.bindings.iter().enumerate() {
+                        if binding.actions != play {
+                            continue;
+                        }
+                        ui.horizontal(|ui| {
+                            let scope = binding.mode.map_or("All modes", |m| m.name());
+                            ui.label(format!("• {}: {}", scope, binding.event));
+                            if ui.small_button("✖").clicked() {
+                                unbind = Some(binding_index);
+                            }
+                        });
+                    }
+                    if let Some(binding_index) = unbind {
+                        table.bindings.remove(binding_index);
+                        changed = true;
+                    }
+                });
+        }
+    });
+
+    // Keep bindings and other macros pointing at the renamed macro
+    if let Some((old, new)) = renamed {
+        settings.rename_macro(&old, &new);
+    }
+    if changed {
+        let _ = settings.save();
+    }
+    command
+}
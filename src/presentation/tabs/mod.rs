@@ -1,4 +1,6 @@
 pub mod calibration;
 pub mod debug;
 pub mod home;
+pub mod macros;
+pub mod profiles;
 pub mod settings;
@@ -0,0 +1,244 @@
+use crate::domain::actions::{Action, Key};
+use crate::domain::input_mapper::{Binding, ButtonTrigger, ControllerButton, InputEvent};
+use crate::domain::models::ControlMode;
+use crate::domain::profiles::{Profile, WindowMatcher};
+use crate::presentation::app::GearVRApp;
+use crate::presentation::components::Components;
+use eframe::egui;
+
+/// Modes a profile can select (Settings only opens the settings tab)
+const PROFILE_MODES: [ControlMode; 5] = [
+    ControlMode::Mouse,
+    ControlMode::Touchpad,
+    ControlMode::Pointer,
+    ControlMode::Presentation,
+    ControlMode::Media,
+];
+
+pub fn render(app: &mut GearVRApp, ui: &mut egui::Ui) {
+    Components::heading(ui, "Application Profiles");
+    ui.add_space(20.0);
+
+    let Ok(mut settings) = app.settings.lock() else {
+        return;
+    };
+    let profiles = &mut settings.get_mut().profiles;
+    let mut changed = false;
+
+    Components::brutalist_card(ui, "Foreground Window", |ui| {
+        let active = app.profile_switcher.active().unwrap_or("Default");
+        ui.label(format!("Active profile: {}", active));
+
+        match app.profile_switcher.last_window() {
+            Some(window) => {
+                ui.label(format!("Application: {}", window.executable));
+                ui.label(format!("Title: {}", window.title));
+                if ui.button("+ New Profile for This Application").clicked() {
+                    let name = window
+                        .executable
+                        .rsplit_once('.')
+                        .map_or(window.executable.as_str(), |(stem, _)| stem);
+                    profiles.push(Profile::new(
+                        name,
+                        WindowMatcher::Executable(window.executable.clone()),
+                    ));
+                    changed = true;
+                }
+            }
+            None => {
+                ui.label("Switch to another application to detect it.");
+            }
+        }
+    });
+
+    ui.add_space(10.0);
+
+    Components::brutalist_card(ui, "Profiles", |ui| {
+        ui.label(
+            egui::RichText::new(
+                "The first profile matching the foreground window is used. Patterns are \
+                 case-insensitive and accept * and ? wildcards.",
+            )
+            .italics()
+            .size(12.0),
+        );
+        ui.add_space(5.0);
+
+        let mut delete = None;
+        let mut move_up = None;
+        for (index, profile) in profiles.iter_mut().enumerate() {
+            egui::CollapsingHeader::new(format!(
+                "{}  ({})",
+                profile.name,
+                profile.matcher.pattern()
+            ))
+            .id_salt(("profile", index))
+            .show(ui, |ui| {
+                changed |= ui_profile(ui, index, profile);
+
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(index > 0, egui::Button::new("⬆ Higher Priority"))
+                        .clicked()
+                    {
+                        move_up = Some(index);
+                    }
+                    if ui.button("🗑 Delete Profile").clicked() {
+                        delete = Some(index);
+                    }
+                });
+            });
+        }
+
+        if let Some(index) = move_up {
+            profiles.swap(index, index - 1);
+            changed = true;
+        }
+        if let Some(index) = delete {
+            profiles.remove(index);
+            changed = true;
+        }
+
+        if ui.button("+ New Profile").clicked() {
+            profiles.push(Profile::new(
+                "New Profile",
+                WindowMatcher::Title("*".to_string()),
+            ));
+            changed = true;
+        }
+    });
+
+    if changed {
+        let _ = settings.save();
+    }
+}
+
+/// Editor for one profile, returns true if anything changed
+fn ui_profile(ui: &mut egui::Ui, index: usize, profile: &mut Profile) -> bool {
+    let mut changed = false;
+
+    egui::Grid::new(("profile_grid", index))
+        .spacing([10.0, 6.0])
+        .show(ui, |ui| {
+            ui.label("Name:");
+            changed |= ui.text_edit_singleline(&mut profile.name).changed();
+            ui.end_row();
+
+            ui.label("Match:");
+            ui.horizontal(|ui| {
+                let pattern = profile.matcher.pattern().to_string();
+                let is_title = matches!(profile.matcher, WindowMatcher::Title(_));
+                egui::ComboBox::from_id_salt(("profile_match", index))
+                    .selected_text(if is_title {
+                        "Window title"
+                    } else {
+                        "Executable"
+                    })
+                    .show_ui(ui, |ui| {
+                        if ui.selectable_label(!is_title, "Executable").clicked() {
+                            profile.matcher = WindowMatcher::Executable(pattern.clone());
+                            changed = true;
+                        }
+                        if ui.selectable_label(is_title, "Window title").clicked() {
+                            profile.matcher = WindowMatcher::Title(pattern.clone());
+                            changed = true;
+                        }
+                    });
+                let (WindowMatcher::Executable(pattern) | WindowMatcher::Title(pattern)) =
+                    &mut profile.matcher;
+                changed |= ui.text_edit_singleline(pattern).changed();
+            });
+            ui.end_row();
+
+            ui.label("Mode:");
+            let selected = profile.mode.map_or("Keep current", |m| m.name());
+            egui::ComboBox::from_id_salt(("profile_mode", index))
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    changed |= ui
+                        .selectable_value(&mut profile.mode, None, "Keep current")
+                        .changed();
+                    for mode in PROFILE_MODES {
+                        changed |= ui
+                            .selectable_value(&mut profile.mode, Some(mode), mode.name())
+                            .changed();
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Sensitivity:");
+            ui.horizontal(|ui| {
+                let mut overridden = profile.mouse_sensitivity.is_some();
+                if ui.checkbox(&mut overridden, "Override").changed() {
+                    profile.mouse_sensitivity = overridden.then_some(2.0);
+                    changed = true;
+                }
+                if let Some(sensitivity) = &mut profile.mouse_sensitivity {
+                    changed |= ui.add(egui::Slider::new(sensitivity, 0.1..=10.0)).changed();
+                }
+            });
+            ui.end_row();
+        });
+
+    ui.add_space(5.0);
+    Components::sub_heading(ui, "Bindings");
+
+    let mut remove = None;
+    for (binding_index, binding) in profile.bindings.bindings.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            let mode_text = binding.mode.map_or("All modes", |m| m.name());
+            egui::ComboBox::from_id_salt(("binding_mode", index, binding_index))
+                .selected_text(mode_text)
+                .show_ui(ui, |ui| {
+                    changed |= ui
+                        .selectable_value(&mut binding.mode, None, "All modes")
+                        .changed();
+                    for mode in PROFILE_MODES {
+                        changed |= ui
+                            .selectable_value(&mut binding.mode, Some(mode), mode.name())
+                            .changed();
+                    }
+                });
+
+            egui::ComboBox::from_id_salt(("binding_event", index, binding_index))
+                .selected_text(binding.event.to_string())
+                .show_ui(ui, |ui| {
+                    for event in InputEvent::bindable() {
+                        changed |= ui
+                            .selectable_value(&mut binding.event, event, event.to_string())
+                            .changed();
+                    }
+                });
+
+            // Several actions are edited as one sequence
+            let mut action = match binding.actions.as_slice() {
+                [single] => single.clone(),
+                actions => Action::Sequence(actions.to_vec()),
+            };
+            let id = ui.id().with(("binding_action", index, binding_index));
+            if Components::action_edit(ui, id, &mut action) {
+                binding.actions = vec![action];
+                changed = true;
+            }
+
+            if ui.small_button("✖").clicked() {
+                remove = Some(binding_index);
+            }
+        });
+    }
+    if let Some(binding_index) = remove {
+        profile.bindings.bindings.remove(binding_index);
+        changed = true;
+    }
+
+    if ui.button("+ Add Binding").clicked() {
+        profile.bindings.bindings.push(Binding::new(
+            None,
+            InputEvent::Button(ControllerButton::Trigger, ButtonTrigger::Tap),
+            vec![Action::KeyPress(Key::Space.into())],
+        ));
+        changed = true;
+    }
+
+    changed
+}
@@ -1,3 +1,9 @@
+use crate::domain::axes::{AxisInvert, Hand};
+use crate::domain::filters::{FilterKind, FilterSettings, FilterSource};
+use crate::domain::models::BluetoothCommand;
+use crate::domain::orientation::FusionAlgorithm;
+use crate::domain::scroll::ScrollAxes;
+use crate::domain::transfer::{PointerTransfer, TransferCurve, MAX_CURVE_SPEED};
 use crate::presentation::app::GearVRApp;
 use crate::presentation::components::Components;
 use eframe::egui;
@@ -6,6 +12,7 @@ pub fn render(app: &mut GearVRApp, ui: &mut egui::Ui) {
     Components::heading(ui, "Global Settings");
     ui.add_space(20.0);
 
+    let mut recenter = false;
     if let Ok(mut settings) = app.settings.lock() {
         let settings_mut = settings.get_mut();
 
@@ -20,6 +27,276 @@ pub fn render(app: &mut GearVRApp, ui: &mut egui::Ui) {
             ui.checkbox(&mut settings_mut.enable_touchpad, "Enable Trackpad Input");
             ui.checkbox(&mut settings_mut.enable_buttons, "Enable Button Mapping");
             ui.checkbox(&mut settings_mut.enable_gestures, "Enable Gesture Commands");
+            ui.checkbox(
+                &mut settings_mut.media_session_enabled,
+                "Control Media Players Directly (Media mode)",
+            )
+            .on_hover_text("Uses the system media session instead of media keys");
+
+            ui.collapsing("Button Timing", |ui| {
+                let timings = &mut settings_mut.button_timings;
+                egui::Grid::new("button_timings")
+                    .spacing([10.0, 6.0])
+                    .show(ui, |ui| {
+                        for (label, value, range) in [
+                            ("Debounce:", &mut timings.debounce_ms, 0..=200),
+                            ("Long Press:", &mut timings.long_press_ms, 100..=2000),
+                            ("Double Tap Window:", &mut timings.double_tap_ms, 100..=1000),
+                            ("Repeat Delay:", &mut timings.repeat_delay_ms, 0..=1000),
+                            (
+                                "Repeat Interval:",
+                                &mut timings.repeat_interval_ms,
+                                20..=500,
+                            ),
+                            ("Chord Window:", &mut timings.chord_window_ms, 50..=500),
+                        ] {
+                            ui.label(label);
+                            ui.add(egui::Slider::new(value, range).suffix(" ms"));
+                            ui.end_row();
+                        }
+                    });
+            });
+
+            ui.collapsing("Scroll Ring", |ui| {
+                let ring = &mut settings_mut.scroll_ring;
+                ui.checkbox(&mut ring.enabled, "Circle the touchpad to scroll");
+                egui::Grid::new("scroll_ring")
+                    .spacing([10.0, 6.0])
+                    .show(ui, |ui| {
+                        ui.label("Degrees per Step:");
+                        ui.add(
+                            egui::Slider::new(&mut ring.degrees_per_step, 5.0..=90.0).suffix("°"),
+                        );
+                        ui.end_row();
+                        ui.label("Ring Inner Radius:");
+                        ui.add_enabled(
+                            ring.edge_ring_only,
+                            egui::Slider::new(&mut ring.ring_radius, 0.3..=0.95),
+                        );
+                        ui.end_row();
+                    });
+                ui.checkbox(&mut ring.edge_ring_only, "Only on the outer ring");
+                ui.checkbox(&mut ring.reverse, "Reverse direction");
+            });
+
+            ui.collapsing("Scrolling", |ui| {
+                let scrolling = &mut settings_mut.scrolling;
+                ui.label("Touchpad scrolling in Air Mouse mode.");
+                ui.horizontal(|ui| {
+                    ui.label("Direction:");
+                    ui.selectable_value(&mut scrolling.axes, ScrollAxes::Vertical, "Vertical");
+                    ui.selectable_value(&mut scrolling.axes, ScrollAxes::Horizontal, "Horizontal");
+                    ui.selectable_value(&mut scrolling.axes, ScrollAxes::Both, "Both");
+                    ui.selectable_value(&mut scrolling.axes, ScrollAxes::Locked, "Axis Locked")
+                        .on_hover_text("Follow whichever axis the finger moves along first");
+                });
+                egui::Grid::new("scrolling")
+                    .spacing([10.0, 6.0])
+                    .show(ui, |ui| {
+                        ui.label("Speed:");
+                        ui.add(
+                            egui::Slider::new(&mut scrolling.speed, 0.5..=20.0)
+                                .logarithmic(true)
+                                .text("notches per half pad"),
+                        );
+                        ui.end_row();
+                        ui.label("Friction:");
+                        ui.add_enabled(
+                            scrolling.momentum,
+                            egui::Slider::new(&mut scrolling.friction, 0.5..=15.0),
+                        )
+                        .on_hover_text("Higher stops a flick sooner");
+                        ui.end_row();
+                    });
+                ui.checkbox(&mut scrolling.momentum, "Keep scrolling after a flick");
+            });
+
+            ui.collapsing("Gesture Recognition", |ui| {
+                let gestures = &mut settings_mut.gestures;
+                egui::Grid::new("gesture_settings")
+                    .spacing([10.0, 6.0])
+                    .show(ui, |ui| {
+                        ui.label("Min Swipe Distance:");
+                        ui.add(egui::Slider::new(
+                            &mut gestures.min_swipe_distance,
+                            0.05..=1.0,
+                        ));
+                        ui.end_row();
+                        ui.label("Direction Tolerance:")
+                            .on_hover_text("45° disables the diagonals");
+                        ui.add(
+                            egui::Slider::new(&mut gestures.direction_tolerance, 10.0..=45.0)
+                                .suffix("°"),
+                        );
+                        ui.end_row();
+                        ui.label("Flick Speed:");
+                        ui.add(
+                            egui::Slider::new(&mut gestures.flick_velocity, 1.0..=20.0)
+                                .suffix(" /s"),
+                        );
+                        ui.end_row();
+                        ui.label("Speed Samples:");
+                        ui.add(egui::Slider::new(&mut gestures.sample_count, 2..=20));
+                        ui.end_row();
+                        ui.label("Edge Radius:");
+                        ui.add(egui::Slider::new(&mut gestures.edge_radius, 0.5..=0.95));
+                        ui.end_row();
+                        ui.label("Tap Distance:");
+                        ui.add(egui::Slider::new(&mut gestures.tap_distance, 0.02..=0.3));
+                        ui.end_row();
+                        for (label, value, range) in [
+                            ("Max Tap Duration:", &mut gestures.tap_max_ms, 50..=1000),
+                            (
+                                "Double Tap Window:",
+                                &mut gestures.double_tap_ms,
+                                100..=1000,
+                            ),
+                            ("Hold:", &mut gestures.hold_ms, 200..=2000),
+                        ] {
+                            ui.label(label);
+                            ui.add(egui::Slider::new(value, range).suffix(" ms"));
+                            ui.end_row();
+                        }
+                    });
+            });
+
+            ui.collapsing("Motion Gestures", |ui| {
+                let motion = &mut settings_mut.motion_gestures;
+                ui.checkbox(&mut motion.enabled, "Flick, twist, shake and knock");
+                egui::Grid::new("motion_settings")
+                    .spacing([10.0, 6.0])
+                    .show(ui, |ui| {
+                        for (label, value, range) in [
+                            ("Flick Speed:", &mut motion.flick_rate, 2.0..=20.0),
+                            ("Twist Speed:", &mut motion.twist_rate, 2.0..=20.0),
+                        ] {
+                            ui.label(label);
+                            ui.add(egui::Slider::new(value, range).suffix(" rad/s"));
+                            ui.end_row();
+                        }
+                        for (label, value, range) in [
+                            ("Shake Strength:", &mut motion.shake_accel, 0.5..=4.0),
+                            ("Knock Strength:", &mut motion.knock_accel, 0.3..=4.0),
+                        ] {
+                            ui.label(label);
+                            ui.add(egui::Slider::new(value, range).suffix(" g"));
+                            ui.end_row();
+                        }
+                        ui.label("Shake Reversals:");
+                        ui.add(egui::Slider::new(&mut motion.shake_reversals, 2..=10));
+                        ui.end_row();
+                        for (label, value, range) in [
+                            ("Max Flick Duration:", &mut motion.max_flick_ms, 100..=1000),
+                            ("Flick Cooldown:", &mut motion.cooldown_ms, 0..=2000),
+                            ("Shake Window:", &mut motion.shake_window_ms, 200..=2000),
+                            ("Shake Cooldown:", &mut motion.shake_cooldown_ms, 0..=5000),
+                            ("Max Knock Duration:", &mut motion.knock_max_ms, 10..=200),
+                        ] {
+                            ui.label(label);
+                            ui.add(egui::Slider::new(value, range).suffix(" ms"));
+                            ui.end_row();
+                        }
+                    });
+            });
+
+            ui.collapsing("Orientation Fusion", |ui| {
+                let fusion = &mut settings_mut.fusion;
+                ui.horizontal(|ui| {
+                    ui.label("Filter:");
+                    for algorithm in [FusionAlgorithm::Madgwick, FusionAlgorithm::Mahony] {
+                        ui.selectable_value(
+                            &mut fusion.algorithm,
+                            algorithm,
+                            format!("{:?}", algorithm),
+                        );
+                    }
+                });
+                egui::Grid::new("fusion_settings")
+                    .spacing([10.0, 6.0])
+                    .show(ui, |ui| match fusion.algorithm {
+                        FusionAlgorithm::Madgwick => {
+                            ui.label("Gain (beta):");
+                            ui.add(egui::Slider::new(&mut fusion.madgwick_beta, 0.01..=1.0));
+                            ui.end_row();
+                        }
+                        FusionAlgorithm::Mahony => {
+                            ui.label("Proportional Gain:");
+                            ui.add(egui::Slider::new(&mut fusion.mahony_kp, 0.1..=10.0));
+                            ui.end_row();
+                            ui.label("Integral Gain:");
+                            ui.add(egui::Slider::new(&mut fusion.mahony_ki, 0.0..=1.0));
+                            ui.end_row();
+                        }
+                    });
+                ui.checkbox(
+                    &mut fusion.use_magnetometer,
+                    "Correct heading with the magnetometer",
+                )
+                .on_hover_text("Only used once the magnetometer is calibrated (Calibration tab)");
+                ui.checkbox(
+                    &mut settings_mut.gyro_bias.enabled,
+                    "Learn gyro drift while the controller lies still",
+                );
+            });
+
+            ui.collapsing("Laser Pointer", |ui| {
+                let pointer = &mut settings_mut.pointer;
+                ui.horizontal(|ui| {
+                    ui.label("Field of View:");
+                    ui.add(egui::Slider::new(&mut pointer.fov_degrees, 10.0..=120.0).suffix("°"))
+                        .on_hover_text("How far to turn the controller to sweep across the screen");
+                });
+                ui.checkbox(&mut pointer.all_monitors, "Span all monitors");
+                if ui
+                    .button("Recenter")
+                    .on_hover_text("Or long-press Home while pointing at the screen center")
+                    .clicked()
+                {
+                    recenter = true;
+                }
+            });
+
+            ui.collapsing("Controller Orientation", |ui| {
+                let axes = &mut settings_mut.axes;
+                ui.horizontal(|ui| {
+                    ui.label("Hand:");
+                    ui.selectable_value(&mut axes.hand, Hand::Right, "Right");
+                    ui.selectable_value(&mut axes.hand, Hand::Left, "Left");
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Touchpad Rotation:");
+                    for turns in 0..4u8 {
+                        ui.selectable_value(
+                            &mut axes.touchpad_quarter_turns,
+                            turns,
+                            format!("{}°", turns as u32 * 90),
+                        );
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Fine Angle:");
+                    ui.add(
+                        egui::Slider::new(&mut axes.touchpad_fine_angle, -45.0..=45.0).suffix("°"),
+                    )
+                    .on_hover_text("Slant of the thumb; mirrored for the left hand");
+                });
+                egui::Grid::new("axis_invert_grid").show(ui, |ui| {
+                    ui.label("Invert");
+                    ui.label("X");
+                    ui.label("Y");
+                    ui.end_row();
+                    invert_row(ui, "Touchpad", &mut axes.invert_touchpad);
+                    invert_row(ui, "Scroll", &mut axes.invert_scroll);
+                    invert_row(ui, "Air Mouse", &mut axes.invert_imu);
+                });
+                ui.checkbox(&mut axes.natural_scrolling, "Natural scrolling")
+                    .on_hover_text("Content follows the finger");
+                ui.label(
+                    egui::RichText::new("The Calibration tab can detect the rotation for you.")
+                        .italics()
+                        .size(12.0),
+                );
+            });
 
             ui.separator();
             Components::sub_heading(ui, "Precision Processing");
@@ -29,44 +306,115 @@ pub fn render(app: &mut GearVRApp, ui: &mut egui::Ui) {
                 ui.add(egui::Slider::new(&mut settings_mut.dead_zone, 0.0..=0.5));
             });
 
-            ui.checkbox(&mut settings_mut.enable_smoothing, "Motion Smoothing");
-            if settings_mut.enable_smoothing {
-                ui.indent("smoothing_indent", |ui| {
-                    ui.horizontal(|ui| {
-                        ui.label("Sample Window:");
-                        ui.add(egui::Slider::new(
-                            &mut settings_mut.smoothing_factor,
-                            1..=20,
-                        ));
-                    });
-                });
-            }
+            ui.collapsing("Smoothing", |ui| {
+                ui.label("Touchpad:");
+                filter_controls(ui, "touchpad_filter", &mut settings_mut.touchpad_filter);
+                ui.label("Air Mouse:");
+                filter_controls(ui, "imu_filter", &mut settings_mut.imu_filter);
 
-            ui.checkbox(
-                &mut settings_mut.enable_acceleration,
-                "Pointer Acceleration",
-            );
-            if settings_mut.enable_acceleration {
-                ui.indent("accel_indent", |ui| {
-                    ui.horizontal(|ui| {
-                        ui.label("Power Curve:");
-                        ui.add(egui::Slider::new(
-                            &mut settings_mut.acceleration_power,
-                            1.0..=5.0,
-                        ));
-                    });
+                ui.add_space(6.0);
+                let preview = &mut app.filter_preview;
+                ui.horizontal(|ui| {
+                    ui.label("Preview:");
+                    let mut source = preview.source;
+                    ui.selectable_value(&mut source, FilterSource::Touchpad, "Touchpad");
+                    ui.selectable_value(&mut source, FilterSource::Imu, "Air Mouse");
+                    preview.set_source(source);
                 });
-            }
+                let (raw, filtered): (Vec<f64>, Vec<f64>) = preview.history().unzip();
+                let raw_color = ui.visuals().weak_text_color();
+                let filtered_color = ui.visuals().selection.bg_fill;
+                Components::signal_plot(ui, &[(raw, raw_color), (filtered, filtered_color)], 100.0);
+                ui.label(
+                    egui::RichText::new(match preview.source {
+                        FilterSource::Touchpad => "Slide a finger left and right on the touchpad",
+                        FilterSource::Imu => "Turn the controller left and right",
+                    })
+                    .italics()
+                    .size(12.0),
+                );
+            });
+
+            ui.collapsing("Pointer Curves", |ui| {
+                ui.label("Touchpad:");
+                transfer_controls(ui, "touchpad_transfer", &mut settings_mut.touchpad_transfer);
+                ui.label("Air Mouse:");
+                transfer_controls(ui, "imu_transfer", &mut settings_mut.imu_transfer);
+            });
         });
 
         ui.add_space(10.0);
 
         Components::brutalist_card(ui, "Bluetooth Protocol", |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Bluetooth Adapter:");
+                let selected_name = match &settings_mut.bluetooth_adapter_id {
+                    Some(id) => app
+                        .adapters
+                        .iter()
+                        .find(|a| &a.id == id)
+                        .map(|a| a.name.clone())
+                        .unwrap_or_else(|| "Unavailable adapter".to_string()),
+                    None => "System default".to_string(),
+                };
+                let previous = settings_mut.bluetooth_adapter_id.clone();
+                egui::ComboBox::from_id_salt("bt_adapter")
+                    .selected_text(selected_name)
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(
+                            &mut settings_mut.bluetooth_adapter_id,
+                            None,
+                            "System default",
+                        );
+                        for adapter in &app.adapters {
+                            let label = if adapter.supports_le {
+                                adapter.name.clone()
+                            } else {
+                                format!("{} (no LE)", adapter.name)
+                            };
+                            ui.selectable_value(
+                                &mut settings_mut.bluetooth_adapter_id,
+                                Some(adapter.id.clone()),
+                                label,
+                            );
+                        }
+                    });
+                if settings_mut.bluetooth_adapter_id != previous {
+                    let _ = app.bluetooth_tx.send(BluetoothCommand::RefreshAdapters);
+                }
+                if ui.button("⟳").on_hover_text("Refresh adapters").clicked() {
+                    let _ = app.bluetooth_tx.send(BluetoothCommand::RefreshAdapters);
+                }
+            });
+            ui.label(
+                egui::RichText::new(format!(
+                    "Radio state: {:?} (default adapter, used for scanning: {:?})",
+                    app.adapter_state, app.scan_adapter_state
+                ))
+                .small()
+                .italics(),
+            );
+            ui.label(
+                egui::RichText::new(
+                    "Connections go through this adapter; Windows always scans with the default one.",
+                )
+                .small()
+                .italics(),
+            );
+
             ui.checkbox(
                 &mut settings_mut.debug_show_all_devices,
                 "Verbose Device Scanning (Debug mode)",
             );
 
+            ui.horizontal(|ui| {
+                ui.label("Forget Unseen Devices After:");
+                ui.add(
+                    egui::Slider::new(&mut settings_mut.scan_device_timeout_secs, 2..=60)
+                        .suffix(" s"),
+                );
+            });
+
             ui.collapsing("Override Service UUIDs", |ui| {
                 ui.label(
                     egui::RichText::new("⚠️ Warning: Altering these may break device discovery.")
@@ -142,4 +490,202 @@ pub fn render(app: &mut GearVRApp, ui: &mut egui::Ui) {
             }
         });
     }
+
+    if recenter {
+        app.recenter_pointer();
+    }
+}
+
+/// Filter kind and its parameters for one input source
+fn filter_controls(ui: &mut egui::Ui, id: &str, filter: &mut FilterSettings) {
+    ui.indent(id, |ui| {
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut filter.kind, FilterKind::Off, "Off");
+            ui.selectable_value(
+                &mut filter.kind,
+                FilterKind::MovingAverage,
+                "Moving Average",
+            );
+            ui.selectable_value(&mut filter.kind, FilterKind::OneEuro, "One Euro");
+        });
+        match filter.kind {
+            FilterKind::Off => {}
+            FilterKind::MovingAverage => {
+                ui.add(egui::Slider::new(&mut filter.window, 1..=20).text("Sample Window"));
+            }
+            FilterKind::OneEuro => {
+                ui.add(
+                    egui::Slider::new(&mut filter.min_cutoff, 0.05..=10.0)
+                        .logarithmic(true)
+                        .text("Min Cutoff (Hz)"),
+                )
+                .on_hover_text("Lower removes more jitter when moving slowly");
+                ui.add(
+                    egui::Slider::new(&mut filter.beta, 0.0..=50.0)
+                        .logarithmic(true)
+                        .text("Speed Response (beta)"),
+                )
+                .on_hover_text("Higher reduces lag when moving fast");
+            }
+        }
+    });
+}
+
+fn invert_row(ui: &mut egui::Ui, label: &str, invert: &mut AxisInvert) {
+    ui.label(label);
+    ui.checkbox(&mut invert.x, "");
+    ui.checkbox(&mut invert.y, "");
+    ui.end_row();
+}
+
+/// Sensitivity, axis gains and speed curve for one input source
+fn transfer_controls(ui: &mut egui::Ui, id: &str, transfer: &mut PointerTransfer) {
+    ui.indent(id, |ui| {
+        ui.add(
+            egui::Slider::new(&mut transfer.sensitivity, 0.1..=10.0)
+                .logarithmic(true)
+                .text("Sensitivity"),
+        );
+        ui.add(egui::Slider::new(&mut transfer.gain_x, 0.1..=5.0).text("Horizontal Gain"));
+        ui.add(egui::Slider::new(&mut transfer.gain_y, 0.1..=5.0).text("Vertical Gain"));
+
+        ui.horizontal(|ui| {
+            ui.label("Curve:");
+            for preset in TransferCurve::presets() {
+                let selected = transfer.curve.name() == preset.name();
+                if ui.selectable_label(selected, preset.name()).clicked() && !selected {
+                    transfer.curve = preset;
+                }
+            }
+        });
+        match &mut transfer.curve {
+            TransferCurve::Linear | TransferCurve::Piecewise { .. } => {}
+            TransferCurve::Power { exponent } => {
+                ui.add(egui::Slider::new(exponent, 1.0..=3.0).text("Exponent"));
+            }
+            TransferCurve::Sigmoid {
+                low,
+                high,
+                midpoint,
+                steepness,
+            } => {
+                ui.add(egui::Slider::new(low, 0.0..=5.0).text("Slow Gain"));
+                ui.add(egui::Slider::new(high, 0.0..=5.0).text("Fast Gain"));
+                ui.add(egui::Slider::new(midpoint, 0.0..=MAX_CURVE_SPEED).text("Midpoint Speed"));
+                ui.add(egui::Slider::new(steepness, 0.5..=20.0).text("Steepness"));
+            }
+        }
+        curve_editor(ui, ui.id().with(id), &mut transfer.curve);
+    });
+}
+
+/// Plot of gain against input speed; the points of a custom curve can be
+/// dragged, added by double-clicking and removed by right-clicking
+fn curve_editor(ui: &mut egui::Ui, id: egui::Id, curve: &mut TransferCurve) {
+    /// Highest gain a point can be dragged to
+    const MAX_GAIN: f64 = 10.0;
+    /// How close (pixels) the pointer must be to grab a point
+    const GRAB_RADIUS: f32 = 12.0;
+
+    let (rect, response) = ui.allocate_exact_size(
+        egui::vec2(ui.available_width(), 140.0),
+        egui::Sense::click_and_drag(),
+    );
+    let painter = ui.painter_at(rect);
+    let weak = ui.visuals().weak_text_color();
+    let accent = ui.visuals().selection.bg_fill;
+    painter.rect_stroke(rect, 0.0, ui.visuals().widgets.noninteractive.bg_stroke);
+
+    let samples: Vec<[f64; 2]> = (0..=100)
+        .map(|i| {
+            let speed = i as f64 / 100.0 * MAX_CURVE_SPEED;
+            [speed, curve.gain(speed)]
+        })
+        .collect();
+    let top_gain = samples.iter().map(|s| s[1]).fold(1.0, f64::max) * 1.2;
+    let inner = rect.shrink(6.0);
+    let to_screen = |[speed, gain]: [f64; 2]| {
+        egui::pos2(
+            inner.left() + (speed / MAX_CURVE_SPEED) as f32 * inner.width(),
+            inner.bottom() - (gain / top_gain) as f32 * inner.height(),
+        )
+    };
+    let from_screen = |pos: egui::Pos2| {
+        [
+            ((pos.x - inner.left()) / inner.width()) as f64 * MAX_CURVE_SPEED,
+            ((inner.bottom() - pos.y) / inner.height()) as f64 * top_gain,
+        ]
+    };
+
+    // Unity gain for reference
+    painter.line_segment(
+        [to_screen([0.0, 1.0]), to_screen([MAX_CURVE_SPEED, 1.0])],
+        egui::Stroke::new(1.0, weak),
+    );
+    painter.add(egui::Shape::line(
+        samples.into_iter().map(to_screen).collect(),
+        egui::Stroke::new(2.0, accent),
+    ));
+
+    if let TransferCurve::Piecewise { points } = curve {
+        for point in points.iter() {
+            painter.circle_filled(to_screen(*point), 4.0, accent);
+        }
+
+        let pointer = response.interact_pointer_pos();
+        let nearest = pointer.and_then(|pos| {
+            points
+                .iter()
+                .map(|p| to_screen(*p).distance(pos))
+                .enumerate()
+                .filter(|(_, distance)| *distance <= GRAB_RADIUS)
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(index, _)| index)
+        });
+        let dragged_id = id.with("dragged_point");
+
+        if response.drag_started() {
+            match nearest {
+                Some(index) => ui.data_mut(|d| d.insert_temp(dragged_id, index)),
+                None => ui.data_mut(|d| d.remove::<usize>(dragged_id)),
+            }
+        }
+        let dragged = ui.data(|d| d.get_temp::<usize>(dragged_id));
+        if let (true, Some(index), Some(pos)) = (response.dragged(), dragged, pointer) {
+            if index < points.len() {
+                let [speed, gain] = from_screen(pos);
+                let min_speed = index.checked_sub(1).map_or(0.0, |i| points[i][0]);
+                let max_speed = points.get(index + 1).map_or(MAX_CURVE_SPEED, |p| p[0]);
+                points[index] = [speed.clamp(min_speed, max_speed), gain.clamp(0.0, MAX_GAIN)];
+            }
+        }
+        if response.drag_stopped() {
+            ui.data_mut(|d| d.remove::<usize>(dragged_id));
+        }
+
+        if response.double_clicked() {
+            if let Some(pos) = pointer {
+                let [speed, gain] = from_screen(pos);
+                let point = [speed.clamp(0.0, MAX_CURVE_SPEED), gain.clamp(0.0, MAX_GAIN)];
+                let index = points.partition_point(|p| p[0] < point[0]);
+                points.insert(index, point);
+            }
+        }
+        if response.secondary_clicked() && points.len() > 2 {
+            if let Some(index) = nearest {
+                points.remove(index);
+            }
+        }
+    }
+
+    ui.label(
+        egui::RichText::new(match curve {
+            TransferCurve::Piecewise { .. } => {
+                "Gain vs. speed. Drag points; double-click adds one, right-click removes it."
+            }
+            _ => "Gain vs. speed; the flat line is a gain of 1.",
+        })
+        .italics()
+        .size(12.0),
+    );
 }